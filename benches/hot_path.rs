@@ -0,0 +1,317 @@
+//! Latency benchmarks for the PreToolUse hot path.
+//!
+//! The hook runs on every Bash tool call, so the full check (raw patterns,
+//! parse, gates, settings rules, task expansion) has a budget:
+//!
+//! | Case                          | Budget   |
+//! |-------------------------------|----------|
+//! | Simple command                | < 50 µs  |
+//! | Compound command (3-5 parts)  | < 250 µs |
+//! | mise task / package.json run  | < 500 µs |
+//! | Any of the above, loaded      | < 500 µs |
+//! | Hook process, end to end      | < 10 ms  |
+//!
+//! Pipelines of three or more stages cost ~120 µs in tree-sitter-bash's
+//! parse alone, which is why the compound budget is wider than the others.
+//!
+//! Run with `cargo bench --bench hot_path`.
+//!
+//! Most cases inject every dependency to measure the checks alone. The `loaded`
+//! group builds the engine the way the hook does, so each run also reads
+//! config.toml, settings.json, rule files, `.tool-gates.toml` and session
+//! approvals from fixtures under a temporary HOME.
+//!
+//! Both reuse what the first run compiled (built-in rules, regex sets, security
+//! reminder patterns), which a hook call never gets to: each one is a fresh
+//! process. The `process` group runs the hook binary against the same fixtures,
+//! so it covers process startup, stdin parsing and that cold first check. The
+//! cold check alone is ~1.7 ms against ~0.1 ms warm, and spawning any process
+//! (`process/true`) costs ~1 ms, so the end-to-end budget is in milliseconds.
+
+use chrono::Utc;
+use criterion::{BenchmarkId, Criterion, criterion_group, criterion_main};
+use std::fs;
+use std::hint::black_box;
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use tempfile::TempDir;
+use tool_gates::Engine;
+use tool_gates::config::{self, Config};
+use tool_gates::hint_tracker::MemorySessionStore;
+use tool_gates::models::Decision;
+use tool_gates::repo_policy::{self, TrustStore};
+use tool_gates::session_approvals::{self, SessionApproval, SessionApprovals};
+use tool_gates::settings::{Permissions, Settings};
+use tool_gates::user_rules::RuleSet;
+
+const SIMPLE: &[&str] = &[
+    "git status",
+    "ls -la src",
+    "cargo test --workspace",
+    "rm -rf /",
+    "npm install",
+];
+
+const COMPOUND: &[&str] = &[
+    "git status && git diff --stat && git log --oneline -5",
+    "cd /tmp && rm -rf build && mkdir build",
+    "rg -n TODO src | head -20 | wc -l",
+    "D=target; ls $D && du -sh $D > sizes.txt",
+    "timeout 60 cargo test 2>&1 | tail -30",
+];
+
+/// Engine with every dependency injected, so runs don't touch the disk
+fn engine(cwd: &str) -> Engine {
+    let settings = Settings {
+        permissions: Permissions {
+            allow: vec!["Bash(git log:*)".to_string()],
+            deny: vec!["Bash(rm -rf /:*)".to_string()],
+            ..Default::default()
+        },
+    };
+    Engine::builder()
+        .config(Config::default())
+        .settings(settings)
+        .repo_policy(None)
+        .rules(RuleSet::default())
+        .session_approvals(SessionApprovals::default())
+        .tools(|tool: &str| matches!(tool, "rg" | "bat" | "fd"))
+        .session_store(MemorySessionStore::new())
+        .cwd(cwd)
+        .build()
+}
+
+/// Project with a mise.toml and package.json for task expansion
+fn project() -> TempDir {
+    let dir = TempDir::new().unwrap();
+    write_task_files(dir.path());
+    dir
+}
+
+fn write_task_files(dir: &Path) {
+    fs::write(
+        dir.join("mise.toml"),
+        r#"
+[tasks.lint]
+run = "cargo clippy -- -D warnings"
+
+[tasks.check]
+depends = ["lint"]
+run = ["cargo fmt --check", "pnpm test"]
+"#,
+    )
+    .unwrap();
+    fs::write(
+        dir.join("package.json"),
+        r#"{"scripts": {"test": "vitest run", "build": "tsc && vite build"}}"#,
+    )
+    .unwrap();
+}
+
+/// Temporary HOME with user config, settings.json, a rule file and session
+/// approvals, holding a trusted project with its own settings and policy file.
+///
+/// Points HOME at the fixtures and clears the variables that would override it.
+fn loaded_home() -> (TempDir, String) {
+    let home = TempDir::new().unwrap();
+    // SAFETY: set before criterion starts measuring; benches run single-threaded
+    unsafe {
+        std::env::set_var("HOME", home.path());
+        std::env::remove_var("XDG_CONFIG_HOME");
+        std::env::remove_var("XDG_CACHE_HOME");
+        std::env::remove_var("CLAUDE_CONFIG_DIR");
+    }
+
+    let write = |path: &str, content: &str| {
+        let path = home.path().join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    };
+    write(".config/tool-gates/config.toml", "[features]\n");
+    write(
+        ".claude/settings.json",
+        r#"{"permissions": {"allow": ["Bash(git log:*)"], "deny": ["Bash(rm -rf /:*)"]}}"#,
+    );
+    write(
+        ".config/tool-gates/rules/deployctl.toml",
+        r#"
+[[programs]]
+name = "deployctl"
+
+[[programs.allow]]
+subcommand = "status"
+"#,
+    );
+
+    let project = home.path().join("project");
+    fs::create_dir_all(&project).unwrap();
+    write_task_files(&project);
+    let cwd = project.to_string_lossy().to_string();
+    write(
+        "project/.claude/settings.json",
+        r#"{"permissions": {"ask": ["Bash(npm install:*)"]}}"#,
+    );
+    write(
+        "project/.tool-gates.toml",
+        "allow = [\"./scripts/dev.sh:*\"]\nblock = [\"terraform destroy:*\"]\n",
+    );
+
+    let policy = project.join(repo_policy::POLICY_FILE);
+    let hash = repo_policy::hash_contents(&fs::read(&policy).unwrap());
+    let mut store = TrustStore::load();
+    store.trust(&policy, &hash);
+    store.save().unwrap();
+    session_approvals::add(SessionApproval {
+        pattern: "docker compose up:*".to_string(),
+        project: cwd.clone(),
+        session_id: Some("bench".to_string()),
+        expires_at: None,
+        created_at: Utc::now(),
+    })
+    .unwrap();
+    (home, cwd)
+}
+
+/// Engine built like the PreToolUse hook: everything loaded from disk
+fn loaded_engine(cwd: &str) -> Engine {
+    Engine::builder()
+        .config(config::load())
+        .cwd(cwd)
+        .permission_mode("default")
+        .session_id("bench")
+        .build()
+}
+
+fn bench_simple(c: &mut Criterion) {
+    let engine = engine("/project");
+    let mut group = c.benchmark_group("simple");
+    for command in SIMPLE {
+        group.bench_with_input(BenchmarkId::from_parameter(command), command, |b, cmd| {
+            b.iter(|| engine.check(black_box(cmd)));
+        });
+    }
+    group.finish();
+}
+
+fn bench_compound(c: &mut Criterion) {
+    let engine = engine("/project");
+    let mut group = c.benchmark_group("compound");
+    for command in COMPOUND {
+        group.bench_with_input(BenchmarkId::from_parameter(command), command, |b, cmd| {
+            b.iter(|| engine.check(black_box(cmd)));
+        });
+    }
+    group.finish();
+}
+
+fn bench_tasks(c: &mut Criterion) {
+    let dir = project();
+    let engine = engine(&dir.path().to_string_lossy());
+    let mut group = c.benchmark_group("tasks");
+    for command in ["mise run check", "mise lint", "npm run build", "pnpm test"] {
+        group.bench_with_input(BenchmarkId::from_parameter(command), command, |b, cmd| {
+            b.iter(|| engine.check(black_box(cmd)));
+        });
+    }
+    group.finish();
+}
+
+fn bench_loaded(c: &mut Criterion) {
+    let (_home, cwd) = loaded_home();
+    // The fixtures have to load, or the group measures the no-config path
+    let engine = loaded_engine(&cwd);
+    for (command, decision) in [
+        ("terraform destroy", Decision::Block),
+        ("docker compose up -d", Decision::Allow),
+        ("deployctl status", Decision::Allow),
+    ] {
+        assert_eq!(engine.check(command).decision, decision, "{command}");
+    }
+
+    let mut group = c.benchmark_group("loaded");
+    for command in [
+        "git status",
+        "cd /tmp && rm -rf build && mkdir build",
+        "docker compose up -d",
+        "terraform destroy",
+        "mise run check",
+    ] {
+        group.bench_with_input(BenchmarkId::from_parameter(command), command, |b, cmd| {
+            b.iter(|| loaded_engine(&cwd).check(black_box(cmd)));
+        });
+    }
+    group.finish();
+}
+
+/// Run the PreToolUse hook binary on a Bash command, returning its stdout
+fn run_hook(cwd: &str, command: &str) -> String {
+    let input = serde_json::json!({
+        "hook_event_name": "PreToolUse",
+        "session_id": "bench",
+        "cwd": cwd,
+        "permission_mode": "default",
+        "tool_name": "Bash",
+        "tool_input": {"command": command},
+    });
+    let mut child = Command::new(env!("CARGO_BIN_EXE_tool-gates"))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(input.to_string().as_bytes())
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success());
+    String::from_utf8(output.stdout).unwrap()
+}
+
+fn bench_process(c: &mut Criterion) {
+    let (_home, cwd) = loaded_home();
+    for (command, decision) in [
+        ("git status", "allow"),
+        ("terraform destroy", "deny"),
+        ("mise run check", "allow"),
+    ] {
+        let output = run_hook(&cwd, command);
+        assert!(
+            output.contains(&format!(r#""permissionDecision":"{decision}""#)),
+            "{command}: {output}"
+        );
+    }
+
+    let mut group = c.benchmark_group("process");
+    group.sample_size(20);
+    group.bench_function("true", |b| {
+        b.iter(|| Command::new("true").status().unwrap());
+    });
+    for command in ["git status", "terraform destroy", "mise run check"] {
+        group.bench_with_input(BenchmarkId::from_parameter(command), command, |b, cmd| {
+            b.iter(|| run_hook(&cwd, black_box(cmd)));
+        });
+    }
+    group.finish();
+}
+
+fn bench_gates_only(c: &mut Criterion) {
+    let engine = engine("/project");
+    c.bench_function("gates_only/compound", |b| {
+        b.iter(|| engine.check_gates(black_box(COMPOUND[0])));
+    });
+}
+
+criterion_group!(
+    benches,
+    bench_simple,
+    bench_compound,
+    bench_tasks,
+    bench_loaded,
+    bench_process,
+    bench_gates_only
+);
+criterion_main!(benches);
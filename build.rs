@@ -1,7 +1,7 @@
 //! Build script for tool-gates.
 //!
 //! Reads all rules/*.toml files and generates:
-//! - src/generated/rules.rs - Rust code for declarative gates
+//! - src/generated/rules.rs - Rust code for declarative gates and wrapper specs
 //! - src/generated/toml_policy.rs - Gemini CLI TOML policy string
 
 use serde::Deserialize;
@@ -129,6 +129,35 @@ fn validate_rule_file(path: &Path, rules: &RuleFile) {
             );
         }
     }
+
+    // Validate wrappers
+    for (i, wrapper) in rules.wrappers.iter().enumerate() {
+        if wrapper.program.trim().is_empty() {
+            panic!("{}: wrappers[{}] has empty program name", file_name, i);
+        }
+        for flag in wrapper
+            .flags_with_value
+            .iter()
+            .chain(&wrapper.command_flags)
+            .chain(&wrapper.process_flags)
+            .chain(&wrapper.output_flags)
+        {
+            if !flag.starts_with('-') {
+                panic!(
+                    "{}: {}: wrapper flag '{}' must start with '-'",
+                    file_name, wrapper.program, flag
+                );
+            }
+        }
+        for flag in &wrapper.output_flags {
+            if !wrapper.flags_with_value.contains(flag) {
+                panic!(
+                    "{}: {}: output flag '{}' must also be in flags_with_value",
+                    file_name, wrapper.program, flag
+                );
+            }
+        }
+    }
 }
 
 fn validate_program_rules(path: &Path, program: &ProgramRules) {
@@ -296,6 +325,8 @@ struct RuleFile {
     conditional_allow: Vec<ConditionalRule>,
     #[serde(default)]
     custom_handlers: Vec<CustomHandler>,
+    #[serde(default)]
+    wrappers: Vec<WrapperRule>,
 }
 
 #[derive(Debug, Default, Deserialize)]
@@ -380,8 +411,8 @@ struct AskRule {
     #[serde(default)]
     action_prefix: Option<String>,
     reason: String,
+    /// High-risk ask; listed in WARN_ASK_REASONS (Gemini export inherits default ask)
     #[serde(default)]
-    #[allow(dead_code)] // Used in TOML but not in Gemini export (inherits default ask)
     warn: bool,
     #[serde(default)]
     if_flags: Vec<String>,
@@ -471,6 +502,43 @@ struct CustomHandler {
     description: Option<String>,
 }
 
+/// A command that runs another command (`timeout 60 cargo test`, `env FOO=1 make`).
+/// The inner command is re-dispatched through all gates.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+#[allow(dead_code)]
+struct WrapperRule {
+    program: String,
+    #[serde(default)]
+    aliases: Vec<String>,
+    /// Flags that consume the following argument (`-s KILL`, `-n 10`)
+    #[serde(default)]
+    flags_with_value: Vec<String>,
+    /// Positional arguments consumed before the inner command (`timeout 60 ...`)
+    #[serde(default)]
+    positional: usize,
+    /// Skip leading `NAME=value` arguments (`env FOO=1 cmd`)
+    #[serde(default)]
+    skip_assignments: bool,
+    /// Remaining arguments are joined and run as a shell string (`watch 'ls | wc'`)
+    #[serde(default)]
+    shell_string: bool,
+    /// Flags whose value is itself a command line (`env -S 'cmd args'`)
+    #[serde(default)]
+    command_flags: Vec<String>,
+    /// Flags that point the wrapper at running processes instead of a command (`ionice -p PID`)
+    #[serde(default)]
+    process_flags: Vec<String>,
+    /// Flags whose value is a file the wrapper writes (`time -o FILE`)
+    #[serde(default)]
+    output_flags: Vec<String>,
+    /// With no inner command, leave the wrapper to the other gates (`env` alone prints the environment)
+    #[serde(default)]
+    bare_unhandled: bool,
+    #[serde(default)]
+    description: Option<String>,
+}
+
 impl AllowRule {
     fn subcommand_parts(&self) -> Vec<&str> {
         if let Some(ref s) = self.subcommand {
@@ -520,21 +588,22 @@ fn generate_rust_code(rule_files: &[(String, RuleFile)]) -> String {
 
     output.push_str("#![allow(dead_code)]\n");
     output.push_str("#![allow(clippy::too_many_lines)]\n");
-    output.push_str("#![allow(clippy::nonminimal_bool)]\n\n");
+    output.push_str("#![allow(clippy::nonminimal_bool)]\n");
+    output.push_str("#![allow(clippy::type_complexity)]\n\n");
 
     output.push_str("use std::collections::{HashMap, HashSet};\n");
     output.push_str("use std::sync::LazyLock;\n");
     output.push_str("use crate::models::{CommandInfo, GateResult};\n\n");
 
-    // Collect all safe commands
-    let mut all_safe_commands: Vec<&str> = Vec::new();
-    for (_, rules) in rule_files {
-        for cmd in &rules.safe_commands {
-            all_safe_commands.push(cmd);
+    // Collect all safe commands with the file and index they're declared at
+    let mut all_safe_commands: Vec<(&str, &str, usize)> = Vec::new();
+    for (name, rules) in rule_files {
+        for (index, cmd) in rules.safe_commands.iter().enumerate() {
+            all_safe_commands.push((cmd, name, index));
         }
     }
     all_safe_commands.sort();
-    all_safe_commands.dedup();
+    all_safe_commands.dedup_by(|a, b| a.0 == b.0);
 
     if !all_safe_commands.is_empty() {
         output.push_str(&generate_safe_commands(&all_safe_commands));
@@ -542,10 +611,10 @@ fn generate_rust_code(rule_files: &[(String, RuleFile)]) -> String {
     }
 
     // Collect all conditional allows
-    let mut all_conditionals: Vec<&ConditionalRule> = Vec::new();
-    for (_, rules) in rule_files {
-        for cond in &rules.conditional_allow {
-            all_conditionals.push(cond);
+    let mut all_conditionals: Vec<(&str, usize, &ConditionalRule)> = Vec::new();
+    for (name, rules) in rule_files {
+        for (index, cond) in rules.conditional_allow.iter().enumerate() {
+            all_conditionals.push((name, index, cond));
         }
     }
 
@@ -562,6 +631,14 @@ fn generate_rust_code(rule_files: &[(String, RuleFile)]) -> String {
         }
     }
 
+    // Reasons of high-risk ask rules
+    output.push_str(&generate_warn_reasons(rule_files));
+    output.push('\n');
+
+    // Generate wrapper specs
+    output.push_str(&generate_wrappers(rule_files));
+    output.push('\n');
+
     // Generate master check function
     output.push_str(&generate_master_check(rule_files));
 
@@ -574,25 +651,73 @@ fn generate_rust_code(rule_files: &[(String, RuleFile)]) -> String {
     output
 }
 
-fn generate_safe_commands(commands: &[&str]) -> String {
+fn generate_warn_reasons(rule_files: &[(String, RuleFile)]) -> String {
+    let mut reasons: Vec<&str> = rule_files
+        .iter()
+        .flat_map(|(_, rules)| &rules.programs)
+        .flat_map(|program| &program.ask)
+        .filter(|ask| ask.warn)
+        .map(|ask| ask.reason.as_str())
+        .collect();
+    reasons.sort();
+    reasons.dedup();
+
+    let mut output = String::new();
+    output.push_str("/// Reasons returned by ask rules marked `warn = true`\n");
+    output.push_str("pub static WARN_ASK_REASONS: &[&str] = &[\n");
+    for reason in reasons {
+        output.push_str(&format!("    \"{}\",\n", escape_rust_string(reason)));
+    }
+    output.push_str("];\n");
+    output
+}
+
+fn generate_safe_commands(commands: &[(&str, &str, usize)]) -> String {
     let mut output = String::new();
 
     output.push_str("/// Safe commands that are always allowed\n");
     output.push_str("pub static SAFE_COMMANDS: LazyLock<HashSet<&str>> = LazyLock::new(|| {\n");
     output.push_str("    [\n");
 
-    for cmd in commands {
+    for (cmd, _, _) in commands {
         output.push_str(&format!("        \"{}\",\n", escape_rust_string(cmd)));
     }
 
     output.push_str("    ].into_iter().collect()\n");
     output.push_str("});\n\n");
 
+    output.push_str("/// Where each safe command is declared (file, index into safe_commands)\n");
+    output.push_str(
+        "pub static SAFE_COMMAND_SOURCES: LazyLock<HashMap<&str, (&str, usize)>> = LazyLock::new(|| {\n",
+    );
+    output.push_str("    [\n");
+
+    for (cmd, file, index) in commands {
+        output.push_str(&format!(
+            "        (\"{}\", (\"{}\", {})),\n",
+            escape_rust_string(cmd),
+            file,
+            index
+        ));
+    }
+
+    output.push_str("    ].into_iter().collect()\n");
+    output.push_str("});\n\n");
+
     output.push_str("/// Check if a command is in the safe commands list\n");
     output.push_str("pub fn check_safe_command(cmd: &CommandInfo) -> Option<GateResult> {\n");
     output.push_str("    // Strip path prefix to handle /usr/bin/cat etc.\n");
     output.push_str("    let program = cmd.program.rsplit('/').next().unwrap_or(&cmd.program);\n");
     output.push_str("    if SAFE_COMMANDS.contains(program) {\n");
+    output.push_str("        if crate::trace::is_active() {\n");
+    output.push_str(
+        "            if let Some((name, &(file, index))) = SAFE_COMMAND_SOURCES.get_key_value(program) {\n",
+    );
+    output.push_str(
+        "                crate::trace::toml_rule(file, name, \"safe_commands\", index);\n",
+    );
+    output.push_str("            }\n");
+    output.push_str("        }\n");
     output.push_str("        Some(GateResult::allow())\n");
     output.push_str("    } else {\n");
     output.push_str("        None\n");
@@ -602,7 +727,7 @@ fn generate_safe_commands(commands: &[&str]) -> String {
     output
 }
 
-fn generate_conditional_rules(rules: &[&ConditionalRule]) -> String {
+fn generate_conditional_rules(rules: &[(&str, usize, &ConditionalRule)]) -> String {
     let mut output = String::new();
 
     output.push_str("#[derive(Debug, Clone, Copy, PartialEq, Eq)]\n");
@@ -612,14 +737,15 @@ fn generate_conditional_rules(rules: &[&ConditionalRule]) -> String {
     output.push_str("    Block,\n");
     output.push_str("}\n\n");
 
-    output
-        .push_str("/// Conditional allow rules (program -> (flags that prevent allow, action))\n");
     output.push_str(
-        "pub static CONDITIONAL_ALLOW: LazyLock<HashMap<&str, (&[&str], ConditionalAction)>> = LazyLock::new(|| {\n",
+        "/// Conditional allow rules (program -> (flags that prevent allow, action, file, rule index))\n",
+    );
+    output.push_str(
+        "pub static CONDITIONAL_ALLOW: LazyLock<HashMap<&str, (&[&str], ConditionalAction, &str, usize)>> = LazyLock::new(|| {\n",
     );
     output.push_str("    [\n");
 
-    for rule in rules {
+    for (file, index, rule) in rules {
         let flags: Vec<String> = rule
             .unless_flags
             .iter()
@@ -631,17 +757,21 @@ fn generate_conditional_rules(rules: &[&ConditionalRule]) -> String {
             OnFlagAction::Block => "ConditionalAction::Block",
         };
         output.push_str(&format!(
-            "        (\"{}\", (&[{}] as &[&str], {})),\n",
+            "        (\"{}\", (&[{}] as &[&str], {}, \"{}\", {})),\n",
             escape_rust_string(&rule.program),
             flags.join(", "),
-            action
+            action,
+            file,
+            index
         ));
         for alias in &rule.aliases {
             output.push_str(&format!(
-                "        (\"{}\", (&[{}] as &[&str], {})),\n",
+                "        (\"{}\", (&[{}] as &[&str], {}, \"{}\", {})),\n",
                 escape_rust_string(alias),
                 flags.join(", "),
-                action
+                action,
+                file,
+                index
             ));
         }
     }
@@ -653,10 +783,16 @@ fn generate_conditional_rules(rules: &[&ConditionalRule]) -> String {
     output.push_str("pub fn check_conditional_allow(cmd: &CommandInfo) -> Option<GateResult> {\n");
     output.push_str("    // Strip path prefix to handle /usr/bin/sed etc.\n");
     output.push_str("    let program = cmd.program.rsplit('/').next().unwrap_or(&cmd.program);\n");
-    output.push_str("    if let Some((flags, action)) = CONDITIONAL_ALLOW.get(program) {\n");
+    output.push_str(
+        "    if let Some((name, &(flags, action, file, index))) = CONDITIONAL_ALLOW.get_key_value(program) {\n",
+    );
     output.push_str(
         "        let has_flag = cmd.args.iter().any(|arg| flags.contains(&arg.as_str()));\n",
     );
+    output.push_str("        if has_flag && action == ConditionalAction::Skip {\n");
+    output.push_str("            return None;\n");
+    output.push_str("        }\n");
+    output.push_str("        crate::trace::toml_rule(file, name, \"conditional_allow\", index);\n");
     output.push_str("        if has_flag {\n");
     output.push_str("            match action {\n");
     output.push_str("                ConditionalAction::Skip => None,\n");
@@ -686,11 +822,23 @@ fn generate_program_rules(file_name: &str, program: &ProgramRules) -> String {
         file_name
     ));
 
-    // Collect simple allows (no conditions, no reason)
-    let simple_allows: Vec<String> = program
+    // Records which rule matched for `tool-gates explain`
+    let trace = |section: &str, index: &str| {
+        format!(
+            "crate::trace::toml_rule(\"{}\", \"{}\", \"{}\", {});",
+            file_name,
+            escape_rust_string(name),
+            section,
+            index
+        )
+    };
+
+    // Collect simple allows (no conditions, no reason), with their rule index
+    let simple_allows: Vec<(String, usize)> = program
         .allow
         .iter()
-        .filter(|r| {
+        .enumerate()
+        .filter(|(_, r)| {
             r.subcommand_prefix.is_none()
                 && r.action_prefix.is_none()
                 && r.unless_flags.is_empty()
@@ -698,26 +846,27 @@ fn generate_program_rules(file_name: &str, program: &ProgramRules) -> String {
                 && r.if_flags_any.is_empty()
                 && r.reason.is_none() // Allows with reasons go to complex path
         })
-        .map(|r| r.subcommand_parts().join(" "))
-        .filter(|s| !s.is_empty())
+        .map(|(i, r)| (r.subcommand_parts().join(" "), i))
+        .filter(|(s, _)| !s.is_empty())
         .collect();
 
     // Collect simple asks (with subcommand)
-    let simple_asks: Vec<(String, String)> = program
+    let simple_asks: Vec<(String, String, usize)> = program
         .ask
         .iter()
-        .filter(|r| {
+        .enumerate()
+        .filter(|(_, r)| {
             r.subcommand_prefix.is_none()
                 && r.action_prefix.is_none()
                 && r.if_flags.is_empty()
                 && r.if_flags_any.is_empty()
         })
-        .map(|r| (r.subcommand_parts().join(" "), r.reason.clone()))
-        .filter(|(s, _)| !s.is_empty())
+        .map(|(i, r)| (r.subcommand_parts().join(" "), r.reason.clone(), i))
+        .filter(|(s, _, _)| !s.is_empty())
         .collect();
 
     // Find bare ask rule (matches any invocation of the program)
-    let bare_ask: Option<&AskRule> = program.ask.iter().find(|r| {
+    let bare_ask: Option<(usize, &AskRule)> = program.ask.iter().enumerate().find(|(_, r)| {
         r.subcommand_parts().is_empty()
             && r.subcommand_prefix.is_none()
             && r.action_prefix.is_none()
@@ -725,37 +874,44 @@ fn generate_program_rules(file_name: &str, program: &ProgramRules) -> String {
     });
 
     // Collect simple blocks
-    let simple_blocks: Vec<(String, String)> = program
+    let simple_blocks: Vec<(String, String, usize)> = program
         .block
         .iter()
-        .filter(|r| r.if_args_contain.is_empty())
-        .map(|r| (r.subcommand_parts().join(" "), r.reason.clone()))
-        .filter(|(s, _)| !s.is_empty())
+        .enumerate()
+        .filter(|(_, r)| r.if_args_contain.is_empty())
+        .map(|(i, r)| (r.subcommand_parts().join(" "), r.reason.clone(), i))
+        .filter(|(s, _, _)| !s.is_empty())
         .collect();
 
     // Collect complex blocks (with if_args_contain or subcommand_prefix)
-    let complex_blocks: Vec<&BlockRule> = program
+    let complex_blocks: Vec<(usize, &BlockRule)> = program
         .block
         .iter()
-        .filter(|r| !r.if_args_contain.is_empty() || r.subcommand_prefix.is_some())
+        .enumerate()
+        .filter(|(_, r)| !r.if_args_contain.is_empty() || r.subcommand_prefix.is_some())
         .collect();
 
     // Find bare block rule (matches any invocation of the program)
-    let bare_block: Option<&BlockRule> = program.block.iter().find(|r| {
-        r.subcommand_parts().is_empty()
-            && r.subcommand_prefix.is_none()
-            && r.if_args_contain.is_empty()
-    });
+    let bare_block: Option<(usize, &BlockRule)> =
+        program.block.iter().enumerate().find(|(_, r)| {
+            r.subcommand_parts().is_empty()
+                && r.subcommand_prefix.is_none()
+                && r.if_args_contain.is_empty()
+        });
 
     // Generate statics
     if !simple_allows.is_empty() {
         output.push_str(&format!(
-            "pub static {}_ALLOW: LazyLock<HashSet<&str>> = LazyLock::new(|| {{\n",
+            "pub static {}_ALLOW: LazyLock<HashMap<&str, usize>> = LazyLock::new(|| {{\n",
             name_upper
         ));
         output.push_str("    [\n");
-        for subcmd in &simple_allows {
-            output.push_str(&format!("        \"{}\",\n", escape_rust_string(subcmd)));
+        for (subcmd, index) in &simple_allows {
+            output.push_str(&format!(
+                "        (\"{}\", {}),\n",
+                escape_rust_string(subcmd),
+                index
+            ));
         }
         output.push_str("    ].into_iter().collect()\n");
         output.push_str("});\n\n");
@@ -763,15 +919,16 @@ fn generate_program_rules(file_name: &str, program: &ProgramRules) -> String {
 
     if !simple_asks.is_empty() {
         output.push_str(&format!(
-            "pub static {}_ASK: LazyLock<HashMap<&str, &str>> = LazyLock::new(|| {{\n",
+            "pub static {}_ASK: LazyLock<HashMap<&str, (&str, usize)>> = LazyLock::new(|| {{\n",
             name_upper
         ));
         output.push_str("    [\n");
-        for (subcmd, reason) in &simple_asks {
+        for (subcmd, reason, index) in &simple_asks {
             output.push_str(&format!(
-                "        (\"{}\", \"{}\"),\n",
+                "        (\"{}\", (\"{}\", {})),\n",
                 escape_rust_string(subcmd),
-                escape_rust_string(reason)
+                escape_rust_string(reason),
+                index
             ));
         }
         output.push_str("    ].into_iter().collect()\n");
@@ -780,15 +937,16 @@ fn generate_program_rules(file_name: &str, program: &ProgramRules) -> String {
 
     if !simple_blocks.is_empty() {
         output.push_str(&format!(
-            "pub static {}_BLOCK: LazyLock<HashMap<&str, &str>> = LazyLock::new(|| {{\n",
+            "pub static {}_BLOCK: LazyLock<HashMap<&str, (&str, usize)>> = LazyLock::new(|| {{\n",
             name_upper
         ));
         output.push_str("    [\n");
-        for (subcmd, reason) in &simple_blocks {
+        for (subcmd, reason, index) in &simple_blocks {
             output.push_str(&format!(
-                "        (\"{}\", \"{}\"),\n",
+                "        (\"{}\", (\"{}\", {})),\n",
                 escape_rust_string(subcmd),
-                escape_rust_string(reason)
+                escape_rust_string(reason),
+                index
             ));
         }
         output.push_str("    ].into_iter().collect()\n");
@@ -817,7 +975,7 @@ fn generate_program_rules(file_name: &str, program: &ProgramRules) -> String {
     // Check allow_if_flags first
     if !program.allow_if_flags.is_empty() {
         output.push_str("    // Check allow_if_flags (e.g., --dry-run)\n");
-        for flag_override in &program.allow_if_flags {
+        for (index, flag_override) in program.allow_if_flags.iter().enumerate() {
             let flags: Vec<String> = flag_override
                 .flags_any
                 .iter()
@@ -827,6 +985,10 @@ fn generate_program_rules(file_name: &str, program: &ProgramRules) -> String {
                 "    if cmd.args.iter().any(|a| [{}].contains(&a.as_str())) {{\n",
                 flags.join(", ")
             ));
+            output.push_str(&format!(
+                "        {}\n",
+                trace("allow_if_flags", &index.to_string())
+            ));
             output.push_str("        return Some(GateResult::allow());\n");
             output.push_str("    }\n");
         }
@@ -834,11 +996,12 @@ fn generate_program_rules(file_name: &str, program: &ProgramRules) -> String {
     }
 
     // Handle bare block rule (blocks any invocation of the program)
-    if let Some(block) = bare_block {
+    if let Some((index, block)) = bare_block {
         output.push_str(&format!(
             "    // Bare block rule - any {} invocation is blocked\n",
             name
         ));
+        output.push_str(&format!("    {}\n", trace("block", &index.to_string())));
         output.push_str(&format!(
             "    Some(GateResult::block(\"{}: {}\"))\n",
             name,
@@ -865,9 +1028,10 @@ fn generate_program_rules(file_name: &str, program: &ProgramRules) -> String {
     // Check blocks first (highest priority)
     if !simple_blocks.is_empty() {
         output.push_str(&format!(
-            "    if let Some(reason) = {}_BLOCK.get(subcmd.as_str()) {{\n",
+            "    if let Some(&(reason, rule)) = {}_BLOCK.get(subcmd.as_str()) {{\n",
             name_upper
         ));
+        output.push_str(&format!("        {}\n", trace("block", "rule")));
         output.push_str(&format!(
             "        return Some(GateResult::block(format!(\"{}: {{}}\", reason)));\n",
             name
@@ -878,8 +1042,9 @@ fn generate_program_rules(file_name: &str, program: &ProgramRules) -> String {
     // Check complex blocks (with if_args_contain or subcommand_prefix)
     if !complex_blocks.is_empty() {
         output.push_str("    // Check conditional block rules\n");
-        for block in &complex_blocks {
+        for (index, block) in &complex_blocks {
             let parts = block.subcommand_parts();
+            let trace_call = trace("block", &index.to_string());
 
             // Handle subcommand_prefix blocks
             if let Some(ref prefix) = block.subcommand_prefix {
@@ -904,6 +1069,7 @@ fn generate_program_rules(file_name: &str, program: &ProgramRules) -> String {
                         escape_rust_string(prefix)
                     ));
                 }
+                output.push_str(&format!("        {}\n", trace_call));
                 output.push_str(&format!(
                     "        return Some(GateResult::block(\"{}: {}\"));\n",
                     name,
@@ -933,6 +1099,7 @@ fn generate_program_rules(file_name: &str, program: &ProgramRules) -> String {
                 subcmd_check,
                 args_checks.join(" || ")
             ));
+            output.push_str(&format!("        {}\n", trace_call));
             output.push_str(&format!(
                 "        return Some(GateResult::block(\"{}: {}\"));\n",
                 name,
@@ -944,17 +1111,19 @@ fn generate_program_rules(file_name: &str, program: &ProgramRules) -> String {
     }
 
     // Check complex asks (with flags or prefixes)
-    let complex_asks: Vec<&AskRule> = program
+    let complex_asks: Vec<(usize, &AskRule)> = program
         .ask
         .iter()
-        .filter(|r| {
+        .enumerate()
+        .filter(|(_, r)| {
             !r.if_flags_any.is_empty() || r.subcommand_prefix.is_some() || r.action_prefix.is_some()
         })
         .collect();
 
     if !complex_asks.is_empty() {
         output.push_str("    // Check ask rules with flag/prefix conditions\n");
-        for ask in complex_asks {
+        for (index, ask) in complex_asks {
+            let trace_call = trace("ask", &index.to_string());
             if !ask.if_flags_any.is_empty() {
                 let parts = ask.subcommand_parts();
                 let subcmd_check = if parts.is_empty() {
@@ -972,6 +1141,7 @@ fn generate_program_rules(file_name: &str, program: &ProgramRules) -> String {
                     subcmd_check,
                     flags.join(", ")
                 ));
+                output.push_str(&format!("        {}\n", trace_call));
                 output.push_str(&format!(
                     "        return Some(GateResult::ask(\"{}\"));\n",
                     escape_rust_string(&ask.reason)
@@ -1002,6 +1172,7 @@ fn generate_program_rules(file_name: &str, program: &ProgramRules) -> String {
                         escape_rust_string(prefix)
                     ));
                 }
+                output.push_str(&format!("        {}\n", trace_call));
                 output.push_str(&format!(
                     "        return Some(GateResult::ask(\"{}: {}\"));\n",
                     name,
@@ -1016,6 +1187,7 @@ fn generate_program_rules(file_name: &str, program: &ProgramRules) -> String {
                     "    if cmd.args.get(1).is_some_and(|a| a.starts_with(\"{}\")) {{\n",
                     escape_rust_string(prefix)
                 ));
+                output.push_str(&format!("        {}\n", trace_call));
                 output.push_str(&format!(
                     "        return Some(GateResult::ask(\"{}: {}\"));\n",
                     name,
@@ -1030,18 +1202,20 @@ fn generate_program_rules(file_name: &str, program: &ProgramRules) -> String {
     // Check simple allows
     if !simple_allows.is_empty() {
         output.push_str(&format!(
-            "    if {}_ALLOW.contains(subcmd.as_str()) || {}_ALLOW.contains(subcmd_single) {{\n",
+            "    if let Some(&rule) = {}_ALLOW.get(subcmd.as_str()).or_else(|| {}_ALLOW.get(subcmd_single)) {{\n",
             name_upper, name_upper
         ));
+        output.push_str(&format!("        {}\n", trace("allow", "rule")));
         output.push_str("        return Some(GateResult::allow());\n");
         output.push_str("    }\n\n");
     }
 
     // Check complex allows (with conditions)
-    let complex_allows: Vec<&AllowRule> = program
+    let complex_allows: Vec<(usize, &AllowRule)> = program
         .allow
         .iter()
-        .filter(|r| {
+        .enumerate()
+        .filter(|(_, r)| {
             r.subcommand_prefix.is_some()
                 || r.action_prefix.is_some()
                 || !r.unless_flags.is_empty()
@@ -1052,10 +1226,14 @@ fn generate_program_rules(file_name: &str, program: &ProgramRules) -> String {
 
     if !complex_allows.is_empty() {
         output.push_str("    // Check conditional allow rules\n");
-        for allow in complex_allows {
+        for (index, allow) in complex_allows {
             let parts = allow.subcommand_parts();
 
-            let allow_call = generate_allow_call(&allow.reason);
+            let allow_call = format!(
+                "{{ {} {} }}",
+                trace("allow", &index.to_string()),
+                generate_allow_call(&allow.reason)
+            );
 
             if let Some(ref prefix) = allow.subcommand_prefix {
                 if parts.is_empty() {
@@ -1152,9 +1330,10 @@ fn generate_program_rules(file_name: &str, program: &ProgramRules) -> String {
     // Check simple asks
     if !simple_asks.is_empty() {
         output.push_str(&format!(
-            "    if let Some(reason) = {}_ASK.get(subcmd.as_str()).or_else(|| {}_ASK.get(subcmd_single)) {{\n",
+            "    if let Some(&(reason, rule)) = {}_ASK.get(subcmd.as_str()).or_else(|| {}_ASK.get(subcmd_single)) {{\n",
             name_upper, name_upper
         ));
+        output.push_str(&format!("        {}\n", trace("ask", "rule")));
         output.push_str(&format!(
             "        return Some(GateResult::ask(format!(\"{}: {{}}\", reason)));\n",
             name
@@ -1164,16 +1343,17 @@ fn generate_program_rules(file_name: &str, program: &ProgramRules) -> String {
 
     // Handle API rules
     if let Some(ref api) = program.api_rules {
-        output.push_str(&generate_api_rules(name, api));
+        output.push_str(&generate_api_rules(name, api, &trace("api_rules", "0")));
         output.push('\n');
     }
 
     // Handle bare ask rule (matches any invocation of the program)
-    if let Some(ask) = bare_ask {
+    if let Some((index, ask)) = bare_ask {
         output.push_str(&format!(
             "    // Bare ask rule - any {} invocation asks\n",
             name
         ));
+        output.push_str(&format!("    {}\n", trace("ask", &index.to_string())));
         output.push_str(&format!(
             "    Some(GateResult::ask(\"{}: {}\"))\n",
             name,
@@ -1181,6 +1361,9 @@ fn generate_program_rules(file_name: &str, program: &ProgramRules) -> String {
         ));
     } else {
         // Handle unknown action (bare_block already returned early above)
+        if !matches!(program.unknown_action, UnknownAction::Skip) {
+            output.push_str(&format!("    {}\n", trace("unknown_action", "0")));
+        }
         match program.unknown_action {
             UnknownAction::Ask => {
                 output.push_str(&format!(
@@ -1238,7 +1421,7 @@ fn generate_subcommand_match(parts: &[&str]) -> String {
     }
 }
 
-fn generate_api_rules(name: &str, api: &ApiRules) -> String {
+fn generate_api_rules(name: &str, api: &ApiRules, trace_call: &str) -> String {
     let mut output = String::new();
 
     output.push_str(&format!(
@@ -1335,8 +1518,10 @@ fn generate_api_rules(name: &str, api: &ApiRules) -> String {
         "        if [{}].contains(&method.as_str()) {{\n",
         safe_methods.join(", ")
     ));
+    output.push_str(&format!("            {}\n", trace_call));
     output.push_str("            return Some(GateResult::allow());\n");
     output.push_str("        }\n");
+    output.push_str(&format!("        {}\n", trace_call));
     output.push_str(&format!(
         "        return Some(GateResult::ask(format!(\"{} {}: {{}} request\", method)));\n",
         name, api.trigger
@@ -1346,6 +1531,70 @@ fn generate_api_rules(name: &str, api: &ApiRules) -> String {
     output
 }
 
+fn generate_wrappers(rule_files: &[(String, RuleFile)]) -> String {
+    let mut output = String::new();
+
+    output.push_str(
+        "/// How a wrapper command (timeout, env, nice, ...) locates its inner command\n",
+    );
+    output.push_str("#[derive(Debug)]\n");
+    output.push_str("pub struct WrapperSpec {\n");
+    output.push_str("    pub program: &'static str,\n");
+    output.push_str("    pub flags_with_value: &'static [&'static str],\n");
+    output.push_str("    pub positional: usize,\n");
+    output.push_str("    pub skip_assignments: bool,\n");
+    output.push_str("    pub shell_string: bool,\n");
+    output.push_str("    pub command_flags: &'static [&'static str],\n");
+    output.push_str("    pub process_flags: &'static [&'static str],\n");
+    output.push_str("    pub output_flags: &'static [&'static str],\n");
+    output.push_str("    pub bare_unhandled: bool,\n");
+    output.push_str("}\n\n");
+
+    let quote_list = |items: &[String]| -> String {
+        items
+            .iter()
+            .map(|f| format!("\"{}\"", escape_rust_string(f)))
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+
+    output.push_str("/// Wrapper commands (program/alias -> spec)\n");
+    output.push_str(
+        "pub static WRAPPERS: LazyLock<HashMap<&str, WrapperSpec>> = LazyLock::new(|| {\n",
+    );
+    output.push_str("    [\n");
+    for (_, rules) in rule_files {
+        for wrapper in &rules.wrappers {
+            for name in std::iter::once(&wrapper.program).chain(&wrapper.aliases) {
+                output.push_str(&format!(
+                    "        (\"{}\", WrapperSpec {{ program: \"{}\", flags_with_value: &[{}], positional: {}, skip_assignments: {}, shell_string: {}, command_flags: &[{}], process_flags: &[{}], output_flags: &[{}], bare_unhandled: {} }}),\n",
+                    escape_rust_string(name),
+                    escape_rust_string(&wrapper.program),
+                    quote_list(&wrapper.flags_with_value),
+                    wrapper.positional,
+                    wrapper.skip_assignments,
+                    wrapper.shell_string,
+                    quote_list(&wrapper.command_flags),
+                    quote_list(&wrapper.process_flags),
+                    quote_list(&wrapper.output_flags),
+                    wrapper.bare_unhandled,
+                ));
+            }
+        }
+    }
+    output.push_str("    ].into_iter().collect()\n");
+    output.push_str("});\n\n");
+
+    output.push_str("/// Look up the wrapper spec for a program\n");
+    output.push_str("pub fn wrapper_spec(cmd: &CommandInfo) -> Option<&'static WrapperSpec> {\n");
+    output.push_str("    // Strip path prefix to handle /usr/bin/env etc.\n");
+    output.push_str("    let program = cmd.program.rsplit('/').next().unwrap_or(&cmd.program);\n");
+    output.push_str("    WRAPPERS.get(program)\n");
+    output.push_str("}\n");
+
+    output
+}
+
 fn generate_master_check(rule_files: &[(String, RuleFile)]) -> String {
     let mut output = String::new();
 
@@ -1484,6 +1733,21 @@ fn generate_gate_functions(rule_files: &[(String, RuleFile)]) -> String {
         output.push_str("];\n\n");
     }
 
+    // Custom handler lookup for `tool-gates explain`
+    output.push_str("/// Programs routed to custom handlers (program, rule file, handler)\n");
+    output.push_str("pub static CUSTOM_HANDLERS: &[(&str, &str, &str)] = &[\n");
+    for (name, rules) in rule_files {
+        for handler in &rules.custom_handlers {
+            output.push_str(&format!(
+                "    (\"{}\", \"{}\", \"{}\"),\n",
+                escape_rust_string(&handler.program),
+                name,
+                escape_rust_string(&handler.handler)
+            ));
+        }
+    }
+    output.push_str("];\n\n");
+
     output
 }
 
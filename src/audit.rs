@@ -0,0 +1,323 @@
+//! Decision audit log.
+//!
+//! Opt-in (`[audit] enabled = true` in config.toml). Every PreToolUse,
+//! PermissionRequest and PostToolUse call appends one JSON line to
+//! `~/.cache/tool-gates/audit.jsonl`: what was asked, what tool-gates decided,
+//! why, and which rule or mechanism decided it. `tool-gates log` queries it.
+//!
+//! The log rotates to `audit.jsonl.1`, `.2`, ... once it grows past
+//! `max_size_mb`; the oldest file beyond `keep` is deleted.
+
+use crate::config::AuditConfig;
+use crate::models::HookOutput;
+use chrono::{DateTime, NaiveDate, Utc};
+use fs2::FileExt;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+/// One hook call
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuditEntry {
+    pub timestamp: DateTime<Utc>,
+    /// `PreToolUse`, `PermissionRequest` or `PostToolUse`
+    pub hook: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub session_id: String,
+    /// Project directory (`CLAUDE_PROJECT_DIR`, else the hook's cwd)
+    pub project: String,
+    pub tool: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub command: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub paths: Vec<String>,
+    /// `allow`, `ask`, `deny`, or `none` for no opinion. PostToolUse records
+    /// `succeeded` or `failed` for Bash.
+    pub decision: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    /// Rule file, settings scope, handler or permission mode behind the decision
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+    /// Modern CLI hints shown (`cat -> bat`)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub hints: Vec<String>,
+    /// Time spent in the hook, in microseconds
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub elapsed_us: Option<u64>,
+}
+
+impl AuditEntry {
+    /// An entry with no opinion, to be filled in by the hook handler
+    pub fn new(hook: &str, tool: &str, session_id: &str, project: &str) -> Self {
+        Self {
+            timestamp: Utc::now(),
+            hook: hook.to_string(),
+            session_id: session_id.to_string(),
+            project: project.to_string(),
+            tool: tool.to_string(),
+            command: None,
+            paths: Vec::new(),
+            decision: "none".to_string(),
+            reason: None,
+            source: None,
+            hints: Vec::new(),
+            elapsed_us: None,
+        }
+    }
+
+    /// Take the decision and reason from a PreToolUse output
+    pub fn with_output(mut self, output: Option<&HookOutput>) -> Self {
+        if let Some(hso) = output.and_then(|o| o.hook_specific_output.as_ref()) {
+            self.decision = hso.permission_decision.clone();
+            self.reason = hso.permission_decision_reason.clone();
+        }
+        self
+    }
+
+    /// Record the time since the hook started
+    pub fn with_elapsed(mut self, started: Instant) -> Self {
+        self.elapsed_us = Some(u64::try_from(started.elapsed().as_micros()).unwrap_or(u64::MAX));
+        self
+    }
+
+    /// The command, or the file paths for file tools
+    pub fn subject(&self) -> String {
+        match &self.command {
+            Some(command) => command.clone(),
+            None => self.paths.join(", "),
+        }
+    }
+}
+
+/// Path to the current log file
+pub fn audit_path() -> PathBuf {
+    crate::cache::cache_dir().join("audit.jsonl")
+}
+
+/// Append an entry if the audit log is enabled. Failures only warn.
+pub fn record(config: &AuditConfig, entry: &AuditEntry) {
+    if !config.enabled {
+        return;
+    }
+    let max_bytes = config.max_size_mb.saturating_mul(1024 * 1024);
+    if let Err(e) = append(&audit_path(), entry, max_bytes, config.keep) {
+        eprintln!("Warning: Failed to write audit log: {e}");
+    }
+}
+
+/// Append to the log at `path`, rotating first if the entry would push it past `max_bytes`.
+fn append(path: &Path, entry: &AuditEntry, max_bytes: u64, keep: u32) -> std::io::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let line = serde_json::to_string(entry)? + "\n";
+
+    // Lock a sidecar file: the log itself is renamed during rotation
+    let lock = OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(path.with_extension("lock"))?;
+    #[allow(clippy::incompatible_msrv)] // fs2 crate method, not std
+    lock.lock_exclusive()?;
+
+    let size = fs::metadata(path).map(|m| m.len()).unwrap_or(0);
+    if max_bytes > 0 && size > 0 && size + line.len() as u64 > max_bytes {
+        rotate(path, keep)?;
+    }
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    file.write_all(line.as_bytes())?;
+
+    #[allow(clippy::incompatible_msrv)] // fs2 crate method, not std
+    lock.unlock()?;
+    Ok(())
+}
+
+/// Shift `log.N-1` to `log.N` ... `log` to `log.1`, dropping anything past `keep`.
+fn rotate(path: &Path, keep: u32) -> std::io::Result<()> {
+    if keep == 0 {
+        return fs::remove_file(path);
+    }
+    let _ = fs::remove_file(rotated_path(path, keep));
+    for n in (1..keep).rev() {
+        let from = rotated_path(path, n);
+        if from.exists() {
+            fs::rename(&from, rotated_path(path, n + 1))?;
+        }
+    }
+    fs::rename(path, rotated_path(path, 1))
+}
+
+fn rotated_path(path: &Path, n: u32) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(format!(".{n}"));
+    PathBuf::from(name)
+}
+
+/// Filters for `tool-gates log`; every set filter must match
+#[derive(Debug, Clone, Default)]
+pub struct LogFilter {
+    /// Only entries whose project is this directory or below it
+    pub project: Option<String>,
+    pub since: Option<DateTime<Utc>>,
+    /// `allow`, `ask`, `deny`, `none`, ... (`block` is accepted for `deny`)
+    pub decision: Option<String>,
+    /// Case-insensitive substring of the command, paths, reason or source
+    pub grep: Option<String>,
+}
+
+impl LogFilter {
+    pub fn matches(&self, entry: &AuditEntry) -> bool {
+        if let Some(project) = &self.project {
+            if !Path::new(&entry.project).starts_with(project) {
+                return false;
+            }
+        }
+        if self.since.is_some_and(|since| entry.timestamp < since) {
+            return false;
+        }
+        if let Some(decision) = &self.decision {
+            let decision = match decision.to_ascii_lowercase().as_str() {
+                "block" => "deny".to_string(),
+                other => other.to_string(),
+            };
+            if entry.decision != decision {
+                return false;
+            }
+        }
+        if let Some(needle) = &self.grep {
+            let needle = needle.to_lowercase();
+            let haystack = [
+                Some(entry.subject()),
+                entry.reason.clone(),
+                entry.source.clone(),
+            ];
+            if !haystack
+                .iter()
+                .flatten()
+                .any(|text| text.to_lowercase().contains(&needle))
+            {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+/// Read matching entries from the log and its rotated files, oldest first.
+pub fn read_log(filter: &LogFilter) -> Vec<AuditEntry> {
+    read_log_at(&audit_path(), filter)
+}
+
+fn read_log_at(path: &Path, filter: &LogFilter) -> Vec<AuditEntry> {
+    let mut files: Vec<PathBuf> = (1..)
+        .map(|n| rotated_path(path, n))
+        .take_while(|p| p.exists())
+        .collect();
+    files.reverse();
+    files.push(path.to_path_buf());
+
+    files
+        .iter()
+        .filter_map(|p| File::open(p).ok())
+        .flat_map(|file| BufReader::new(file).lines().map_while(Result::ok))
+        .filter_map(|line| serde_json::from_str::<AuditEntry>(&line).ok())
+        .filter(|entry| filter.matches(entry))
+        .collect()
+}
+
+/// Parse `--since`: a duration back from `now` (`2h`, `1d`), a date (`2026-01-31`)
+/// or an RFC 3339 timestamp.
+pub fn parse_since(s: &str, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+    if let Ok(duration) = crate::session_approvals::parse_duration(s) {
+        return now.checked_sub_signed(duration);
+    }
+    if let Ok(date) = NaiveDate::parse_from_str(s, "%Y-%m-%d") {
+        return Some(date.and_hms_opt(0, 0, 0)?.and_utc());
+    }
+    DateTime::parse_from_rfc3339(s)
+        .ok()
+        .map(|t| t.with_timezone(&Utc))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+    use tempfile::TempDir;
+
+    fn entry(command: &str, decision: &str, project: &str) -> AuditEntry {
+        let mut entry = AuditEntry::new("PreToolUse", "Bash", "s1", project);
+        entry.command = Some(command.to_string());
+        entry.decision = decision.to_string();
+        entry
+    }
+
+    #[test]
+    fn test_append_and_rotate() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("audit.jsonl");
+        let e = entry("git status", "allow", "/work/app");
+        let line_len = serde_json::to_string(&e).unwrap().len() as u64 + 1;
+
+        // Two entries per file, keep two rotated files
+        for _ in 0..7 {
+            append(&path, &e, line_len * 2, 2).unwrap();
+        }
+        assert!(rotated_path(&path, 1).exists());
+        assert!(rotated_path(&path, 2).exists());
+        assert!(!rotated_path(&path, 3).exists());
+        assert_eq!(read_log_at(&path, &LogFilter::default()).len(), 5);
+    }
+
+    #[test]
+    fn test_filter() {
+        let mut old = entry("rm -rf /", "deny", "/work/app");
+        old.timestamp = Utc::now() - Duration::days(2);
+        old.source = Some("src/gates/filesystem.rs".to_string());
+        let recent = entry("git push", "ask", "/work/other");
+
+        let deny = LogFilter {
+            decision: Some("block".to_string()),
+            ..Default::default()
+        };
+        assert!(deny.matches(&old));
+        assert!(!deny.matches(&recent));
+
+        let since = LogFilter {
+            since: parse_since("1d", Utc::now()),
+            ..Default::default()
+        };
+        assert!(!since.matches(&old));
+        assert!(since.matches(&recent));
+
+        let project = LogFilter {
+            project: Some("/work/app".to_string()),
+            ..Default::default()
+        };
+        assert!(project.matches(&old));
+        assert!(!project.matches(&recent));
+
+        let grep = LogFilter {
+            grep: Some("FILESYSTEM".to_string()),
+            ..Default::default()
+        };
+        assert!(grep.matches(&old));
+        assert!(!grep.matches(&recent));
+    }
+
+    #[test]
+    fn test_parse_since() {
+        let now = Utc::now();
+        assert_eq!(parse_since("2h", now), Some(now - Duration::hours(2)));
+        assert_eq!(
+            parse_since("2026-01-31", now).unwrap().to_rfc3339(),
+            "2026-01-31T00:00:00+00:00"
+        );
+        assert!(parse_since("2026-01-31T10:00:00Z", now).is_some());
+        assert!(parse_since("yesterday", now).is_none());
+    }
+}
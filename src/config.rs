@@ -52,9 +52,21 @@
 //! [cache]
 //! ttl_days = 14  # tool detection cache TTL (default: 7)
 //! ```
+//!
+//! ## Audit log
+//!
+//! Off by default. Records every hook decision to
+//! `~/.cache/tool-gates/audit.jsonl` (query with `tool-gates log`).
+//!
+//! ```toml
+//! [audit]
+//! enabled = true
+//! max_size_mb = 10  # rotate when the log grows past this (default: 10)
+//! keep = 3          # rotated files to keep: audit.jsonl.1 .. .3 (default: 3)
+//! ```
 
 use serde::Deserialize;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::OnceLock;
 
 /// Top-level configuration.
@@ -75,6 +87,9 @@ pub struct Config {
     /// Cache settings.
     #[serde(default)]
     pub cache: CacheConfig,
+    /// Decision audit log.
+    #[serde(default)]
+    pub audit: AuditConfig,
     /// Security reminder customization.
     #[serde(default)]
     pub security_reminders: SecurityRemindersConfig,
@@ -140,6 +155,28 @@ impl Default for CacheConfig {
     }
 }
 
+/// Decision audit log settings.
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct AuditConfig {
+    /// Record hook decisions to the audit log. Default: false.
+    pub enabled: bool,
+    /// Rotate the log when it grows past this many megabytes. Default: 10.
+    pub max_size_mb: u64,
+    /// Number of rotated files to keep. Default: 3.
+    pub keep: u32,
+}
+
+impl Default for AuditConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            max_size_mb: 10,
+            keep: 3,
+        }
+    }
+}
+
 /// Auto-approve rule for Skill tool calls.
 #[derive(Debug, Deserialize, Clone)]
 pub struct SkillApprovalRule {
@@ -356,35 +393,44 @@ static DEFAULT_BLOCK_RULES: std::sync::LazyLock<Vec<BlockRule>> = std::sync::Laz
     ]
 });
 
-/// Get the config file path.
-fn config_path() -> PathBuf {
+/// Get the config directory (`~/.config/tool-gates`, honoring `XDG_CONFIG_HOME`).
+pub fn config_dir() -> PathBuf {
     std::env::var("XDG_CONFIG_HOME")
         .map(PathBuf::from)
         .ok()
         .or_else(|| dirs::home_dir().map(|h| h.join(".config")))
         .unwrap_or_else(|| PathBuf::from("/tmp"))
         .join("tool-gates")
-        .join("config.toml")
+}
+
+/// Get the config file path.
+pub fn config_path() -> PathBuf {
+    config_dir().join("config.toml")
 }
 
 /// Load configuration. Returns defaults if file doesn't exist or can't be parsed.
 pub fn load() -> Config {
     let path = config_path();
     match std::fs::read_to_string(&path) {
-        Ok(content) => match toml::from_str(&content) {
-            Ok(config) => config,
-            Err(e) => {
-                eprintln!(
-                    "tool-gates: warning: config parse error in {}: {e}",
-                    path.display()
-                );
-                Config::default()
-            }
-        },
+        Ok(content) => parse(&content, &path).unwrap_or_else(|e| {
+            eprintln!("tool-gates: warning: {e}");
+            Config::default()
+        }),
         Err(_) => Config::default(),
     }
 }
 
+/// Load configuration from a specific file (`tool-gates replay --compare-config`).
+pub fn load_from(path: &Path) -> Result<Config, String> {
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| format!("cannot read {}: {e}", path.display()))?;
+    parse(&content, path)
+}
+
+fn parse(content: &str, path: &Path) -> Result<Config, String> {
+    toml::from_str(content).map_err(|e| format!("config parse error in {}: {e}", path.display()))
+}
+
 /// Global config singleton -- loaded once per process.
 static GLOBAL_CONFIG: OnceLock<Config> = OnceLock::new();
 
@@ -595,6 +641,20 @@ ttl_days = 14
         assert_eq!(config.cache.ttl_days, 14);
     }
 
+    #[test]
+    fn test_audit_config() {
+        assert!(!Config::default().audit.enabled);
+        let toml = r#"
+[audit]
+enabled = true
+keep = 5
+"#;
+        let config: Config = toml::from_str(toml).unwrap();
+        assert!(config.audit.enabled);
+        assert_eq!(config.audit.keep, 5);
+        assert_eq!(config.audit.max_size_mb, 10);
+    }
+
     #[test]
     fn test_unknown_keys_ignored() {
         let toml = r#"
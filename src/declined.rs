@@ -0,0 +1,268 @@
+//! Declined and failed commands, the counterpart of the pending queue.
+//!
+//! A command that returned "ask" is tracked until PostToolUse reports that it
+//! ran. When that never happens (the prompt was declined or the command was
+//! interrupted) the tracking entry is recorded here as [`DeclineKind::Declined`]
+//! once its session moves on or ends (see [`crate::tracking`]). A command that
+//! ran but failed is recorded as [`DeclineKind::Failed`].
+//!
+//! Stored in `~/.cache/tool-gates/declined.jsonl`, one entry per command, project
+//! and kind. The review TUI offers deny rules for declined commands, and
+//! `pending list` shows approve/decline counts per suggested pattern.
+
+use chrono::{Duration, Utc};
+use fs2::FileExt;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, Seek, Write};
+use std::path::PathBuf;
+
+use crate::pending::PendingApproval;
+use crate::settings::Settings;
+use crate::tracking::TrackedCommand;
+
+/// Entries not seen for this long are dropped
+const MAX_AGE_DAYS: i64 = 30;
+
+/// Why a tracked command didn't make it into the pending queue
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeclineKind {
+    /// Never completed: declined at the prompt or interrupted
+    Declined,
+    /// Ran and exited non-zero
+    Failed,
+}
+
+/// A declined or failed command
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeclinedCommand {
+    #[serde(flatten)]
+    pub entry: PendingApproval,
+    pub kind: DeclineKind,
+}
+
+impl DeclinedCommand {
+    pub fn from_tracked(tracked: TrackedCommand, kind: DeclineKind) -> Self {
+        Self {
+            entry: PendingApproval::new(
+                tracked.command,
+                tracked.suggested_patterns,
+                tracked.breakdown,
+                tracked.project_id,
+                tracked.cwd,
+                tracked.session_id,
+            ),
+            kind,
+        }
+    }
+}
+
+/// Get the path to the declined queue
+pub fn declined_path() -> PathBuf {
+    crate::cache::cache_dir().join("declined.jsonl")
+}
+
+/// Read declined and failed commands, optionally filtered by project_id.
+pub fn read_declined(filter_project: Option<&str>) -> Vec<DeclinedCommand> {
+    let Ok(file) = File::open(declined_path()) else {
+        return Vec::new();
+    };
+
+    #[allow(clippy::incompatible_msrv)] // fs2 crate method, not std
+    if file.lock_shared().is_err() {
+        eprintln!("Warning: Could not acquire lock on declined file");
+        return Vec::new();
+    }
+
+    let entries = BufReader::new(&file)
+        .lines()
+        .map_while(Result::ok)
+        .filter_map(|line| serde_json::from_str::<DeclinedCommand>(&line).ok())
+        .filter(|d| filter_project.is_none_or(|project| d.entry.project_id == project))
+        .collect();
+
+    #[allow(clippy::incompatible_msrv)] // fs2 crate method, not std
+    let _ = file.unlock();
+
+    entries
+}
+
+/// Atomically modify the declined file, dropping stale entries.
+fn with_exclusive_declined<F, R>(f: F) -> std::io::Result<R>
+where
+    F: FnOnce(&mut Vec<DeclinedCommand>) -> R,
+{
+    let path = declined_path();
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let mut file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(&path)?;
+
+    #[allow(clippy::incompatible_msrv)] // fs2 crate method, not std
+    file.lock_exclusive()?;
+
+    let cutoff = Utc::now() - Duration::days(MAX_AGE_DAYS);
+    let mut entries: Vec<DeclinedCommand> = BufReader::new(&file)
+        .lines()
+        .map_while(Result::ok)
+        .filter_map(|line| serde_json::from_str::<DeclinedCommand>(&line).ok())
+        .filter(|d| d.entry.last_seen > cutoff)
+        .collect();
+
+    let result = f(&mut entries);
+
+    file.set_len(0)?;
+    file.seek(std::io::SeekFrom::Start(0))?;
+
+    let mut writer = std::io::BufWriter::new(&file);
+    for entry in &entries {
+        writeln!(writer, "{}", serde_json::to_string(entry)?)?;
+    }
+    writer.flush()?;
+
+    #[allow(clippy::incompatible_msrv)] // fs2 crate method, not std
+    file.unlock()?;
+
+    Ok(result)
+}
+
+/// Record declined or failed commands, incrementing entries already seen in the same project.
+pub fn record_declined(declined: Vec<DeclinedCommand>) -> std::io::Result<()> {
+    if declined.is_empty() {
+        return Ok(());
+    }
+    with_exclusive_declined(|entries| {
+        for new in declined {
+            match entries.iter_mut().find(|e| {
+                e.kind == new.kind
+                    && e.entry.command == new.entry.command
+                    && e.entry.project_id == new.entry.project_id
+            }) {
+                Some(existing) => existing.entry.increment(),
+                None => entries.push(new),
+            }
+        }
+    })
+}
+
+/// Remove declined entries by ID
+pub fn remove_declined_many(ids: &[String]) -> std::io::Result<usize> {
+    with_exclusive_declined(|entries| {
+        let len_before = entries.len();
+        entries.retain(|e| !ids.contains(&e.entry.id));
+        len_before - entries.len()
+    })
+}
+
+/// How often commands a pattern matches were approved, declined and failed
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PatternStats {
+    pub approved: u32,
+    pub declined: u32,
+    pub failed: u32,
+}
+
+/// Count pending (approved) and declined entries with a segment `pattern` matches.
+pub fn pattern_stats(
+    pattern: &str,
+    pending: &[PendingApproval],
+    declined: &[DeclinedCommand],
+) -> PatternStats {
+    let mut stats = PatternStats {
+        approved: pending
+            .iter()
+            .filter(|e| matches_entry(pattern, e))
+            .map(|e| e.count)
+            .sum(),
+        ..Default::default()
+    };
+    for d in declined.iter().filter(|d| matches_entry(pattern, &d.entry)) {
+        match d.kind {
+            DeclineKind::Declined => stats.declined += d.entry.count,
+            DeclineKind::Failed => stats.failed += d.entry.count,
+        }
+    }
+    stats
+}
+
+/// Whether a pattern matches the command or one of its segments that needed approval
+fn matches_entry(pattern: &str, entry: &PendingApproval) -> bool {
+    Settings::matches_bash_pattern(pattern, &entry.command)
+        || entry
+            .breakdown
+            .iter()
+            .filter(|p| p.decision != "allow")
+            .any(|p| Settings::matches_bash_pattern(pattern, &p.command_line()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::Decision;
+    use crate::tracking::CommandPart;
+
+    fn entry(command: &str, count: u32) -> PendingApproval {
+        let words: Vec<String> = command.split(' ').map(String::from).collect();
+        let mut entry = PendingApproval::new(
+            command.to_string(),
+            vec![],
+            vec![CommandPart::new(&words[0], &words[1..], Decision::Ask, "")],
+            "/tmp/project".to_string(),
+            "/tmp/project".to_string(),
+            "session1".to_string(),
+        );
+        entry.count = count;
+        entry
+    }
+
+    fn declined(command: &str, count: u32, kind: DeclineKind) -> DeclinedCommand {
+        DeclinedCommand {
+            entry: entry(command, count),
+            kind,
+        }
+    }
+
+    #[test]
+    fn test_pattern_stats() {
+        let pending = vec![entry("npm install lodash", 3), entry("npm test", 1)];
+        let declined = vec![
+            declined("npm install left-pad", 2, DeclineKind::Declined),
+            declined("npm install nope", 1, DeclineKind::Failed),
+            declined("git push --force", 4, DeclineKind::Declined),
+        ];
+        assert_eq!(
+            pattern_stats("npm install:*", &pending, &declined),
+            PatternStats {
+                approved: 3,
+                declined: 2,
+                failed: 1
+            }
+        );
+        assert_eq!(
+            pattern_stats("git push:*", &pending, &declined),
+            PatternStats {
+                approved: 0,
+                declined: 4,
+                failed: 0
+            }
+        );
+    }
+
+    #[test]
+    fn test_serde_round_trip() {
+        let d = declined("git push --force", 2, DeclineKind::Failed);
+        let json = serde_json::to_string(&d).unwrap();
+        assert!(json.contains(r#""kind":"failed""#));
+        assert!(json.contains(r#""command":"git push --force""#));
+        let back: DeclinedCommand = serde_json::from_str(&json).unwrap();
+        assert_eq!(back.kind, DeclineKind::Failed);
+        assert_eq!(back.entry.count, 2);
+    }
+}
@@ -0,0 +1,487 @@
+//! Library-level entry point with injectable dependencies.
+//!
+//! The free functions in [`crate::router`] read settings.json, config.toml, the
+//! tool cache and the hint tracker from disk. [`Engine`] takes each of those
+//! from its builder instead, and returns a typed [`Verdict`] rather than a
+//! serialized [`HookOutput`]. Anything not supplied falls back to the same
+//! on-disk source the hook binary uses.
+//!
+//! ```
+//! use tool_gates::{Decision, Engine};
+//! use tool_gates::config::Config;
+//! use tool_gates::hint_tracker::MemorySessionStore;
+//! use tool_gates::session_approvals::SessionApprovals;
+//! use tool_gates::settings::Settings;
+//! use tool_gates::user_rules::RuleSet;
+//!
+//! let engine = Engine::builder()
+//!     .config(Config::default())
+//!     .settings(Settings::default())
+//!     .repo_policy(None)
+//!     .rules(RuleSet::default())
+//!     .session_approvals(SessionApprovals::default())
+//!     .tools(|tool: &str| tool == "rg")
+//!     .session_store(MemorySessionStore::new())
+//!     .cwd("/project")
+//!     .build();
+//!
+//! let verdict = engine.check("git status && rm -rf /");
+//! assert_eq!(verdict.decision, Decision::Block);
+//! assert_eq!(verdict.segments.len(), 2);
+//! ```
+
+use crate::config::{self, Config};
+use crate::hint_tracker::{DiskSessionStore, SessionStore};
+use crate::hints::{self, ModernHint};
+use crate::models::{CommandInfo, Decision, GateResult, HookOutput, PermissionMode};
+use crate::repo_policy::{self, LoadedPolicy};
+use crate::router;
+use crate::session_approvals::SessionApprovals;
+use crate::settings::Settings;
+use crate::tool_cache::ToolAvailability;
+use crate::user_rules::{self, RuleSet};
+use std::sync::Arc;
+
+/// Result of checking a command, before serialization to hook JSON.
+#[derive(Debug, Clone, Default)]
+pub struct Verdict {
+    /// Final decision. `Skip` means no opinion (empty command).
+    pub decision: Decision,
+    /// Reason shown to the user
+    pub reason: Option<String>,
+    /// Gate result for each parsed command, in order
+    pub segments: Vec<SegmentVerdict>,
+    /// Modern CLI hints that survived session dedup
+    pub hints: Vec<ModernHint>,
+    /// Extra context for Claude (hints, approval instructions)
+    pub context: Option<String>,
+}
+
+/// Gate result for one parsed command
+#[derive(Debug, Clone)]
+pub struct SegmentVerdict {
+    pub command: CommandInfo,
+    pub result: GateResult,
+}
+
+impl Verdict {
+    /// No opinion
+    pub fn no_opinion() -> Self {
+        Self::default()
+    }
+
+    pub fn allow(reason: Option<&str>) -> Self {
+        Self {
+            decision: Decision::Allow,
+            reason: reason.map(String::from),
+            ..Self::default()
+        }
+    }
+
+    pub fn ask(reason: &str) -> Self {
+        Self {
+            decision: Decision::Ask,
+            reason: Some(reason.to_string()),
+            ..Self::default()
+        }
+    }
+
+    pub fn block(reason: &str) -> Self {
+        Self {
+            decision: Decision::Block,
+            reason: Some(reason.to_string()),
+            ..Self::default()
+        }
+    }
+
+    /// Attach additional context for Claude.
+    pub fn with_context(mut self, context: &str) -> Self {
+        self.context = Some(context.to_string());
+        self
+    }
+
+    /// Keep per-segment results from an earlier gate analysis.
+    pub fn with_segments(mut self, segments: Vec<SegmentVerdict>) -> Self {
+        self.segments = segments;
+        self
+    }
+
+    /// Serialize to the PreToolUse hook output format.
+    pub fn into_hook_output(self) -> HookOutput {
+        let context = self.context.as_deref();
+        match self.decision {
+            Decision::Skip => HookOutput::no_opinion(),
+            Decision::Block => HookOutput::deny(self.reason.as_deref().unwrap_or("Blocked")),
+            Decision::Ask => {
+                let reason = self.reason.as_deref().unwrap_or("Requires approval");
+                match context {
+                    Some(context) => HookOutput::ask_with_context(reason, context),
+                    None => HookOutput::ask(reason),
+                }
+            }
+            Decision::Allow => match context {
+                Some(context) => HookOutput::allow_with_context(self.reason.as_deref(), context),
+                None => HookOutput::allow(self.reason.as_deref()),
+            },
+        }
+    }
+}
+
+impl From<Verdict> for HookOutput {
+    fn from(verdict: Verdict) -> Self {
+        verdict.into_hook_output()
+    }
+}
+
+impl<F: Fn(&str) -> bool + Send + Sync> ToolAvailability for F {
+    fn is_available(&self, tool: &str) -> bool {
+        self(tool)
+    }
+}
+
+/// Permission engine with injectable config, settings, tool availability and session state.
+#[derive(Clone, Default)]
+pub struct Engine {
+    config: Option<Arc<Config>>,
+    settings: Option<Arc<Settings>>,
+    tools: Option<Arc<dyn ToolAvailability>>,
+    sessions: Option<Arc<dyn SessionStore>>,
+    /// `Some(None)` = injected "no policy file"
+    repo_policy: Option<Option<Arc<LoadedPolicy>>>,
+    rules: Option<Arc<RuleSet>>,
+    session_approvals: Option<Arc<SessionApprovals>>,
+    cwd: String,
+    permission_mode: String,
+    session_id: String,
+}
+
+/// Builder for [`Engine`]
+#[derive(Clone, Default)]
+pub struct EngineBuilder {
+    engine: Engine,
+}
+
+impl EngineBuilder {
+    /// Use this config instead of `~/.config/tool-gates/config.toml`.
+    pub fn config(mut self, config: Config) -> Self {
+        self.engine.config = Some(Arc::new(config));
+        self
+    }
+
+    /// Use these settings instead of loading settings.json for each cwd.
+    ///
+    /// Build from explicit files with [`Settings::load_from`].
+    pub fn settings(mut self, settings: Settings) -> Self {
+        self.engine.settings = Some(Arc::new(settings));
+        self
+    }
+
+    /// Decide which modern CLI tools are installed (controls hints).
+    pub fn tools(mut self, tools: impl ToolAvailability + 'static) -> Self {
+        self.engine.tools = Some(Arc::new(tools));
+        self
+    }
+
+    /// Store for per-session hint and approval-message dedup.
+    pub fn session_store(mut self, store: impl SessionStore + 'static) -> Self {
+        self.engine.sessions = Some(Arc::new(store));
+        self
+    }
+
+    /// Use this repository policy instead of looking up `.tool-gates.toml` from cwd.
+    ///
+    /// `None` means the repository has no policy file.
+    pub fn repo_policy(mut self, policy: Option<LoadedPolicy>) -> Self {
+        self.engine.repo_policy = Some(policy.map(Arc::new));
+        self
+    }
+
+    /// Use these runtime rule files instead of loading `~/.config/tool-gates/rules`
+    /// and `.tool-gates/rules`.
+    pub fn rules(mut self, rules: RuleSet) -> Self {
+        self.engine.rules = Some(Arc::new(rules));
+        self
+    }
+
+    /// Use these temporary approvals instead of loading
+    /// `~/.cache/tool-gates/session-approvals.json`.
+    pub fn session_approvals(mut self, approvals: SessionApprovals) -> Self {
+        self.engine.session_approvals = Some(Arc::new(approvals));
+        self
+    }
+
+    /// Working directory for settings, mise.toml and package.json lookup.
+    pub fn cwd(mut self, cwd: impl Into<String>) -> Self {
+        self.engine.cwd = cwd.into();
+        self
+    }
+
+    /// Claude Code permission mode ("default", "acceptEdits", ...).
+    pub fn permission_mode(mut self, mode: impl Into<String>) -> Self {
+        self.engine.permission_mode = mode.into();
+        self
+    }
+
+    /// Session ID for hint dedup. Empty disables dedup.
+    pub fn session_id(mut self, session_id: impl Into<String>) -> Self {
+        self.engine.session_id = session_id.into();
+        self
+    }
+
+    pub fn build(self) -> Engine {
+        self.engine
+    }
+}
+
+impl Engine {
+    pub fn builder() -> EngineBuilder {
+        EngineBuilder::default()
+    }
+
+    /// Check a command with settings.json rules, permission mode and task expansion.
+    ///
+    /// Same pipeline as the PreToolUse hook.
+    pub fn check(&self, command: &str) -> Verdict {
+        user_rules::with_rules(self.rules(), || router::check_with_settings(self, command))
+    }
+
+    /// Check a command against the gates only (no settings.json, no expansion).
+    pub fn check_gates(&self, command: &str) -> Verdict {
+        user_rules::with_rules(self.rules(), || router::check_gates_only(self, command))
+    }
+
+    pub fn cwd(&self) -> &str {
+        &self.cwd
+    }
+
+    pub fn permission_mode(&self) -> &str {
+        &self.permission_mode
+    }
+
+    /// The permission mode, parsed (unknown modes are treated as `default`)
+    pub fn mode(&self) -> PermissionMode {
+        PermissionMode::parse(&self.permission_mode)
+    }
+
+    pub fn session_id(&self) -> &str {
+        &self.session_id
+    }
+
+    pub fn config(&self) -> &Config {
+        match &self.config {
+            Some(config) => config,
+            None => config::get(),
+        }
+    }
+
+    /// Settings for `cwd`: the injected settings, or settings.json loaded from disk.
+    pub fn settings_for(&self, cwd: &str) -> Arc<Settings> {
+        match &self.settings {
+            Some(settings) => Arc::clone(settings),
+            None => Arc::new(Settings::load(cwd)),
+        }
+    }
+
+    /// Repository policy: the injected one, or `.tool-gates.toml` found from cwd.
+    pub fn repo_policy(&self) -> Option<Arc<LoadedPolicy>> {
+        match &self.repo_policy {
+            Some(policy) => policy.clone(),
+            None => repo_policy::discover(&self.cwd).map(Arc::new),
+        }
+    }
+
+    /// Runtime rule files: the injected set, or the user and project rule dirs.
+    pub fn rules(&self) -> Arc<RuleSet> {
+        match &self.rules {
+            Some(rules) => Arc::clone(rules),
+            None => Arc::new(RuleSet::load(&self.cwd)),
+        }
+    }
+
+    /// Session-scoped and time-boxed approvals: the injected set, or the cache file.
+    pub fn session_approvals(&self) -> Arc<SessionApprovals> {
+        match &self.session_approvals {
+            Some(approvals) => Arc::clone(approvals),
+            None => Arc::new(SessionApprovals::load()),
+        }
+    }
+
+    pub fn sessions(&self) -> &dyn SessionStore {
+        match &self.sessions {
+            Some(sessions) => sessions.as_ref(),
+            None => &DiskSessionStore,
+        }
+    }
+
+    /// Modern CLI hint for a command, using the engine's config and tool source.
+    pub fn modern_hint(&self, cmd: &CommandInfo) -> Option<ModernHint> {
+        match &self.tools {
+            Some(tools) => hints::modern_hint(cmd, self.config(), tools.as_ref()),
+            None => hints::modern_hint(cmd, self.config(), hints::cache()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hint_tracker::MemorySessionStore;
+    use crate::settings::Permissions;
+
+    fn engine() -> EngineBuilder {
+        Engine::builder()
+            .config(Config::default())
+            .settings(Settings::default())
+            .repo_policy(None)
+            .rules(RuleSet::default())
+            .session_approvals(SessionApprovals::default())
+            .tools(|_: &str| false)
+            .session_store(MemorySessionStore::new())
+    }
+
+    #[test]
+    fn test_verdict_has_segments() {
+        let verdict = engine().build().check("git status | wc -l");
+        assert_eq!(verdict.decision, Decision::Allow);
+        assert_eq!(verdict.segments.len(), 2);
+        assert_eq!(verdict.segments[0].command.program, "git");
+        assert_eq!(verdict.segments[0].result.decision, Decision::Allow);
+    }
+
+    #[test]
+    fn test_injected_settings_allow() {
+        let settings = Settings {
+            permissions: Permissions {
+                allow: vec!["Bash(mystery-tool:*)".to_string()],
+                ..Default::default()
+            },
+        };
+        let verdict = engine()
+            .settings(settings)
+            .build()
+            .check("mystery-tool run");
+        assert_eq!(verdict.decision, Decision::Allow);
+        assert_eq!(
+            verdict.reason.as_deref(),
+            Some("Matched settings.json allow rule")
+        );
+        // Gate analysis is kept alongside the settings override
+        assert_eq!(verdict.segments[0].result.decision, Decision::Skip);
+    }
+
+    #[test]
+    fn test_injected_rules() {
+        let file = crate::user_rules::RuleFile::parse(
+            "[[programs]]\nname = \"deployctl\"\n[[programs.allow]]\nsubcommand = \"status\"\n",
+        )
+        .unwrap();
+        let rules = RuleSet {
+            sources: vec![crate::user_rules::RuleSource {
+                path: "deploy.toml".into(),
+                scope: crate::user_rules::RuleScope::User,
+                rules: file,
+            }],
+            errors: Vec::new(),
+        };
+        let engine = engine().rules(rules).build();
+        assert_eq!(engine.check("deployctl status").decision, Decision::Allow);
+        assert_eq!(engine.check("deployctl nuke").decision, Decision::Ask);
+        // User rules can't loosen a built-in block
+        assert_eq!(
+            engine.check("deployctl status && rm -rf /").decision,
+            Decision::Block
+        );
+    }
+
+    #[test]
+    fn test_injected_rules_apply_inside_shell_scripts() {
+        let file = crate::user_rules::RuleFile::parse(
+            "[[programs]]\nname = \"deployctl\"\n[[programs.block]]\nsubcommand = \"nuke\"\nreason = \"Nukes prod\"\n",
+        )
+        .unwrap();
+        let rules = RuleSet {
+            sources: vec![crate::user_rules::RuleSource {
+                path: ".tool-gates/rules/deploy.toml".into(),
+                scope: crate::user_rules::RuleScope::Project(crate::repo_policy::Trust::Untrusted),
+                rules: file,
+            }],
+            errors: Vec::new(),
+        };
+        let engine = engine().rules(rules).build();
+        for command in [
+            "deployctl nuke",
+            "bash -c 'deployctl nuke'",
+            "bash <<'EOF'\ndeployctl nuke\nEOF",
+            "sh -s <<< 'deployctl nuke'",
+        ] {
+            assert_eq!(
+                engine.check(command).decision,
+                Decision::Block,
+                "Failed for: {command}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_injected_tools_control_hints() {
+        let verdict = engine().build().check("cat README.md");
+        assert!(verdict.hints.is_empty());
+
+        let verdict = engine()
+            .tools(|tool: &str| tool == "bat")
+            .build()
+            .check("cat README.md");
+        assert_eq!(verdict.hints.len(), 1);
+        assert!(verdict.context.unwrap().contains("bat"));
+    }
+
+    #[test]
+    fn test_config_disables_hints() {
+        let mut config = Config::default();
+        config.features.hints = false;
+        let verdict = engine()
+            .config(config)
+            .tools(|_: &str| true)
+            .build()
+            .check("cat README.md");
+        assert!(verdict.hints.is_empty());
+    }
+
+    #[test]
+    fn test_session_store_dedups_hints() {
+        let engine = engine()
+            .tools(|_: &str| true)
+            .session_id("engine-session")
+            .build();
+        assert_eq!(engine.check("cat a.txt").hints.len(), 1);
+        assert!(engine.check("cat b.txt").hints.is_empty());
+    }
+
+    #[test]
+    fn test_accept_edits_mode() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let cwd = dir.path().to_string_lossy().to_string();
+        let command = format!("sd foo bar {cwd}/file.txt");
+
+        let verdict = engine().cwd(&cwd).build().check(&command);
+        assert_eq!(verdict.decision, Decision::Ask);
+
+        let verdict = engine()
+            .cwd(&cwd)
+            .permission_mode("acceptEdits")
+            .build()
+            .check(&command);
+        assert_eq!(verdict.decision, Decision::Allow);
+    }
+
+    #[test]
+    fn test_into_hook_output() {
+        let output = Verdict::block("nope").into_hook_output();
+        let hso = output.hook_specific_output.unwrap();
+        assert_eq!(hso.permission_decision, "deny");
+        assert_eq!(hso.permission_decision_reason.as_deref(), Some("nope"));
+
+        let output: HookOutput = Verdict::no_opinion().into();
+        assert!(output.hook_specific_output.is_none());
+    }
+}
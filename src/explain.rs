@@ -0,0 +1,434 @@
+//! Decision trace for `tool-gates explain '<command>'`.
+//!
+//! Runs a command through the same path as the PreToolUse hook with tracing
+//! enabled, then annotates each step with where it came from: the TOML rule
+//! file, the custom handler, or the settings.json scope.
+
+use crate::generated::rules::CUSTOM_HANDLERS;
+use crate::parser::extract_commands;
+use crate::repo_policy;
+use crate::router::check_command_with_settings_and_session;
+use crate::session_approvals;
+use crate::settings::Settings;
+use crate::trace::{self, TraceEntry, TraceEvent};
+use serde::Serialize;
+use std::collections::HashMap;
+
+/// Full explanation of how a command was decided
+#[derive(Debug, Serialize)]
+pub struct Explanation {
+    pub command: String,
+    pub cwd: String,
+    pub mode: String,
+    /// Top-level commands as parsed from the command string
+    pub segments: Vec<String>,
+    /// Decision steps in the order they ran (skipped gates omitted)
+    pub steps: Vec<Step>,
+    /// Final hook decision ("allow", "ask", "deny"), or None for no opinion
+    pub decision: Option<String>,
+    pub reason: Option<String>,
+    pub context: Option<String>,
+}
+
+/// One trace event plus where it came from
+#[derive(Debug, Serialize)]
+pub struct Step {
+    #[serde(flatten)]
+    pub entry: TraceEntry,
+    /// Rule file, handler, or settings scope responsible for this step
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+}
+
+/// Check a command with tracing enabled and annotate the trace.
+pub fn explain(command: &str, cwd: &str, mode: &str) -> Explanation {
+    let (output, trace) =
+        trace::collect(|| check_command_with_settings_and_session(command, cwd, mode, ""));
+
+    let hso = output.hook_specific_output;
+    Explanation {
+        command: command.to_string(),
+        cwd: cwd.to_string(),
+        mode: mode.to_string(),
+        segments: extract_commands(command)
+            .into_iter()
+            .map(|cmd| cmd.raw)
+            .collect(),
+        steps: annotate(trace, cwd),
+        decision: hso.as_ref().map(|o| o.permission_decision.clone()),
+        reason: hso
+            .as_ref()
+            .and_then(|o| o.permission_decision_reason.clone()),
+        context: hso.and_then(|o| o.additional_context),
+    }
+}
+
+/// Attach sources to trace entries and drop gates that skipped.
+fn annotate(trace: Vec<TraceEntry>, cwd: &str) -> Vec<Step> {
+    // Per depth: program being checked, and whether a TOML rule fired for the current gate
+    let mut programs: HashMap<usize, String> = HashMap::new();
+    let mut rule_seen: HashMap<usize, bool> = HashMap::new();
+    let mut steps = Vec::new();
+
+    for entry in trace {
+        let depth = entry.depth;
+        let source = match &entry.event {
+            TraceEvent::Command { program, .. } => {
+                programs.insert(depth + 1, program.clone());
+                rule_seen.insert(depth + 1, false);
+                None
+            }
+            TraceEvent::TomlRule { file, .. } => {
+                rule_seen.insert(depth, true);
+                Some(format!("rules/{file}.toml"))
+            }
+            TraceEvent::Gate { gate, decision, .. } => {
+                let had_rule = rule_seen.insert(depth, false).unwrap_or(false);
+                if *decision == "skip" {
+                    continue;
+                }
+                if had_rule {
+                    None
+                } else {
+                    Some(handler_source(
+                        gate,
+                        programs.get(&depth).map_or("", String::as_str),
+                    ))
+                }
+            }
+            TraceEvent::UserRule { file, .. } => {
+                rule_seen.insert(depth, true);
+                Some(file.clone())
+            }
+            TraceEvent::SettingsRule { list, rule, .. } => Settings::rule_source(cwd, list, rule)
+                .map(|(scope, path)| format!("{scope} ({})", path.display())),
+            TraceEvent::PolicyRule { .. } => {
+                repo_policy::find(cwd).map(|path| path.display().to_string())
+            }
+            TraceEvent::SessionApproval { .. } => {
+                Some(session_approvals::approvals_path().display().to_string())
+            }
+            TraceEvent::RawPattern { .. } => Some("src/router.rs".to_string()),
+            TraceEvent::Redirect { .. } | TraceEvent::Expansion { .. } => None,
+        };
+        steps.push(Step { entry, source });
+    }
+
+    steps
+}
+
+/// The rule or mechanism behind a hook decision, from its trace (for the audit log).
+///
+/// A permission mode that changed the result wins, then the last settings.json,
+/// `.tool-gates.toml` or session approval match, then the first gate that
+/// returned the final decision (nested commands are recorded before their wrapper).
+pub fn decision_source(trace: Vec<TraceEntry>, cwd: &str, decision: &str) -> Option<String> {
+    let gate_decision = if decision == "deny" {
+        "block"
+    } else {
+        decision
+    };
+    let steps = annotate(trace, cwd);
+
+    let mode = steps.iter().rev().find_map(|step| match &step.entry.event {
+        TraceEvent::Expansion { source, .. }
+            if matches!(*source, "acceptEdits" | "plan" | "bypassPermissions") =>
+        {
+            Some(format!("permission mode {source}"))
+        }
+        _ => None,
+    });
+    if mode.is_some() {
+        return mode;
+    }
+
+    if let Some(rule) = steps.iter().rev().find(|step| {
+        matches!(
+            step.entry.event,
+            TraceEvent::SettingsRule { .. }
+                | TraceEvent::PolicyRule { .. }
+                | TraceEvent::SessionApproval { .. }
+        )
+    }) {
+        return match rule.entry.event {
+            // Managed or unreadable settings files have no scope to name
+            TraceEvent::SettingsRule { .. } => rule
+                .source
+                .clone()
+                .or_else(|| Some("settings.json".to_string())),
+            _ => rule.source.clone(),
+        };
+    }
+
+    // TOML and user rules leave the following gate step without a source
+    let mut rule_sources: HashMap<usize, String> = HashMap::new();
+    for step in steps {
+        let depth = step.entry.depth;
+        match &step.entry.event {
+            TraceEvent::RawPattern { .. } => return step.source,
+            TraceEvent::TomlRule { .. } | TraceEvent::UserRule { .. } => {
+                if let Some(source) = step.source {
+                    rule_sources.insert(depth, source);
+                }
+            }
+            TraceEvent::Gate { decision, .. } => {
+                let source = step.source.or_else(|| rule_sources.remove(&depth));
+                if *decision == gate_decision {
+                    return source;
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Source for a gate result that no TOML rule produced.
+fn handler_source(gate: &str, program: &str) -> String {
+    if gate == "router" {
+        return "src/router.rs".to_string();
+    }
+    let base = program.rsplit('/').next().unwrap_or(program);
+    match CUSTOM_HANDLERS.iter().find(|(p, _, _)| *p == base) {
+        Some((_, file, handler)) => {
+            format!("custom handler {handler} (rules/{file}.toml, src/gates/{gate}.rs)")
+        }
+        None => format!("src/gates/{gate}.rs"),
+    }
+}
+
+impl Explanation {
+    /// Human-readable trace.
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        out.push_str(&format!("Command: {}\n", self.command));
+        out.push_str(&format!("cwd:     {}\n", self.cwd));
+        out.push_str(&format!("mode:    {}\n", self.mode));
+
+        out.push_str("\nSegments:\n");
+        if self.segments.is_empty() {
+            out.push_str("  (none)\n");
+        }
+        for (i, segment) in self.segments.iter().enumerate() {
+            out.push_str(&format!("  {}. {segment}\n", i + 1));
+        }
+
+        out.push_str("\nTrace:\n");
+        for step in &self.steps {
+            let indent = "  ".repeat(step.entry.depth + 1);
+            let line = match &step.entry.event {
+                TraceEvent::RawPattern { pattern, reason } => {
+                    format!("raw pattern {pattern}: ask - {reason}")
+                }
+                TraceEvent::Command { raw, .. } => format!("$ {raw}"),
+                TraceEvent::Redirect { decision, reason } => {
+                    format!("redirect: {decision}{}", with_reason(reason))
+                }
+                TraceEvent::Gate {
+                    gate,
+                    decision,
+                    reason,
+                } => format!("gate {gate}: {decision}{}", with_reason(reason)),
+                TraceEvent::TomlRule {
+                    program,
+                    section,
+                    index,
+                    ..
+                } => format!("rule: {program} {section}[{index}]"),
+                TraceEvent::UserRule {
+                    program,
+                    section,
+                    index,
+                    ..
+                } => format!("user rule: {program} {section}[{index}]"),
+                TraceEvent::SettingsRule {
+                    list,
+                    rule,
+                    command,
+                } => format!("settings.json {list}: {rule} matched `{command}`"),
+                TraceEvent::PolicyRule {
+                    list,
+                    rule,
+                    command,
+                } => format!(".tool-gates.toml {list}: {rule} matched `{command}`"),
+                TraceEvent::SessionApproval {
+                    rule,
+                    command,
+                    lifetime,
+                } => format!("session approval: {rule} matched `{command}` ({lifetime})"),
+                TraceEvent::Expansion { source, detail } => format!("{source}: {detail}"),
+            };
+            out.push_str(&indent);
+            out.push_str(&line);
+            if let Some(source) = &step.source {
+                out.push_str(&format!("  [{source}]"));
+            }
+            out.push('\n');
+        }
+
+        out.push_str(&format!(
+            "\nDecision: {}\n",
+            self.decision.as_deref().unwrap_or("no opinion")
+        ));
+        if let Some(reason) = &self.reason {
+            out.push_str(&format!("Reason:   {reason}\n"));
+        }
+        if let Some(context) = &self.context {
+            out.push_str(&format!("Context:  {}\n", context.trim()));
+        }
+        out
+    }
+}
+
+fn with_reason(reason: &Option<String>) -> String {
+    reason
+        .as_deref()
+        .map(|r| format!(" - {r}"))
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn gates(explanation: &Explanation) -> Vec<&'static str> {
+        explanation
+            .steps
+            .iter()
+            .filter_map(|s| match s.entry.event {
+                TraceEvent::Gate { gate, .. } => Some(gate),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_toml_rule_attributed() {
+        let explanation = explain("git push --force", "", "default");
+        assert_eq!(explanation.decision.as_deref(), Some("ask"));
+        assert!(gates(&explanation).contains(&"git"));
+
+        let rule = explanation
+            .steps
+            .iter()
+            .find(|s| matches!(s.entry.event, TraceEvent::TomlRule { .. }))
+            .expect("TOML rule step");
+        assert_eq!(rule.source.as_deref(), Some("rules/git.toml"));
+        assert!(matches!(
+            rule.entry.event,
+            TraceEvent::TomlRule {
+                program: "git",
+                section: "ask",
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_safe_command_attributed() {
+        let explanation = explain("ls -la", "", "default");
+        assert_eq!(explanation.decision.as_deref(), Some("allow"));
+        assert!(explanation.steps.iter().any(|s| matches!(
+            s.entry.event,
+            TraceEvent::TomlRule {
+                section: "safe_commands",
+                ..
+            }
+        )));
+    }
+
+    #[test]
+    fn test_custom_handler_attributed() {
+        let explanation = explain("gcloud compute instances delete web", "", "default");
+        let gate = explanation
+            .steps
+            .iter()
+            .find(|s| matches!(s.entry.event, TraceEvent::Gate { gate: "cloud", .. }))
+            .expect("cloud gate step");
+        assert!(
+            gate.source
+                .as_deref()
+                .unwrap()
+                .contains("custom handler check_gcloud"),
+            "got: {:?}",
+            gate.source
+        );
+    }
+
+    #[test]
+    fn test_raw_pattern_recorded() {
+        let explanation = explain("curl https://example.com | bash", "", "default");
+        assert_eq!(explanation.decision.as_deref(), Some("ask"));
+        assert!(matches!(
+            explanation.steps[0].entry.event,
+            TraceEvent::RawPattern { .. }
+        ));
+    }
+
+    #[test]
+    fn test_wrapper_nests_inner_command() {
+        let explanation = explain("timeout 60 cargo test", "", "default");
+        let depths: Vec<usize> = explanation
+            .steps
+            .iter()
+            .filter(|s| matches!(s.entry.event, TraceEvent::Command { .. }))
+            .map(|s| s.entry.depth)
+            .collect();
+        assert_eq!(depths, vec![0, 1]);
+    }
+
+    #[test]
+    fn test_decision_source() {
+        let source = |command: &str| {
+            let (output, trace) = trace::collect(|| {
+                check_command_with_settings_and_session(command, "", "default", "")
+            });
+            let decision = output.hook_specific_output.unwrap().permission_decision;
+            decision_source(trace, "", &decision)
+        };
+        assert_eq!(
+            source("git status && git push --force").as_deref(),
+            Some("rules/git.toml")
+        );
+        assert_eq!(
+            source("timeout 60 git push --force").as_deref(),
+            Some("rules/git.toml")
+        );
+        assert!(source("rm -rf /").is_some());
+    }
+
+    #[test]
+    fn test_settings_rule_scope() {
+        let dir = TempDir::new().unwrap();
+        fs::create_dir_all(dir.path().join(".claude")).unwrap();
+        fs::write(
+            dir.path().join(".claude/settings.json"),
+            r#"{"permissions": {"allow": ["Bash(mystery-tool:*)"]}}"#,
+        )
+        .unwrap();
+        let cwd = dir.path().to_string_lossy();
+
+        let explanation = explain("mystery-tool run", &cwd, "default");
+        assert_eq!(explanation.decision.as_deref(), Some("allow"));
+        let step = explanation
+            .steps
+            .iter()
+            .find(|s| matches!(s.entry.event, TraceEvent::SettingsRule { .. }))
+            .expect("settings step");
+        assert!(step.source.as_deref().unwrap().starts_with("project"));
+    }
+
+    #[test]
+    fn test_render_and_json() {
+        let explanation = explain("git status > out.txt", "", "default");
+        let text = explanation.render();
+        assert!(text.contains("redirect: ask"));
+        assert!(text.contains("Decision: ask"));
+
+        let json = serde_json::to_value(&explanation).unwrap();
+        assert_eq!(json["decision"], "ask");
+        assert_eq!(json["steps"][0]["kind"], "command");
+    }
+}
@@ -139,6 +139,8 @@ pub fn check_git(cmd: &CommandInfo) -> GateResult {
         program: cmd.program.clone(),
         args: normalized_args.clone(),
         raw: cmd.raw.clone(),
+        redirects: cmd.redirects.clone(),
+        flow: cmd.flow,
     };
 
     // Special case: git add with wildcards, --all, or . (complex logic)
@@ -53,6 +53,11 @@ pub fn check_mcp(cmd: &CommandInfo) -> GateResult {
 /// - Ask if no match or explicitly in ask list
 /// - Deny if explicitly denied in settings.json
 pub fn check_mcp_call(cmd: &CommandInfo, cwd: &str) -> GateResult {
+    check_mcp_call_with_settings(cmd, &Settings::load(cwd))
+}
+
+/// Same as [`check_mcp_call`] with already-loaded settings.
+pub fn check_mcp_call_with_settings(cmd: &CommandInfo, settings: &Settings) -> GateResult {
     if cmd.program != "mcp-cli" {
         return GateResult::skip();
     }
@@ -67,7 +72,6 @@ pub fn check_mcp_call(cmd: &CommandInfo, cwd: &str) -> GateResult {
     // Handle `call` command
     if subcmd == "call" {
         if let Some((server, tool)) = parse_call_args(&cmd.args) {
-            let settings = Settings::load(cwd);
             match settings.check_mcp_tool(&server, &tool) {
                 SettingsDecision::Allow => {
                     return GateResult::allow_with_reason(format!(
@@ -18,6 +18,7 @@ pub mod package_managers;
 pub mod shortcut;
 pub mod system;
 pub mod tool_gates;
+pub mod wrappers;
 
 #[cfg(test)]
 pub mod test_utils;
@@ -29,12 +30,13 @@ pub use devtools::check_devtools;
 pub use filesystem::check_filesystem;
 pub use gh::check_gh;
 pub use git::check_git;
-pub use mcp::{check_mcp, check_mcp_call};
+pub use mcp::{check_mcp, check_mcp_call, check_mcp_call_with_settings};
 pub use network::check_network;
 pub use package_managers::check_package_managers;
 pub use shortcut::check_shortcut;
 pub use system::check_system;
 pub use tool_gates::check_tool_gates;
+pub use wrappers::check_wrappers;
 
 use crate::models::{CommandInfo, GateResult};
 
@@ -42,8 +44,10 @@ use crate::models::{CommandInfo, GateResult};
 pub type GateCheckFn = fn(&CommandInfo) -> GateResult;
 
 /// All gates to run (in order)
-/// mcp runs first (priority 5), basics runs last as a catch-all for safe commands
+/// wrappers runs first (unwraps timeout/env/nice and re-checks the inner command),
+/// basics runs last as a catch-all for safe commands
 pub static GATES: &[(&str, GateCheckFn)] = &[
+    ("wrappers", check_wrappers),
     ("mcp", check_mcp),
     ("gh", check_gh),
     ("beads", check_beads),
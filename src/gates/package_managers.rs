@@ -99,6 +99,8 @@ fn check_pm_binary_exec(cmd: &CommandInfo, pm_name: &str) -> Option<GateResult>
         program: underlying_program.clone(),
         args: cmd.args[cmd_start + 1..].to_vec(),
         raw: cmd.raw.clone(),
+        redirects: cmd.redirects.clone(),
+        flow: cmd.flow,
     };
 
     // Run through ALL gates to catch dangerous commands
@@ -574,6 +576,8 @@ fn check_mise(cmd: &CommandInfo) -> GateResult {
                     program: underlying_program.clone(),
                     args: cmd.args[cmd_start + 1..].to_vec(),
                     raw: cmd.raw.clone(),
+                    redirects: cmd.redirects.clone(),
+                    flow: cmd.flow,
                 };
                 // Run through ALL gates (not just devtools) to catch dangerous commands
                 for (_name, gate_fn) in GATES.iter() {
@@ -688,6 +692,8 @@ fn check_python_run_command(cmd: &CommandInfo, pm_name: &str) -> Option<GateResu
         raw: cmd.raw.clone(),
         program: run_cmd.to_string(),
         args: run_args.to_vec(),
+        redirects: cmd.redirects.clone(),
+        flow: cmd.flow,
     };
 
     // Run through ALL gates to catch dangerous commands like rm -rf /
@@ -765,6 +771,8 @@ fn check_invoked_devtool(cmd: &CommandInfo, pm_name: &str) -> Option<GateResult>
         raw: cmd.raw.clone(),
         program: tool.to_string(),
         args: cmd.args[1..].to_vec(),
+        redirects: cmd.redirects.clone(),
+        flow: cmd.flow,
     };
 
     let result = check_devtools(&tool_cmd);
@@ -37,6 +37,8 @@ pub fn check_shortcut(cmd: &CommandInfo) -> GateResult {
                 raw: cmd.raw.clone(),
                 program: cmd.program.clone(),
                 args: new_args,
+                redirects: cmd.redirects.clone(),
+                flow: cmd.flow,
             }
         } else {
             cmd.clone()
@@ -17,5 +17,7 @@ pub fn cmd(program: &str, args: &[&str]) -> CommandInfo {
         raw: format!("{} {}", program, args.join(" ")),
         program: program.to_string(),
         args: args.iter().map(|s| s.to_string()).collect(),
+        redirects: Vec::new(),
+        flow: Default::default(),
     }
 }
@@ -42,6 +42,35 @@ mod tests {
         assert_eq!(result.decision, Decision::Allow);
     }
 
+    #[test]
+    fn test_rules_lint_allows_unless_fixing() {
+        let result = check_tool_gates(&cmd("tool-gates", &["rules", "lint", "--json"]));
+        assert_eq!(result.decision, Decision::Allow);
+        let result = check_tool_gates(&cmd("tool-gates", &["rules", "lint", "--fix"]));
+        assert_eq!(result.decision, Decision::Ask);
+    }
+
+    #[test]
+    fn test_log_allows() {
+        let result = check_tool_gates(&cmd("tool-gates", &["log", "--decision", "deny"]));
+        assert_eq!(result.decision, Decision::Allow);
+    }
+
+    #[test]
+    fn test_stats_allows() {
+        let result = check_tool_gates(&cmd("tool-gates", &["stats", "--since", "7d"]));
+        assert_eq!(result.decision, Decision::Allow);
+    }
+
+    #[test]
+    fn test_replay_allows() {
+        let result = check_tool_gates(&cmd(
+            "tool-gates",
+            &["replay", "transcripts/", "--compare-config", "new.toml"],
+        ));
+        assert_eq!(result.decision, Decision::Allow);
+    }
+
     #[test]
     fn test_hooks_status_allows() {
         let result = check_tool_gates(&cmd("tool-gates", &["hooks", "status"]));
@@ -0,0 +1,387 @@
+//! Wrapper command gate (env, timeout, nice, nohup, time, stdbuf, ionice, setsid, watch, chronic).
+//!
+//! Declarative - add wrappers to rules/wrappers.toml. The wrapper's own flags
+//! and positional args are skipped, then the inner command goes back through
+//! every gate via `check_single_command`. The reason shows the wrapper chain,
+//! e.g. "nice → timeout → cargo test".
+
+use crate::generated::rules::{WrapperSpec, wrapper_spec};
+use crate::models::{CommandInfo, Decision, GateResult, Redirect, RedirectOp};
+use crate::parser::extract_commands;
+use crate::router::check_single_command;
+
+/// What a wrapper ends up running
+enum Inner {
+    /// No inner command (`env`, `time`) - the wrapper just prints or exits
+    None,
+    /// A regular command (`timeout 60 cargo test`)
+    Command(CommandInfo),
+    /// A command line run through a shell or re-split (`watch 'ls | wc'`, `env -S '...'`)
+    Script(String),
+    /// Running processes picked by a process flag (`ionice -p PID`)
+    Processes,
+}
+
+/// Check wrapper commands by unwrapping and checking the inner command.
+pub fn check_wrappers(cmd: &CommandInfo) -> GateResult {
+    let Some(spec) = wrapper_spec(cmd) else {
+        return GateResult::skip();
+    };
+
+    match unwrap_command(cmd, spec) {
+        // `env` alone dumps the environment - not the wrapper gate's call
+        Inner::None if spec.bare_unhandled => GateResult::skip(),
+        Inner::None => GateResult::allow(),
+        Inner::Processes => GateResult::ask(format!(
+            "{}: Changes settings of running processes",
+            spec.program
+        )),
+        Inner::Command(inner) => describe(spec.program, &inner, check_single_command(&inner)),
+        Inner::Script(script) => {
+            let inner_commands = extract_commands(&script);
+            let mut strictest = GateResult::allow();
+            for inner in &inner_commands {
+                let result = describe(spec.program, inner, check_single_command(inner));
+                if result.decision > strictest.decision || strictest.reason.is_none() {
+                    strictest = result;
+                }
+            }
+            strictest
+        }
+    }
+}
+
+/// Skip the wrapper's own arguments and return the command it runs.
+///
+/// Files the wrapper writes through an output flag (`time -o FILE`) become
+/// write redirects on the inner command, so they get the same checks as `> FILE`.
+fn unwrap_command(cmd: &CommandInfo, spec: &WrapperSpec) -> Inner {
+    let args = &cmd.args;
+    let mut redirects = cmd.redirects.clone();
+    let mut i = 0;
+
+    while i < args.len() {
+        let arg = &args[i];
+        if arg == "--" {
+            i += 1;
+            break;
+        }
+        if spec.skip_assignments && is_assignment(arg) {
+            i += 1;
+            continue;
+        }
+        if !arg.starts_with('-') || arg == "-" {
+            break;
+        }
+        // The remaining args are process IDs, not a command
+        if is_process_flag(arg, spec) {
+            return Inner::Processes;
+        }
+        if let Some(target) = output_flag_value(args, i, spec) {
+            redirects.push(Redirect {
+                fd: None,
+                op: RedirectOp::Write,
+                target: target.to_string(),
+                body: None,
+            });
+        }
+
+        // Flag value is a whole command line: env -S 'cmd args' / --split-string='cmd args'
+        for flag in spec.command_flags {
+            let (value, next) = if arg == flag {
+                match args.get(i + 1) {
+                    Some(value) => (value.as_str(), i + 2),
+                    None => return Inner::None,
+                }
+            } else if let Some(value) = arg.strip_prefix(&format!("{flag}=")) {
+                (value, i + 1)
+            } else {
+                continue;
+            };
+            let mut script = value.to_string();
+            for rest in &args[next..] {
+                script.push(' ');
+                script.push_str(rest);
+            }
+            return Inner::Script(script);
+        }
+
+        i += if spec.flags_with_value.contains(&arg.as_str()) {
+            2
+        } else {
+            1
+        };
+    }
+
+    i += spec.positional;
+    if i >= args.len() {
+        return Inner::None;
+    }
+
+    if spec.shell_string {
+        return Inner::Script(args[i..].join(" "));
+    }
+
+    Inner::Command(CommandInfo {
+        raw: cmd.raw.clone(),
+        program: args[i].clone(),
+        args: args[i + 1..].to_vec(),
+        redirects,
+        flow: cmd.flow,
+    })
+}
+
+/// Whether a flag is one of the wrapper's process flags: `-p`, `--pid=1`, `-p1`,
+/// or a short flag cluster like `-tp`.
+fn is_process_flag(arg: &str, spec: &WrapperSpec) -> bool {
+    if spec.process_flags.iter().any(|flag| {
+        arg == *flag
+            || arg
+                .strip_prefix(*flag)
+                .is_some_and(|rest| rest.starts_with('='))
+    }) {
+        return true;
+    }
+    if arg.starts_with("--") {
+        return false;
+    }
+    // Short cluster - a flag that takes a value consumes the rest of the arg
+    for c in arg.chars().skip(1) {
+        let short = format!("-{c}");
+        if spec.process_flags.contains(&short.as_str()) {
+            return true;
+        }
+        if spec.flags_with_value.contains(&short.as_str()) {
+            return false;
+        }
+    }
+    false
+}
+
+/// The file named by an output flag at `args[i]`: `-o FILE`, `--output=FILE` or `-oFILE`.
+fn output_flag_value<'a>(args: &'a [String], i: usize, spec: &WrapperSpec) -> Option<&'a str> {
+    let arg = args[i].as_str();
+    for flag in spec.output_flags {
+        if arg == *flag {
+            return args.get(i + 1).map(String::as_str);
+        }
+        if let Some(value) = arg.strip_prefix(*flag) {
+            if flag.starts_with("--") {
+                if let Some(value) = value.strip_prefix('=') {
+                    return Some(value);
+                }
+            } else if !value.is_empty() {
+                return Some(value);
+            }
+        }
+    }
+    None
+}
+
+/// `NAME=value` environment assignment
+fn is_assignment(arg: &str) -> bool {
+    match arg.split_once('=') {
+        Some((name, _)) => {
+            !name.is_empty()
+                && !name.starts_with(|c: char| c.is_ascii_digit())
+                && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+        }
+        None => false,
+    }
+}
+
+/// Prefix the inner result's reason with the wrapper chain.
+fn describe(wrapper: &str, inner: &CommandInfo, result: GateResult) -> GateResult {
+    // Nested wrapper already describes the rest of the chain
+    if wrapper_spec(inner).is_some() {
+        if let Some(reason) = &result.reason {
+            return GateResult {
+                decision: result.decision,
+                reason: Some(format!("{wrapper} → {reason}")),
+            };
+        }
+    }
+
+    let program = inner.program.rsplit('/').next().unwrap_or(&inner.program);
+    let label = match inner.args.first() {
+        Some(sub) if !sub.starts_with('-') => format!("{program} {sub}"),
+        _ => program.to_string(),
+    };
+    let chain = format!("{wrapper} → {label}");
+
+    match result.decision {
+        Decision::Skip => GateResult::ask(format!("{chain}: Unknown command: {program}")),
+        Decision::Allow => GateResult::allow_with_reason(chain),
+        decision => GateResult {
+            decision,
+            reason: Some(match result.reason {
+                Some(reason) => format!("{chain}: {reason}"),
+                None => chain,
+            }),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gates::test_utils::cmd;
+
+    #[test]
+    fn test_timeout_cargo_test_allows_with_chain() {
+        let result = check_wrappers(&cmd("timeout", &["60", "cargo", "test"]));
+        assert_eq!(result.decision, Decision::Allow);
+        assert_eq!(result.reason.as_deref(), Some("timeout → cargo test"));
+    }
+
+    #[test]
+    fn test_timeout_flags_skipped() {
+        let result = check_wrappers(&cmd(
+            "timeout",
+            &["-s", "KILL", "--preserve-status", "5m", "git", "status"],
+        ));
+        assert_eq!(result.decision, Decision::Allow);
+        assert_eq!(result.reason.as_deref(), Some("timeout → git status"));
+    }
+
+    #[test]
+    fn test_env_rm_root_blocks() {
+        let result = check_wrappers(&cmd("env", &["rm", "-rf", "/"]));
+        assert_eq!(result.decision, Decision::Block);
+        assert!(result.reason.unwrap().starts_with("env → rm"));
+    }
+
+    #[test]
+    fn test_env_assignments_skipped() {
+        let result = check_wrappers(&cmd("env", &["-i", "FOO=1", "BAR=2", "rm", "-rf", "/"]));
+        assert_eq!(result.decision, Decision::Block);
+    }
+
+    #[test]
+    fn test_env_split_string() {
+        let result = check_wrappers(&cmd("env", &["-S", "rm -rf /"]));
+        assert_eq!(result.decision, Decision::Block);
+    }
+
+    #[test]
+    fn test_bare_wrapper_allows() {
+        for program in ["time", "nohup"] {
+            let result = check_wrappers(&cmd(program, &[]));
+            assert_eq!(result.decision, Decision::Allow, "Failed for: {program}");
+        }
+    }
+
+    #[test]
+    fn test_bare_env_unhandled() {
+        for args in [&[][..], &["-u", "HOME"][..], &["FOO=1"][..]] {
+            let result = check_wrappers(&cmd("env", args));
+            assert_eq!(result.decision, Decision::Skip, "Failed for: {args:?}");
+        }
+    }
+
+    #[test]
+    fn test_nested_wrappers_chain() {
+        let result = check_wrappers(&cmd(
+            "nice",
+            &["-n", "10", "timeout", "60", "npm", "install"],
+        ));
+        assert_eq!(result.decision, Decision::Ask);
+        let reason = result.reason.unwrap();
+        assert!(
+            reason.starts_with("nice → timeout → npm install"),
+            "got: {reason}"
+        );
+    }
+
+    #[test]
+    fn test_unknown_inner_command_asks() {
+        let result = check_wrappers(&cmd("nohup", &["mystery-tool", "--serve"]));
+        assert_eq!(result.decision, Decision::Ask);
+        assert!(
+            result
+                .reason
+                .unwrap()
+                .contains("Unknown command: mystery-tool")
+        );
+    }
+
+    #[test]
+    fn test_watch_shell_string() {
+        let result = check_wrappers(&cmd("watch", &["-n", "2", "ls -la | wc -l"]));
+        assert_eq!(result.decision, Decision::Allow);
+
+        let result = check_wrappers(&cmd("watch", &["rm -rf /"]));
+        assert_eq!(result.decision, Decision::Block);
+    }
+
+    #[test]
+    fn test_other_wrappers_unwrap() {
+        for (program, args) in [
+            ("stdbuf", &["-oL", "rm", "-rf", "/"][..]),
+            ("ionice", &["-c", "3", "rm", "-rf", "/"][..]),
+            ("setsid", &["-f", "rm", "-rf", "/"][..]),
+            ("chronic", &["rm", "-rf", "/"][..]),
+            ("time", &["-p", "rm", "-rf", "/"][..]),
+            ("/usr/bin/env", &["rm", "-rf", "/"][..]),
+        ] {
+            let result = check_wrappers(&cmd(program, args));
+            assert_eq!(result.decision, Decision::Block, "Failed for: {program}");
+        }
+    }
+
+    #[test]
+    fn test_process_flags_ask() {
+        for args in [
+            &["-c", "3", "-p", "1234"][..],
+            &["-p", "1234", "5678"][..],
+            &["-P", "100"][..],
+            &["--uid=1000"][..],
+            &["-p1234"][..],
+            &["-tp", "1234"][..],
+        ] {
+            let result = check_wrappers(&cmd("ionice", args));
+            assert_eq!(result.decision, Decision::Ask, "Failed for: {args:?}");
+            assert_eq!(
+                result.reason.as_deref(),
+                Some("ionice: Changes settings of running processes")
+            );
+        }
+
+        // A class value isn't a cluster of flags
+        let result = check_wrappers(&cmd("ionice", &["-c3", "ls"]));
+        assert_eq!(result.decision, Decision::Allow);
+    }
+
+    #[test]
+    fn test_output_flag_checked_as_redirect() {
+        for (program, args) in [
+            ("time", &["-o", "~/.bashrc", "true"][..]),
+            ("/usr/bin/time", &["--output=/etc/profile", "ls"][..]),
+            ("time", &["--output", "times.txt", "ls"][..]),
+            ("time", &["-otimes.txt", "-p", "ls"][..]),
+        ] {
+            let result = check_wrappers(&cmd(program, args));
+            assert_eq!(result.decision, Decision::Ask, "Failed for: {args:?}");
+            assert!(
+                result
+                    .reason
+                    .unwrap()
+                    .contains("Output redirection (writes to"),
+                "Failed for: {args:?}"
+            );
+        }
+
+        let result = check_wrappers(&cmd("time", &["-o", "/dev/sda", "ls"]));
+        assert_eq!(result.decision, Decision::Block);
+
+        // stdbuf -o sets buffering, it doesn't name a file
+        let result = check_wrappers(&cmd("stdbuf", &["-o", "L", "ls"]));
+        assert_eq!(result.decision, Decision::Allow);
+    }
+
+    #[test]
+    fn test_non_wrapper_skips() {
+        let result = check_wrappers(&cmd("ls", &["-la"]));
+        assert_eq!(result.decision, Decision::Skip);
+    }
+}
@@ -166,6 +166,80 @@ pub fn is_security_warning_new(session_id: &str, key: &str) -> bool {
     is_new
 }
 
+/// Per-session dedup state used by the router.
+///
+/// [`DiskSessionStore`] is the hook default (global tracker persisted to
+/// `hint-tracker.json`). [`MemorySessionStore`] keeps state in process for
+/// embedders and hermetic tests. An empty `session_id` disables dedup.
+pub trait SessionStore: Send + Sync {
+    /// Retain only hints not yet emitted this session.
+    fn filter_hints(&self, session_id: &str, hints: &mut Vec<crate::hints::ModernHint>);
+    /// Whether this is the first "ask" decision this session.
+    fn is_first_ask(&self, session_id: &str) -> bool;
+    /// Whether a security warning key is new for this session.
+    fn is_security_warning_new(&self, session_id: &str, key: &str) -> bool;
+}
+
+/// Session store backed by the global on-disk tracker
+#[derive(Debug, Default, Clone, Copy)]
+pub struct DiskSessionStore;
+
+impl SessionStore for DiskSessionStore {
+    fn filter_hints(&self, session_id: &str, hints: &mut Vec<crate::hints::ModernHint>) {
+        filter_hints(session_id, hints);
+    }
+
+    fn is_first_ask(&self, session_id: &str) -> bool {
+        is_first_ask(session_id)
+    }
+
+    fn is_security_warning_new(&self, session_id: &str, key: &str) -> bool {
+        is_security_warning_new(session_id, key)
+    }
+}
+
+/// In-memory session store (never touches disk)
+#[derive(Debug, Default)]
+pub struct MemorySessionStore {
+    tracker: Mutex<HintTracker>,
+}
+
+impl MemorySessionStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Lock the tracker, resetting it when the session changes.
+    fn session(&self, session_id: &str) -> std::sync::MutexGuard<'_, HintTracker> {
+        let mut tracker = self.tracker.lock().unwrap();
+        if tracker.session_id != session_id {
+            *tracker = HintTracker {
+                session_id: session_id.to_string(),
+                ..Default::default()
+            };
+        }
+        tracker
+    }
+}
+
+impl SessionStore for MemorySessionStore {
+    fn filter_hints(&self, session_id: &str, hints: &mut Vec<crate::hints::ModernHint>) {
+        if session_id.is_empty() || hints.is_empty() {
+            return;
+        }
+        let mut tracker = self.session(session_id);
+        hints.retain(|h| tracker.is_hint_new(h.legacy_command));
+    }
+
+    fn is_first_ask(&self, session_id: &str) -> bool {
+        session_id.is_empty() || self.session(session_id).is_first_ask()
+    }
+
+    fn is_security_warning_new(&self, session_id: &str, key: &str) -> bool {
+        session_id.is_empty() || self.session(session_id).is_security_warning_new(key)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -421,4 +495,24 @@ mod tests {
         assert!(loaded.security_warnings.is_empty());
         assert!(loaded.hints.contains("cat"));
     }
+
+    #[test]
+    fn test_memory_store_dedups_per_session() {
+        let store = MemorySessionStore::new();
+        assert!(store.is_first_ask("mem-1"));
+        assert!(!store.is_first_ask("mem-1"));
+        assert!(store.is_security_warning_new("mem-1", "k"));
+        assert!(!store.is_security_warning_new("mem-1", "k"));
+
+        // New session resets state
+        assert!(store.is_first_ask("mem-2"));
+        assert!(store.is_security_warning_new("mem-2", "k"));
+    }
+
+    #[test]
+    fn test_memory_store_empty_session_never_dedups() {
+        let store = MemorySessionStore::new();
+        assert!(store.is_first_ask(""));
+        assert!(store.is_first_ask(""));
+    }
 }
@@ -5,15 +5,16 @@
 //!
 //! Hints are only shown if the modern tool is actually installed (checked via cache).
 
+use crate::config::Config;
 use crate::models::CommandInfo;
-use crate::tool_cache::{ToolCache, get_cache};
+use crate::tool_cache::{ToolAvailability, ToolCache, get_cache};
 use std::sync::OnceLock;
 
 /// Global tool cache - loaded once per process
 static TOOL_CACHE: OnceLock<ToolCache> = OnceLock::new();
 
 /// Get the tool cache (loads from disk on first call)
-fn cache() -> &'static ToolCache {
+pub fn cache() -> &'static ToolCache {
     TOOL_CACHE.get_or_init(get_cache)
 }
 
@@ -29,8 +30,15 @@ pub struct ModernHint {
 /// Only returns hints for tools that are actually installed.
 /// Respects `features.hints` toggle and `hints.disable` list from config.
 pub fn get_modern_hint(cmd: &CommandInfo) -> Option<ModernHint> {
-    let config = crate::config::get();
+    modern_hint(cmd, crate::config::get(), cache())
+}
 
+/// Same as [`get_modern_hint`] with an explicit config and tool availability source.
+pub fn modern_hint(
+    cmd: &CommandInfo,
+    config: &Config,
+    tools: &dyn ToolAvailability,
+) -> Option<ModernHint> {
     // Global hints toggle
     if !config.features.hints {
         return None;
@@ -75,7 +83,7 @@ pub fn get_modern_hint(cmd: &CommandInfo) -> Option<ModernHint> {
     }?;
 
     // Only return hint if the modern tool is installed
-    if cache().is_available(hint.modern_command) {
+    if tools.is_available(hint.modern_command) {
         Some(hint)
     } else {
         None
@@ -605,6 +613,8 @@ mod tests {
             program: program.to_string(),
             args: args.iter().map(|s| s.to_string()).collect(),
             raw: format!("{} {}", program, args.join(" ")),
+            redirects: Vec::new(),
+            flow: Default::default(),
         }
     }
 
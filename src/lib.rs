@@ -21,9 +21,17 @@
 //! let decision = &output.hook_specific_output.as_ref().unwrap().permission_decision;
 //! assert_eq!(decision, "deny");
 //! ```
+//!
+//! Embedders that need to supply their own config, settings or session state
+//! should use [`Engine`] instead, which returns a typed [`Verdict`].
 
+pub mod audit;
+pub mod blast_radius;
 pub mod cache;
 pub mod config;
+pub mod declined;
+pub mod engine;
+pub mod explain;
 pub mod file_guards;
 pub mod gates;
 pub mod generated;
@@ -37,16 +45,27 @@ pub mod patterns;
 pub mod pending;
 pub mod permission_request;
 pub mod post_tool_use;
+pub mod pre_tool_use;
+pub mod replay;
+pub mod repo_policy;
 pub mod router;
+pub mod rules_lint;
 pub mod security_reminders;
+pub mod session_approvals;
 pub mod settings;
 pub mod settings_writer;
+pub mod source_mask;
+pub mod stats;
 pub mod toml_export;
 pub mod tool_blocks;
 pub mod tool_cache;
+pub mod trace;
 pub mod tracking;
 pub mod tui;
+pub mod user_rules;
+pub mod workdir;
 
+pub use engine::{Engine, EngineBuilder, SegmentVerdict, Verdict};
 pub use models::{CommandInfo, Decision, GateResult};
 pub use permission_request::handle_permission_request;
 pub use router::{
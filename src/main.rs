@@ -19,20 +19,30 @@
 
 use std::env;
 use std::io::{self, Read};
+use std::time::Instant;
+use tool_gates::audit::{self, AuditEntry, LogFilter};
+use tool_gates::blast_radius::BlastRadius;
 use tool_gates::config;
-use tool_gates::file_guards::check_file_guard;
-use tool_gates::models::{HookInput, HookOutput, PermissionRequestInput, PostToolUseInput};
-use tool_gates::patterns::suggest_patterns;
+use tool_gates::declined::{pattern_stats, read_declined};
+use tool_gates::engine::Engine;
+use tool_gates::models::{
+    CommandInfo, Decision, HookInput, HookOutput, PermissionRequestDecision,
+    PermissionRequestInput, PostToolUseInput,
+};
+use tool_gates::patterns::{merge_suggestions, suggest_patterns};
 use tool_gates::pending::{clear_pending, pending_count, read_pending};
 use tool_gates::permission_request::handle_permission_request;
 use tool_gates::post_tool_use::handle_post_tool_use;
-use tool_gates::router::check_command_with_settings_and_session;
-use tool_gates::security_reminders::check_security_reminders;
+use tool_gates::pre_tool_use::{
+    PreToolUseResult, decide_pre_tool_use, extract_file_paths_from_map,
+};
+use tool_gates::replay::{self, ReplayReport, Replayer};
+use tool_gates::session_approvals::{self, SessionApproval, SessionApprovals};
 use tool_gates::settings_writer::{
-    RuleType, Scope, add_rule, list_all_rules, list_rules, remove_rule,
+    RuleType, Scope, add_rule, list_all_rules, list_rules, remove_duplicate_rules, remove_rule,
 };
+use tool_gates::stats::Stats;
 use tool_gates::toml_export;
-use tool_gates::tool_blocks::check_tool_block;
 use tool_gates::tool_cache;
 use tool_gates::tracking::{CommandPart, track_ask_command};
 use tool_gates::tui::run_review;
@@ -64,6 +74,31 @@ fn main() {
         return;
     }
 
+    if args.len() > 1 && args[1] == "explain" {
+        handle_explain_subcommand(&args[2..]);
+        return;
+    }
+
+    if args.len() > 1 && args[1] == "log" {
+        handle_log_subcommand(&args[2..]);
+        return;
+    }
+
+    if args.len() > 1 && args[1] == "stats" {
+        handle_stats_subcommand(&args[2..]);
+        return;
+    }
+
+    if args.len() > 1 && args[1] == "replay" {
+        handle_replay_subcommand(&args[2..]);
+        return;
+    }
+
+    if args.len() > 1 && args[1] == "trust" {
+        handle_trust_subcommand(&args[2..]);
+        return;
+    }
+
     if args.len() > 1 && args[1] == "doctor" {
         handle_doctor_subcommand();
         return;
@@ -71,7 +106,8 @@ fn main() {
 
     if args.len() > 1 && args[1] == "review" {
         let show_all = args.iter().any(|a| a == "--all" || a == "-a");
-        handle_review_subcommand(show_all);
+        let declined = args.iter().any(|a| a == "--declined");
+        handle_review_subcommand(show_all, declined);
         return;
     }
 
@@ -157,6 +193,7 @@ fn main() {
 
 /// Handle PreToolUse hook -- routes all tool types
 fn handle_pre_tool_use_hook(input: &str) {
+    let started = Instant::now();
     let hook_input: HookInput = match serde_json::from_str(input) {
         Ok(hi) => hi,
         Err(e) => {
@@ -166,9 +203,14 @@ fn handle_pre_tool_use_hook(input: &str) {
         }
     };
 
-    let config = config::load();
+    let engine = Engine::builder()
+        .config(config::load())
+        .cwd(hook_input.cwd.as_str())
+        .permission_mode(hook_input.permission_mode.as_str())
+        .session_id(hook_input.session_id.as_str())
+        .build();
+    let config = engine.config();
 
-    // Check configurable block rules first (applies to ALL tool types)
     // Re-extract tool_input as raw map since Structured variant
     // drops unknown fields (url, pattern, etc.)
     let tool_input_map = serde_json::from_str::<serde_json::Value>(input)
@@ -179,185 +221,115 @@ fn handle_pre_tool_use_hook(input: &str) {
             _ => None,
         })
         .unwrap_or_default();
-    if let Some(output) =
-        check_tool_block(&hook_input.tool_name, &tool_input_map, config.block_rules())
-    {
-        if let Ok(json) = serde_json::to_string(&output) {
-            println!("{json}");
-        } else {
-            println!(
-                r#"{{"hookSpecificOutput":{{"hookEventName":"PreToolUse","permissionDecision":"deny","permissionDecisionReason":"Internal error serializing block deny"}}}}"#
+
+    // Trace only for the audit log, to name the deciding rule
+    let mut result =
+        decide_pre_tool_use(&engine, &hook_input, &tool_input_map, config.audit.enabled);
+    if let Some(output) = &result.output {
+        print_hook_output(output);
+    }
+
+    if config.audit.enabled {
+        let mut entry = AuditEntry::new(
+            "PreToolUse",
+            &hook_input.tool_name,
+            &hook_input.session_id,
+            &audit_project(&hook_input.cwd),
+        )
+        .with_output(result.output.as_ref())
+        .with_elapsed(started);
+        if hook_input.tool_name == "Bash" {
+            // A command denied for containing a secret is logged with it redacted
+            entry.command = Some(
+                result
+                    .redacted_command
+                    .take()
+                    .unwrap_or_else(|| hook_input.get_command()),
             );
+        } else {
+            entry.paths = extract_file_paths_from_map(&tool_input_map);
         }
-        return;
+        entry.source = result.source.take();
+        entry.hints = std::mem::take(&mut result.hints);
+        audit::record(&config.audit, &entry);
     }
 
-    // Route by tool type
-    match hook_input.tool_name.as_str() {
-        // Bash tools: full gate engine
-        "Bash" => {
-            if !config.features.bash_gates {
-                print_no_opinion();
-                return;
-            }
-            handle_bash_pre_tool_use(&hook_input);
-        }
-        // File tools: symlink guard + security reminders
-        "Read" | "Write" | "Edit" | "MultiEdit" => {
-            // 1. File guards: symlink check for AI config files
-            if config.features.file_guards {
-                let file_paths = extract_file_paths_from_map(&tool_input_map);
-                for file_path in &file_paths {
-                    if let Some(output) =
-                        check_file_guard(file_path, &hook_input.tool_name, &config.file_guards)
-                    {
-                        if let Ok(json) = serde_json::to_string(&output) {
-                            println!("{json}");
-                        } else {
-                            println!(
-                                r#"{{"hookSpecificOutput":{{"hookEventName":"PreToolUse","permissionDecision":"deny","permissionDecisionReason":"Internal error serializing file guard deny"}}}}"#
-                            );
-                        }
-                        return;
-                    }
-                }
-            }
-
-            // 2. Security reminders: content scanning for Write/Edit/MultiEdit
-            if config.features.security_reminders && hook_input.tool_name != "Read" {
-                if let Some(output) = check_security_reminders(
-                    &hook_input.tool_name,
-                    &tool_input_map,
-                    &config.security_reminders,
-                    &hook_input.session_id,
-                ) {
-                    if let Ok(json) = serde_json::to_string(&output) {
-                        println!("{json}");
-                    } else {
-                        println!(
-                            r#"{{"hookSpecificOutput":{{"hookEventName":"PreToolUse","permissionDecision":"deny","permissionDecisionReason":"Internal error serializing security reminder"}}}}"#
-                        );
-                    }
-                }
-            }
-            // No output = allow (pass through)
-        }
-        // Skill tool: auto-approve based on config rules
-        "Skill" => {
-            if !config.auto_approve_skills.is_empty() {
-                let skill_name = tool_input_map
-                    .get("skill")
-                    .and_then(|v| v.as_str())
-                    .unwrap_or("");
-                let project_dir = std::env::var("CLAUDE_PROJECT_DIR").unwrap_or_default();
-
-                for rule in &config.auto_approve_skills {
-                    if rule.matches_skill(skill_name) && rule.conditions_met(&project_dir) {
-                        let reason = rule
-                            .message
-                            .as_deref()
-                            .and_then(|m| if m.is_empty() { None } else { Some(m) });
-                        let output = HookOutput::allow(reason);
-                        if let Ok(json) = serde_json::to_string(&output) {
-                            println!("{json}");
-                        }
-                        return;
-                    }
-                }
-            }
-            // No match = pass through (no opinion)
-        }
-        // All other tools: pass through (blocks already checked above)
-        _ => {}
-    }
+    track_ask(&hook_input, result);
 }
 
-/// Extract all file paths from a raw tool_input map.
-/// Handles both single-file tools (file_path) and MultiEdit (files[].file_path).
-fn extract_file_paths_from_map(map: &serde_json::Map<String, serde_json::Value>) -> Vec<String> {
-    let mut paths = Vec::new();
-
-    // Single file_path (Read/Write/Edit)
-    if let Some(fp) = map.get("file_path").and_then(|v| v.as_str()) {
-        if !fp.is_empty() {
-            paths.push(fp.to_string());
-        }
-    }
-
-    // MultiEdit: files[].file_path
-    if let Some(files) = map.get("files").and_then(|v| v.as_array()) {
-        for file in files {
-            if let Some(fp) = file.get("file_path").and_then(|v| v.as_str()) {
-                if !fp.is_empty() {
-                    paths.push(fp.to_string());
-                }
+/// Print a PreToolUse output. If it can't be serialized, a deny still denies.
+fn print_hook_output(output: &HookOutput) {
+    match serde_json::to_string(output) {
+        Ok(json) => println!("{json}"),
+        Err(e) => {
+            eprintln!("Error serializing output: {e}");
+            let denied = output
+                .hook_specific_output
+                .as_ref()
+                .is_some_and(|hso| hso.permission_decision == "deny");
+            if denied {
+                println!(
+                    r#"{{"hookSpecificOutput":{{"hookEventName":"PreToolUse","permissionDecision":"deny","permissionDecisionReason":"Internal error serializing deny"}}}}"#
+                );
+            } else {
+                print_no_opinion();
             }
         }
     }
+}
 
-    paths
+/// Project directory recorded in the audit log
+fn audit_project(cwd: &str) -> String {
+    std::env::var("CLAUDE_PROJECT_DIR")
+        .ok()
+        .filter(|dir| !dir.is_empty())
+        .unwrap_or_else(|| cwd.to_string())
 }
 
-/// Handle Bash-specific PreToolUse logic (gate engine + tracking)
-fn handle_bash_pre_tool_use(hook_input: &HookInput) {
-    let command = hook_input.get_command();
-    if command.is_empty() {
-        print_no_opinion();
+/// Track an asked Bash command for PostToolUse correlation (pending and declined queues)
+fn track_ask(hook_input: &HookInput, result: PreToolUseResult) {
+    if hook_input.tool_name != "Bash"
+        || result.decision() != "ask"
+        || hook_input.tool_use_id.is_empty()
+    {
         return;
     }
+    let command = hook_input.get_command();
+    let reason = result
+        .output
+        .as_ref()
+        .and_then(|o| o.hook_specific_output.as_ref())
+        .and_then(|hso| hso.permission_decision_reason.clone())
+        .unwrap_or_else(|| "Requires approval".to_string());
+
+    // Reuse the parsed segments; only task expansion and raw patterns leave them empty
+    let commands: Vec<CommandInfo> = if result.segments.is_empty() {
+        tool_gates::parser::extract_commands(&command)
+    } else {
+        result.segments.into_iter().map(|s| s.command).collect()
+    };
+    let suggested_patterns: Vec<String> = commands.iter().flat_map(suggest_patterns).collect();
+
+    let breakdown: Vec<CommandPart> = commands
+        .iter()
+        .filter(|cmd| !cmd.program.is_empty())
+        .map(|cmd| CommandPart::new(&cmd.program, &cmd.args, tool_gates::Decision::Ask, &reason))
+        .collect();
 
-    // Check command with settings.json awareness, mode detection, and session hint dedup
-    let output = check_command_with_settings_and_session(
+    track_ask_command(
+        &hook_input.tool_use_id,
         &command,
+        suggested_patterns,
+        breakdown,
+        &hook_input.project_id(),
         &hook_input.cwd,
-        &hook_input.permission_mode,
         &hook_input.session_id,
     );
-
-    // If the result is "ask", track it for PostToolUse correlation
-    if let Some(ref hso) = output.hook_specific_output {
-        if hso.permission_decision == "ask" && !hook_input.tool_use_id.is_empty() {
-            let commands = tool_gates::parser::extract_commands(&command);
-            let suggested_patterns: Vec<String> =
-                commands.iter().flat_map(suggest_patterns).collect();
-
-            let breakdown: Vec<CommandPart> = commands
-                .iter()
-                .map(|cmd| {
-                    CommandPart::new(
-                        &cmd.program,
-                        &cmd.args,
-                        tool_gates::Decision::Ask,
-                        hso.permission_decision_reason
-                            .as_deref()
-                            .unwrap_or("Requires approval"),
-                    )
-                })
-                .collect();
-
-            track_ask_command(
-                &hook_input.tool_use_id,
-                &command,
-                suggested_patterns,
-                breakdown,
-                &hook_input.project_id(),
-                &hook_input.cwd,
-                &hook_input.session_id,
-            );
-        }
-    }
-
-    match serde_json::to_string(&output) {
-        Ok(json) => println!("{json}"),
-        Err(e) => {
-            eprintln!("Error serializing output: {e}");
-            print_no_opinion();
-        }
-    }
 }
 
 /// Handle PermissionRequest hook (for subagent approval)
 fn handle_permission_request_hook(input: &str) {
+    let started = Instant::now();
     let perm_input: PermissionRequestInput = match serde_json::from_str(input) {
         Ok(pi) => pi,
         Err(e) => {
@@ -374,8 +346,9 @@ fn handle_permission_request_hook(input: &str) {
     }
 
     // Check if we should approve this
-    if let Some(output) = handle_permission_request(&perm_input) {
-        match serde_json::to_string(&output) {
+    let output = handle_permission_request(&perm_input);
+    if let Some(ref output) = output {
+        match serde_json::to_string(output) {
             Ok(json) => println!("{json}"),
             Err(e) => {
                 eprintln!("Error serializing PermissionRequest output: {e}");
@@ -384,10 +357,33 @@ fn handle_permission_request_hook(input: &str) {
         }
     }
     // If None, we don't output anything - lets the normal permission prompt show
+
+    let config = config::load();
+    if config.audit.enabled {
+        let mut entry = AuditEntry::new(
+            "PermissionRequest",
+            &perm_input.tool_name,
+            &perm_input.session_id,
+            &audit_project(&perm_input.cwd),
+        )
+        .with_elapsed(started);
+        entry.command = Some(perm_input.get_command());
+        if let Some(output) = output {
+            match output.hook_specific_output.decision {
+                PermissionRequestDecision::Allow { .. } => entry.decision = "allow".to_string(),
+                PermissionRequestDecision::Deny { message, .. } => {
+                    entry.decision = "deny".to_string();
+                    entry.reason = message;
+                }
+            }
+        }
+        audit::record(&config.audit, &entry);
+    }
 }
 
 /// Handle PostToolUse hook (for tracking successful executions + security reminders)
 fn handle_post_tool_use_hook(input: &str) {
+    let started = Instant::now();
     let post_input: PostToolUseInput = match serde_json::from_str(input) {
         Ok(pi) => pi,
         Err(e) => {
@@ -396,9 +392,24 @@ fn handle_post_tool_use_hook(input: &str) {
         }
     };
 
+    let config = config::load();
+    let mut entry = AuditEntry::new(
+        "PostToolUse",
+        &post_input.tool_name,
+        &post_input.session_id,
+        &audit_project(&post_input.cwd),
+    );
+
     match post_input.tool_name.as_str() {
         // Bash: track successful executions for approval learning
         "Bash" => {
+            entry.command = Some(post_input.get_command());
+            entry.decision = if post_input.is_success() {
+                "succeeded"
+            } else {
+                "failed"
+            }
+            .to_string();
             if let Some(output) = handle_post_tool_use(&post_input) {
                 if let Ok(json) = serde_json::to_string(&output) {
                     println!("{json}");
@@ -407,10 +418,6 @@ fn handle_post_tool_use_hook(input: &str) {
         }
         // File tools: post-write security scanning (Tier 2 anti-patterns)
         "Write" | "Edit" | "MultiEdit" => {
-            let config = config::load();
-            if !config.features.security_reminders {
-                return;
-            }
             // Re-extract tool_input as raw map
             let tool_input_map = serde_json::from_str::<serde_json::Value>(input)
                 .ok()
@@ -420,19 +427,32 @@ fn handle_post_tool_use_hook(input: &str) {
                     _ => None,
                 })
                 .unwrap_or_default();
-            if let Some(output) = tool_gates::security_reminders::check_security_reminders_post(
-                &post_input.tool_name,
-                &tool_input_map,
-                &config.security_reminders,
-                &post_input.session_id,
-            ) {
-                if let Ok(json) = serde_json::to_string(&output) {
-                    println!("{json}");
+            entry.paths = extract_file_paths_from_map(&tool_input_map);
+            if config.features.security_reminders {
+                if let Some(output) = tool_gates::security_reminders::check_security_reminders_post(
+                    &post_input.tool_name,
+                    &tool_input_map,
+                    post_input.original_file(),
+                    &config.security_reminders,
+                    &tool_gates::security_reminders::ReminderRules::load(&post_input.cwd),
+                    &tool_gates::hint_tracker::DiskSessionStore,
+                    &post_input.session_id,
+                ) {
+                    entry.reason = output
+                        .hook_specific_output
+                        .as_ref()
+                        .and_then(|hso| hso.additional_context.clone());
+                    entry.source = Some("security_reminders".to_string());
+                    if let Ok(json) = serde_json::to_string(&output) {
+                        println!("{json}");
+                    }
                 }
             }
         }
         _ => {}
     }
+
+    audit::record(&config.audit, &entry.with_elapsed(started));
 }
 
 fn get_binary_path() -> String {
@@ -780,6 +800,11 @@ fn print_main_help() {
     eprintln!("  tool-gates rules <command>   List/remove permission rules");
     eprintln!("  tool-gates pending <command> Manage pending approval queue");
     eprintln!("  tool-gates review            Interactive TUI for pending approvals");
+    eprintln!("  tool-gates explain '<cmd>'   Show the decision trace for a command");
+    eprintln!("  tool-gates log [filters]     Query the decision audit log");
+    eprintln!("  tool-gates stats             Report frequent asks and rule coverage");
+    eprintln!("  tool-gates replay <path>     Re-run transcript tool calls through the rules");
+    eprintln!("  tool-gates trust [path]      Trust this repository's .tool-gates.toml");
     eprintln!("  tool-gates doctor            Check config, hooks, and cache health");
     eprintln!("  tool-gates --export-toml     Export Gemini CLI policy rules");
     eprintln!("  tool-gates --refresh-tools   Refresh modern CLI tool detection");
@@ -793,9 +818,16 @@ fn print_main_help() {
     eprintln!("  approve <pattern> -s <scope> Add allow rule for command pattern");
     eprintln!("  rules list                   List all permission rules");
     eprintln!("  rules remove <pattern>       Remove a permission rule");
+    eprintln!("  rules lint [--fix]           Check settings.json rules for problems");
     eprintln!("  pending list                 List pending approvals");
     eprintln!("  pending clear                Clear pending approval queue");
     eprintln!("  review                       Interactive TUI for pending approvals");
+    eprintln!("  review --declined            Review declined commands (deny suggestions)");
+    eprintln!("  explain '<cmd>' [--json]     Show which gates, rules and settings decided");
+    eprintln!("  log [--decision deny]        Show audited hook decisions");
+    eprintln!("  stats [--since 7d]           Report frequent asks, unknown programs, hints");
+    eprintln!("  replay <transcripts>         Re-run recorded tool calls through current rules");
+    eprintln!("  trust [--revoke]             Enable .tool-gates.toml allow rules");
     eprintln!();
     eprintln!("SCOPES:");
     eprintln!("  user     ~/.claude/settings.json (global, recommended)");
@@ -852,8 +884,11 @@ fn handle_approve_subcommand(args: &[String]) {
         return;
     }
 
-    // Find the pattern (first non-flag argument)
-    let pattern = args.iter().find(|a| !a.starts_with('-'));
+    // Find the pattern (first argument that is neither a flag nor a flag's value)
+    let pattern = args.iter().enumerate().find_map(|(i, a)| {
+        let is_value = i > 0 && APPROVE_VALUE_FLAGS.contains(&args[i - 1].as_str());
+        (!a.starts_with('-') && !is_value).then_some(a)
+    });
 
     let Some(pattern) = pattern else {
         eprintln!("Error: Pattern is required");
@@ -869,6 +904,19 @@ fn handle_approve_subcommand(args: &[String]) {
         std::process::exit(1);
     }
 
+    if args.iter().any(|a| a == "--preview") {
+        print_blast_radius(&BlastRadius::load(pattern));
+        return;
+    }
+
+    if args
+        .iter()
+        .any(|a| a == "--session" || a == "--for" || a == "--session-id")
+    {
+        handle_temporary_approval(pattern, args);
+        return;
+    }
+
     // Parse --scope option
     let scope_str = args
         .iter()
@@ -877,7 +925,7 @@ fn handle_approve_subcommand(args: &[String]) {
         .map(|s| s.as_str());
 
     let Some(scope_str) = scope_str else {
-        eprintln!("Error: --scope (-s) is required");
+        eprintln!("Error: --scope (-s), --session or --for is required");
         eprintln!();
         print_approve_help();
         std::process::exit(1);
@@ -932,6 +980,14 @@ fn handle_approve_subcommand(args: &[String]) {
         Ok(_) => {
             eprintln!("✓ Added {} rule: {}", rule_type.as_str(), formatted);
             eprintln!("  Scope: {} ({})", scope.as_str(), scope.path().display());
+            if rule_type == RuleType::Allow {
+                let warnings = BlastRadius::load(pattern).warnings().count();
+                if warnings > 0 {
+                    eprintln!(
+                        "  Warning: also matches {warnings} high-risk command(s). See: tool-gates approve '{pattern}' --preview"
+                    );
+                }
+            }
         }
         Err(e) => {
             eprintln!("Error: Failed to add rule: {}", e);
@@ -940,25 +996,170 @@ fn handle_approve_subcommand(args: &[String]) {
     }
 }
 
+/// `approve --preview`: what an allow rule for the pattern would cover
+fn print_blast_radius(radius: &BlastRadius) {
+    eprintln!("Preview: Bash({})", radius.pattern);
+    if radius.matches.is_empty() {
+        eprintln!("  Matches no pending, declined or known risky commands the gates ask about.");
+        return;
+    }
+    eprintln!();
+    eprintln!("Would auto-allow:");
+    for m in &radius.matches {
+        let marker = match (m.decision, m.warning) {
+            (Decision::Block, _) => "✗",
+            (_, true) => "!",
+            _ => " ",
+        };
+        eprintln!("  {marker} {}  ({})", m.command, m.source.as_str());
+        if m.warning {
+            if let Some(reason) = &m.reason {
+                eprintln!("      {reason}");
+            }
+        }
+    }
+
+    let blocked = radius
+        .matches
+        .iter()
+        .filter(|m| m.decision == Decision::Block)
+        .count();
+    let warnings = radius.warnings().count();
+    eprintln!();
+    if warnings > 0 {
+        eprintln!("Warning: {warnings} high-risk match(es) (!: warned ask rule, ✗: gate block).");
+    }
+    if blocked > 0 {
+        eprintln!(
+            "tool-gates still blocks ✗ commands, but Claude Code applies the rule wherever the hook isn't running."
+        );
+    }
+}
+
+/// `approve` options that take a value
+const APPROVE_VALUE_FLAGS: [&str; 6] = ["--scope", "-s", "--type", "-t", "--for", "--session-id"];
+
+/// `approve --session` / `--for`: store a temporary approval instead of a settings.json rule
+fn handle_temporary_approval(pattern: &str, args: &[String]) {
+    let value_of = |flags: &[&str]| {
+        args.iter()
+            .position(|a| flags.contains(&a.as_str()))
+            .map(|i| args.get(i + 1).map(String::as_str))
+    };
+
+    if value_of(&["--scope", "-s"]).is_some() {
+        eprintln!("Error: --scope cannot be combined with --session or --for");
+        std::process::exit(1);
+    }
+    if let Some(rule_type) = value_of(&["--type", "-t"]) {
+        if rule_type != Some("allow") {
+            eprintln!("Error: Temporary approvals can only allow; use -s <scope> for ask or deny");
+            std::process::exit(1);
+        }
+    }
+
+    let now = chrono::Utc::now();
+    let expires_at = match value_of(&["--for"]) {
+        None => None,
+        Some(value) => match session_approvals::expires_at(now, value.unwrap_or_default()) {
+            Ok(expires_at) => Some(expires_at),
+            Err(e) => {
+                eprintln!("Error: Invalid duration for --for: {e}");
+                std::process::exit(1);
+            }
+        },
+    };
+
+    let project = std::env::current_dir()
+        .map(|p| p.to_string_lossy().to_string())
+        .unwrap_or_else(|_| ".".to_string());
+
+    let session_id = if args.iter().any(|a| a == "--session" || a == "--session-id") {
+        let explicit = match value_of(&["--session-id"]) {
+            Some(Some(id)) => Some(id.to_string()),
+            Some(None) => {
+                eprintln!("Error: --session-id requires a value");
+                std::process::exit(1);
+            }
+            None => None,
+        };
+        let Some(id) = explicit.or_else(|| session_approvals::latest_session(&project)) else {
+            eprintln!("Error: No pending approvals from a session in {project}");
+            eprintln!("  Pass the session ID with --session-id <id>");
+            std::process::exit(1);
+        };
+        Some(id)
+    } else {
+        None
+    };
+
+    let approval = SessionApproval {
+        pattern: tool_gates::settings_writer::parse_pattern(pattern),
+        project,
+        session_id,
+        expires_at,
+        created_at: now,
+    };
+
+    let dry_run = args.iter().any(|a| a == "--dry-run" || a == "-n");
+    let verb = if dry_run {
+        "--dry-run: Would add"
+    } else {
+        "✓ Added"
+    };
+    let print_summary = || {
+        eprintln!("{verb} temporary allow: {}", approval.pattern);
+        eprintln!("  Project: {}", approval.project);
+        if let Some(id) = &approval.session_id {
+            eprintln!("  Session: {id}");
+        }
+        eprintln!("  Lifetime: {}", approval.lifetime(now));
+    };
+    if dry_run {
+        print_summary();
+        return;
+    }
+
+    match session_approvals::add(approval.clone()) {
+        Ok(()) => print_summary(),
+        Err(e) => {
+            eprintln!("Error: Failed to add approval: {}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
 fn print_approve_help() {
     eprintln!("tool-gates approve - Add a command pattern to settings.json");
     eprintln!();
     eprintln!("USAGE:");
     eprintln!("  tool-gates approve <pattern> -s <scope> [--type <type>] [--dry-run]");
+    eprintln!("  tool-gates approve <pattern> --session [--session-id <id>] [--for <duration>]");
+    eprintln!("  tool-gates approve <pattern> --for <duration>");
     eprintln!();
     eprintln!("ARGUMENTS:");
     eprintln!("  <pattern>   Command pattern to approve (e.g., 'npm install:*', 'git:*')");
     eprintln!();
     eprintln!("OPTIONS:");
-    eprintln!("  -s, --scope <scope>   Target scope: user, project, or local (required)");
-    eprintln!("  -t, --type <type>     Rule type: allow (default), ask, or deny");
-    eprintln!("  -n, --dry-run         Preview changes without writing");
+    eprintln!("  -s, --scope <scope>     Target scope: user, project, or local");
+    eprintln!("  -t, --type <type>       Rule type: allow (default), ask, or deny");
+    eprintln!("  --session               Allow for one Claude Code session in this project");
+    eprintln!("  --session-id <id>       Session to allow for (default: latest pending session)");
+    eprintln!("  --for <duration>        Allow in this project for 30m, 2h, 1h30m, 1d, ...");
+    eprintln!("  -n, --dry-run           Preview changes without writing");
+    eprintln!("  --preview               List what the pattern would allow, then exit");
+    eprintln!();
+    eprintln!("Either --scope or --session/--for is required. Temporary approvals are");
+    eprintln!("stored in ~/.cache/tool-gates/session-approvals.json, not settings.json.");
     eprintln!();
     eprintln!("EXAMPLES:");
     eprintln!("  tool-gates approve 'npm install:*' -s local");
     eprintln!("  tool-gates approve 'biome:*' -s user");
     eprintln!("  tool-gates approve 'rm -rf*' -s user -t deny");
     eprintln!("  tool-gates approve 'cargo:*' -s local --dry-run");
+    eprintln!("  tool-gates approve 'git push:*' --preview");
+    eprintln!("  tool-gates approve 'docker compose up:*' --session");
+    eprintln!("  tool-gates approve 'terraform plan:*' --for 2h");
 }
 
 // === Rules subcommand ===
@@ -975,6 +1176,7 @@ fn handle_rules_subcommand(args: &[String]) {
     match subcommand.as_str() {
         "list" => handle_rules_list(sub_args),
         "remove" => handle_rules_remove(sub_args),
+        "lint" => handle_rules_lint(sub_args),
         _ => {
             eprintln!("Unknown rules subcommand: {}", subcommand);
             eprintln!("Run 'tool-gates rules --help' for usage.");
@@ -1004,7 +1206,14 @@ fn handle_rules_list(args: &[String]) {
         list_all_rules()
     };
 
-    if rules.is_empty() {
+    // Temporary approvals have no settings.json scope, so only show them unfiltered
+    let temporary = if scope_str.is_none() {
+        SessionApprovals::load()
+    } else {
+        SessionApprovals::default()
+    };
+
+    if rules.is_empty() && temporary.entries().is_empty() {
         eprintln!("No permission rules found.");
         return;
     }
@@ -1034,6 +1243,23 @@ fn handle_rules_list(args: &[String]) {
         }
         eprintln!();
     }
+
+    if !temporary.entries().is_empty() {
+        let now = chrono::Utc::now();
+        eprintln!(
+            "  temporary ({}):",
+            session_approvals::approvals_path().display()
+        );
+        for approval in temporary.entries() {
+            eprintln!(
+                "    ✓ {} in {} ({})",
+                approval.pattern,
+                approval.project,
+                approval.lifetime(now)
+            );
+        }
+        eprintln!();
+    }
 }
 
 fn handle_rules_remove(args: &[String]) {
@@ -1083,19 +1309,110 @@ fn handle_rules_remove(args: &[String]) {
     }
 }
 
+fn handle_rules_lint(args: &[String]) {
+    use tool_gates::rules_lint::{self, FindingKind};
+
+    let json = args.iter().any(|a| a == "--json");
+    let fix = args.iter().any(|a| a == "--fix");
+
+    let mut rules = list_all_rules();
+    let mut findings = rules_lint::lint(&rules);
+
+    let mut fixed = 0;
+    if fix {
+        for scope in [Scope::User, Scope::Project, Scope::Local] {
+            if !findings
+                .iter()
+                .any(|f| f.fixable && f.scope == scope.as_str())
+            {
+                continue;
+            }
+            match remove_duplicate_rules(scope, &rules_lint::fixes_for(&findings, scope)) {
+                Ok(count) => fixed += count,
+                Err(e) => {
+                    eprintln!("Error: Failed to update {}: {}", scope.path().display(), e);
+                    std::process::exit(1);
+                }
+            }
+        }
+        if fixed > 0 {
+            // Removing a rule can resolve other findings about it (broad_allow, shadowed)
+            rules = list_all_rules();
+            findings = rules_lint::lint(&rules);
+        }
+    }
+
+    if json {
+        let report = serde_json::json!({
+            "rules": rules.len(),
+            "findings": findings,
+            "fixed": fixed,
+        });
+        println!("{}", serde_json::to_string_pretty(&report).unwrap());
+    } else {
+        if fixed > 0 {
+            eprintln!("✓ Removed {} duplicate rule(s)\n", fixed);
+        }
+        if findings.is_empty() {
+            eprintln!("✓ No problems found in {} permission rules.", rules.len());
+        } else {
+            for kind in [
+                FindingKind::DangerousAllow,
+                FindingKind::Malformed,
+                FindingKind::BroadAllow,
+                FindingKind::HomePath,
+                FindingKind::Duplicate,
+                FindingKind::Shadowed,
+            ] {
+                let of_kind: Vec<_> = findings.iter().filter(|f| f.kind == kind).collect();
+                if of_kind.is_empty() {
+                    continue;
+                }
+                eprintln!("  {}:", kind.as_str());
+                let icon = if kind.is_error() { "✗" } else { "⚠" };
+                for finding in of_kind {
+                    eprintln!(
+                        "    {} {} ({} {}): {}",
+                        icon, finding.rule, finding.scope, finding.list, finding.message
+                    );
+                }
+                eprintln!();
+            }
+            let fixable = findings.iter().filter(|f| f.fixable).count();
+            if fixable > 0 {
+                eprintln!(
+                    "{} problem(s), {} fixable with 'tool-gates rules lint --fix'",
+                    findings.len(),
+                    fixable
+                );
+            } else {
+                eprintln!("{} problem(s)", findings.len());
+            }
+        }
+    }
+
+    if !findings.is_empty() {
+        std::process::exit(1);
+    }
+}
+
 fn print_rules_help() {
     eprintln!("tool-gates rules - Manage permission rules in settings.json");
     eprintln!();
     eprintln!("USAGE:");
     eprintln!("  tool-gates rules list [--scope <scope>]");
     eprintln!("  tool-gates rules remove <pattern> -s <scope>");
+    eprintln!("  tool-gates rules lint [--json] [--fix]");
     eprintln!();
     eprintln!("COMMANDS:");
     eprintln!("  list     List all permission rules");
     eprintln!("  remove   Remove a permission rule");
+    eprintln!("  lint     Find duplicate, shadowed, malformed and risky rules");
     eprintln!();
     eprintln!("OPTIONS:");
     eprintln!("  -s, --scope <scope>   Filter by scope: user, project, or local");
+    eprintln!("  --json                Print lint findings as JSON");
+    eprintln!("  --fix                 Remove duplicate rules found by lint");
 }
 
 // === Pending subcommand ===
@@ -1138,6 +1455,9 @@ fn handle_pending_list(args: &[String]) {
 
     eprintln!("Pending approvals ({}):\n", all_entries.len());
 
+    // Approve vs decline counts per suggested pattern
+    let declined = read_declined(filter);
+
     for entry in &all_entries {
         // Show abbreviated project_id (extract last segment)
         let project = entry
@@ -1157,7 +1477,16 @@ fn handle_pending_list(args: &[String]) {
         if !entry.patterns.is_empty() {
             eprintln!("    Suggested patterns:");
             for pattern in &entry.patterns {
-                eprintln!("      - {}", pattern);
+                let stats = pattern_stats(pattern, &all_entries, &declined);
+                let failed = if stats.failed > 0 {
+                    format!(", {} failed", stats.failed)
+                } else {
+                    String::new()
+                };
+                eprintln!(
+                    "      - {} ({} approved, {} declined{})",
+                    pattern, stats.approved, stats.declined, failed
+                );
             }
         }
 
@@ -1176,6 +1505,22 @@ fn handle_pending_list(args: &[String]) {
         eprintln!();
     }
 
+    let merged = merge_suggestions(&all_entries);
+    if !merged.is_empty() {
+        eprintln!("Merged suggestions:");
+        for suggestion in &merged {
+            eprintln!(
+                "  - {} (covers {} pending)",
+                suggestion.pattern,
+                suggestion.entry_ids.len()
+            );
+            for sample in &suggestion.also_allows {
+                eprintln!("      also allows: {}", sample);
+            }
+        }
+        eprintln!();
+    }
+
     eprintln!("To approve a pattern: tool-gates approve '<pattern>' -s <scope>");
     eprintln!("To review interactively: tool-gates review");
 }
@@ -1233,7 +1578,7 @@ fn print_pending_help() {
     eprintln!("  tool-gates pending clear [--project | --all] --force");
     eprintln!();
     eprintln!("COMMANDS:");
-    eprintln!("  list    List pending approvals");
+    eprintln!("  list    List pending approvals and merged pattern suggestions");
     eprintln!("  clear   Clear pending approval queue (requires --force)");
     eprintln!();
     eprintln!("OPTIONS:");
@@ -1242,32 +1587,657 @@ fn print_pending_help() {
     eprintln!("  -f, --force     Confirm destructive clear operation");
 }
 
-// === Review subcommand ===
+// === Explain subcommand ===
 
-fn handle_review_subcommand(show_all: bool) {
-    if let Err(e) = run_review(show_all) {
-        eprintln!("Error running review TUI: {}", e);
-        std::process::exit(1);
+fn handle_explain_subcommand(args: &[String]) {
+    if args.is_empty() || args.iter().any(|a| a == "--help" || a == "-h") {
+        print_explain_help();
+        return;
     }
-}
 
-// === Doctor subcommand ===
+    let mut command: Option<&str> = None;
+    let mut cwd: Option<String> = None;
+    let mut mode = "default".to_string();
+    let mut json = false;
 
-fn handle_doctor_subcommand() {
-    let mut issues: Vec<String> = Vec::new();
-    let mut ok_count = 0;
+    let mut i = 0;
+    while i < args.len() {
+        match args[i].as_str() {
+            "--cwd" | "-C" => {
+                i += 1;
+                cwd = args.get(i).cloned();
+            }
+            "--mode" | "-m" => {
+                i += 1;
+                if let Some(m) = args.get(i) {
+                    mode = m.clone();
+                }
+            }
+            "--json" => json = true,
+            arg if command.is_none() => command = Some(arg),
+            arg => {
+                eprintln!("Error: Unexpected argument '{}'", arg);
+                eprintln!("Quote the command: tool-gates explain 'cmd args'");
+                std::process::exit(1);
+            }
+        }
+        i += 1;
+    }
 
-    eprintln!("tool-gates doctor\n");
+    let Some(command) = command else {
+        eprintln!("Error: Command is required");
+        eprintln!();
+        print_explain_help();
+        std::process::exit(1);
+    };
 
-    // 1. Version
-    eprintln!("  Version: {}", env!("GIT_VERSION"));
+    let cwd = cwd.unwrap_or_else(|| {
+        std::env::current_dir()
+            .map(|p| p.to_string_lossy().to_string())
+            .unwrap_or_default()
+    });
 
-    // 2. Config file
-    let config_path = dirs::home_dir()
-        .map(|h| h.join(".config/tool-gates/config.toml"))
-        .unwrap_or_default();
-    if config_path.exists() {
-        match std::fs::read_to_string(&config_path) {
+    let explanation = tool_gates::explain::explain(command, &cwd, &mode);
+    if json {
+        match serde_json::to_string_pretty(&explanation) {
+            Ok(out) => println!("{out}"),
+            Err(e) => {
+                eprintln!("Error serializing explanation: {e}");
+                std::process::exit(1);
+            }
+        }
+    } else {
+        print!("{}", explanation.render());
+    }
+}
+
+fn print_explain_help() {
+    eprintln!("tool-gates explain - Show how a command is decided");
+    eprintln!();
+    eprintln!("USAGE:");
+    eprintln!("  tool-gates explain '<command>' [--cwd <dir>] [--mode <mode>] [--json]");
+    eprintln!();
+    eprintln!("OPTIONS:");
+    eprintln!("  -C, --cwd <dir>     Directory to evaluate in (default: current directory)");
+    eprintln!(
+        "  -m, --mode <mode>   Permission mode: default, acceptEdits, plan, bypassPermissions"
+    );
+    eprintln!("      --json          Output the trace as JSON");
+    eprintln!();
+    eprintln!("Shows each parsed segment, the gate and TOML rule or handler that");
+    eprintln!("decided it, raw-string patterns, settings.json rules with their scope,");
+    eprintln!("and how acceptEdits or mise/package.json expansion changed the outcome.");
+    eprintln!();
+    eprintln!("EXAMPLES:");
+    eprintln!("  tool-gates explain 'git push --force'");
+    eprintln!("  tool-gates explain 'sed -i s/a/b/ f.txt' --mode acceptEdits");
+    eprintln!("  tool-gates explain 'npm run build' --cwd ~/proj --json");
+}
+
+// === Log subcommand ===
+
+fn handle_log_subcommand(args: &[String]) {
+    if args.iter().any(|a| a == "--help" || a == "-h") {
+        print_log_help();
+        return;
+    }
+
+    let mut filter = LogFilter::default();
+    let mut limit: usize = 50;
+    let mut json = false;
+
+    let mut i = 0;
+    while i < args.len() {
+        match args[i].as_str() {
+            "--project" | "-p" => filter.project = Some(project_arg(args, &mut i)),
+            "--since" => {
+                i += 1;
+                filter.since = Some(since_arg(args.get(i)));
+            }
+            "--decision" | "-d" => {
+                i += 1;
+                filter.decision = args.get(i).cloned();
+            }
+            "--grep" | "-g" => {
+                i += 1;
+                filter.grep = args.get(i).cloned();
+            }
+            "--limit" | "-n" => {
+                i += 1;
+                let Some(n) = args.get(i).and_then(|n| n.parse().ok()) else {
+                    eprintln!("Error: --limit needs a number (0 for all)");
+                    std::process::exit(1);
+                };
+                limit = n;
+            }
+            "--json" => json = true,
+            arg => {
+                eprintln!("Error: Unknown option '{arg}'");
+                eprintln!();
+                print_log_help();
+                std::process::exit(1);
+            }
+        }
+        i += 1;
+    }
+
+    if !audit_log_exists() {
+        return;
+    }
+
+    let mut entries = audit::read_log(&filter);
+    if limit > 0 && entries.len() > limit {
+        entries.drain(..entries.len() - limit);
+    }
+
+    if json {
+        for entry in &entries {
+            if let Ok(line) = serde_json::to_string(entry) {
+                println!("{line}");
+            }
+        }
+        return;
+    }
+
+    if entries.is_empty() {
+        eprintln!("No matching entries.");
+        return;
+    }
+    for entry in &entries {
+        let time = entry
+            .timestamp
+            .with_timezone(&chrono::Local)
+            .format("%Y-%m-%d %H:%M:%S");
+        println!(
+            "{time}  {:<9} {:<10} {}",
+            entry.decision,
+            entry.tool,
+            entry.subject()
+        );
+        // PreToolUse is the common case; name the other hooks
+        let hook = (entry.hook != "PreToolUse").then(|| format!("({})", entry.hook));
+        let reason = entry
+            .reason
+            .as_deref()
+            .and_then(|r| r.lines().next())
+            .map(String::from);
+        let source = entry.source.as_ref().map(|s| format!("[{s}]"));
+        let detail: Vec<String> = [hook, reason, source].into_iter().flatten().collect();
+        if !detail.is_empty() {
+            println!("{:21}{}", "", detail.join(" "));
+        }
+        if !entry.hints.is_empty() {
+            println!("{:21}hints: {}", "", entry.hints.join(", "));
+        }
+    }
+}
+
+/// `--project [dir]`: the value is optional and defaults to the current directory
+fn project_arg(args: &[String], i: &mut usize) -> String {
+    match args.get(*i + 1) {
+        Some(dir) if !dir.starts_with('-') => {
+            *i += 1;
+            std::fs::canonicalize(dir)
+                .map(|p| p.to_string_lossy().to_string())
+                .unwrap_or_else(|_| dir.clone())
+        }
+        _ => std::env::current_dir()
+            .map(|p| p.to_string_lossy().to_string())
+            .unwrap_or_default(),
+    }
+}
+
+fn since_arg(value: Option<&String>) -> chrono::DateTime<chrono::Utc> {
+    let value = value.map(String::as_str).unwrap_or("");
+    let Some(since) = audit::parse_since(value, chrono::Utc::now()) else {
+        eprintln!(
+            "Error: Invalid --since '{value}'. Use a duration (30m, 2h, 1d), a date (2026-01-31) or an RFC 3339 time"
+        );
+        std::process::exit(1);
+    };
+    since
+}
+
+/// Whether the audit log exists; explains how to enable it if not
+fn audit_log_exists() -> bool {
+    let path = audit::audit_path();
+    if path.exists() {
+        return true;
+    }
+    eprintln!("No audit log at {}.", path.display());
+    if !config::get().audit.enabled {
+        eprintln!(
+            "Enable it in {}:\n\n[audit]\nenabled = true",
+            config::config_path().display()
+        );
+    }
+    false
+}
+
+fn print_log_help() {
+    eprintln!("tool-gates log - Query the decision audit log");
+    eprintln!();
+    eprintln!("USAGE:");
+    eprintln!(
+        "  tool-gates log [--project [dir]] [--since <when>] [--decision <d>] [--grep <text>]"
+    );
+    eprintln!();
+    eprintln!("OPTIONS:");
+    eprintln!("  -p, --project [dir]   Only this project and below (default: current directory)");
+    eprintln!("      --since <when>    Duration back (30m, 2h, 1d), date, or RFC 3339 time");
+    eprintln!("  -d, --decision <d>    allow, ask, deny (or block), none, succeeded, failed");
+    eprintln!("  -g, --grep <text>     Case-insensitive match on command, paths, reason, source");
+    eprintln!("  -n, --limit <n>       Show the last n matches (default: 50, 0 for all)");
+    eprintln!("      --json            Output matching entries as JSON lines");
+    eprintln!();
+    eprintln!("The log is off by default. Enable it in ~/.config/tool-gates/config.toml:");
+    eprintln!();
+    eprintln!("  [audit]");
+    eprintln!("  enabled = true");
+    eprintln!();
+    eprintln!("EXAMPLES:");
+    eprintln!("  tool-gates log --project --since 1d");
+    eprintln!("  tool-gates log --decision deny");
+    eprintln!("  tool-gates log --grep 'git push' --json");
+}
+
+// === Stats subcommand ===
+
+fn handle_stats_subcommand(args: &[String]) {
+    if args.iter().any(|a| a == "--help" || a == "-h") {
+        print_stats_help();
+        return;
+    }
+
+    let mut filter = LogFilter::default();
+    let mut top: usize = 10;
+    let mut json = false;
+
+    let mut i = 0;
+    while i < args.len() {
+        match args[i].as_str() {
+            "--project" | "-p" => filter.project = Some(project_arg(args, &mut i)),
+            "--since" => {
+                i += 1;
+                filter.since = Some(since_arg(args.get(i)));
+            }
+            "--top" | "-n" => {
+                i += 1;
+                let Some(n) = args.get(i).and_then(|n| n.parse().ok()) else {
+                    eprintln!("Error: --top needs a number");
+                    std::process::exit(1);
+                };
+                top = n;
+            }
+            "--json" => json = true,
+            arg => {
+                eprintln!("Error: Unknown option '{arg}'");
+                eprintln!();
+                print_stats_help();
+                std::process::exit(1);
+            }
+        }
+        i += 1;
+    }
+
+    let entries = if audit::audit_path().exists() {
+        audit::read_log(&filter)
+    } else {
+        Vec::new()
+    };
+    let pending: Vec<_> = read_pending(None)
+        .into_iter()
+        .filter(|entry| {
+            filter
+                .project
+                .as_ref()
+                .is_none_or(|project| std::path::Path::new(&entry.cwd).starts_with(project))
+                && filter.since.is_none_or(|since| entry.last_seen >= since)
+        })
+        .collect();
+    let stats = Stats::compute(&entries, &pending, top);
+
+    if json {
+        match serde_json::to_string_pretty(&stats) {
+            Ok(out) => println!("{out}"),
+            Err(e) => {
+                eprintln!("Error: {e}");
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+
+    if entries.is_empty() {
+        audit_log_exists();
+        eprintln!("Hints, decision sources and latency need the audit log.");
+        eprintln!();
+    }
+    print!("{}", stats.render());
+}
+
+fn print_stats_help() {
+    eprintln!("tool-gates stats - Friction and coverage report");
+    eprintln!();
+    eprintln!("USAGE:");
+    eprintln!("  tool-gates stats [--project [dir]] [--since <when>] [--top <n>] [--json]");
+    eprintln!();
+    eprintln!("Reports the most frequent asks per project, unknown programs (candidates");
+    eprintln!("for new gate rules), how often each hint fires, what decided each call");
+    eprintln!("(settings, gates, acceptEdits, ...) and median hook latency.");
+    eprintln!();
+    eprintln!("Reads the audit log ([audit] enabled = true in config.toml). Without it,");
+    eprintln!("asks and unknown programs come from the pending approval queue.");
+    eprintln!();
+    eprintln!("OPTIONS:");
+    eprintln!("  -p, --project [dir]   Only this project and below (default: current directory)");
+    eprintln!("      --since <when>    Duration back (30m, 2h, 1d), date, or RFC 3339 time");
+    eprintln!("  -n, --top <n>         Entries per list (default: 10)");
+    eprintln!("      --json            Output the report as JSON");
+    eprintln!();
+    eprintln!("EXAMPLES:");
+    eprintln!("  tool-gates stats --since 7d");
+    eprintln!("  tool-gates stats --project --top 5");
+}
+
+// === Replay subcommand ===
+
+fn handle_replay_subcommand(args: &[String]) {
+    if args.iter().any(|a| a == "--help" || a == "-h") {
+        print_replay_help();
+        return;
+    }
+
+    let mut path: Option<&str> = None;
+    let mut compare: Option<&str> = None;
+    let mut json = false;
+
+    let mut i = 0;
+    while i < args.len() {
+        match args[i].as_str() {
+            "--compare-config" | "-c" => {
+                i += 1;
+                let Some(file) = args.get(i) else {
+                    eprintln!("Error: --compare-config needs a config.toml path");
+                    std::process::exit(1);
+                };
+                compare = Some(file);
+            }
+            "--json" => json = true,
+            arg if arg.starts_with('-') => {
+                eprintln!("Error: Unknown option '{arg}'");
+                eprintln!();
+                print_replay_help();
+                std::process::exit(1);
+            }
+            arg => path = Some(arg),
+        }
+        i += 1;
+    }
+
+    let Some(path) = path else {
+        eprintln!("Error: Missing transcript file or directory");
+        eprintln!();
+        print_replay_help();
+        std::process::exit(1);
+    };
+    let files = replay::transcript_files(std::path::Path::new(path));
+    if files.is_empty() {
+        eprintln!("Error: No .jsonl transcripts found at {path}");
+        std::process::exit(1);
+    }
+
+    let compared = compare.map(|file| match config::load_from(std::path::Path::new(file)) {
+        Ok(config) => (file, Replayer::new(config)),
+        Err(e) => {
+            eprintln!("Error: {e}");
+            std::process::exit(1);
+        }
+    });
+
+    let mut calls = Vec::new();
+    for file in &files {
+        match replay::read_transcript(file) {
+            Ok(recorded) => calls.extend(recorded),
+            Err(e) => eprintln!("Warning: Skipping {}: {e}", file.display()),
+        }
+    }
+
+    let report = ReplayReport::compute(
+        files.len(),
+        calls,
+        &Replayer::new(config::load()),
+        compared.as_ref().map(|(file, replayer)| (*file, replayer)),
+    );
+
+    if json {
+        match serde_json::to_string_pretty(&report) {
+            Ok(out) => println!("{out}"),
+            Err(e) => {
+                eprintln!("Error: {e}");
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+    print!("{}", report.render());
+}
+
+fn print_replay_help() {
+    eprintln!("tool-gates replay - Re-run recorded tool calls through the current rules");
+    eprintln!();
+    eprintln!("USAGE:");
+    eprintln!("  tool-gates replay <transcript.jsonl | dir> [--compare-config <file>] [--json]");
+    eprintln!();
+    eprintln!("Reads Claude Code transcripts (a file, or every .jsonl under a directory");
+    eprintln!("such as ~/.claude/projects), re-runs each tool call through the PreToolUse");
+    eprintln!("decision with the current config, settings and rules, and reports how many");
+    eprintln!("calls would be allowed, asked, denied or left alone. Nothing is recorded.");
+    eprintln!();
+    eprintln!("OPTIONS:");
+    eprintln!("  -c, --compare-config <file>   Also replay with this config.toml and list");
+    eprintln!("                                calls whose decision would change");
+    eprintln!("      --json                    Output the report as JSON");
+    eprintln!();
+    eprintln!("EXAMPLES:");
+    eprintln!("  tool-gates replay ~/.claude/projects");
+    eprintln!("  tool-gates replay ~/.claude/projects/-home-me-app --compare-config new.toml");
+}
+
+// === Trust subcommand ===
+
+fn handle_trust_subcommand(args: &[String]) {
+    use tool_gates::repo_policy::{self, LoadedPolicy, POLICY_FILE, TrustStore};
+    use tool_gates::user_rules::{self, PROJECT_RULES_DIR, RuleFile};
+
+    if args.iter().any(|a| a == "--help" || a == "-h") {
+        print_trust_help();
+        return;
+    }
+
+    let revoke = args.iter().any(|a| a == "--revoke");
+    let target = args.iter().find(|a| !a.starts_with('-'));
+
+    // Policy file and project rule files to (un)trust
+    let paths: Vec<std::path::PathBuf> = match target {
+        Some(target) => {
+            let path = std::path::PathBuf::from(target);
+            if path.is_dir() {
+                let policy = path.join(POLICY_FILE);
+                policy
+                    .is_file()
+                    .then_some(policy)
+                    .into_iter()
+                    .chain(user_rules::rule_files_in(&path.join(PROJECT_RULES_DIR)))
+                    .collect()
+            } else {
+                vec![path]
+            }
+        }
+        None => {
+            let cwd = std::env::current_dir()
+                .map(|p| p.to_string_lossy().to_string())
+                .unwrap_or_default();
+            let paths: Vec<_> = repo_policy::find(&cwd)
+                .into_iter()
+                .chain(
+                    user_rules::project_rules_dir(&cwd)
+                        .map(|dir| user_rules::rule_files_in(&dir))
+                        .unwrap_or_default(),
+                )
+                .collect();
+            if paths.is_empty() {
+                eprintln!(
+                    "Error: No {POLICY_FILE} or {PROJECT_RULES_DIR}/*.toml found in {cwd} or its parents"
+                );
+                std::process::exit(1);
+            }
+            paths
+        }
+    };
+    if paths.is_empty() {
+        eprintln!(
+            "Error: Nothing to trust in {}",
+            target.map_or("", String::as_str)
+        );
+        std::process::exit(1);
+    }
+
+    let mut store = TrustStore::load();
+
+    if revoke {
+        let mut revoked = false;
+        for path in &paths {
+            if store.revoke(path) {
+                eprintln!("Revoked trust for {}", path.display());
+                revoked = true;
+            } else {
+                eprintln!("{} was not trusted", path.display());
+            }
+        }
+        if revoked {
+            if let Err(e) = store.save() {
+                eprintln!("Error saving trust store: {e}");
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+
+    for path in &paths {
+        let is_policy = path.file_name().is_some_and(|name| name == POLICY_FILE);
+        let hash = if is_policy {
+            let loaded = match LoadedPolicy::load(path, &store) {
+                Ok(loaded) => loaded,
+                Err(e) => {
+                    eprintln!("Error: {} {e}", path.display());
+                    std::process::exit(1);
+                }
+            };
+            if let Some(e) = &loaded.error {
+                eprintln!("Error: {} {e}", path.display());
+                std::process::exit(1);
+            }
+            let policy = &loaded.policy;
+            eprintln!("Policy: {}", path.display());
+            if !policy.has_loosening_entries() {
+                eprintln!("  No allow rules or disabled security reminders; nothing needs trust.");
+            }
+            for rule in &policy.allow {
+                eprintln!("  allow: {rule}");
+            }
+            for rule in &policy.security_reminders.disable_rules {
+                eprintln!("  disable security reminder: {rule}");
+            }
+            loaded.hash
+        } else {
+            let content = match std::fs::read_to_string(path) {
+                Ok(content) => content,
+                Err(e) => {
+                    eprintln!("Error: {} read error: {e}", path.display());
+                    std::process::exit(1);
+                }
+            };
+            let rules = match RuleFile::parse(&content) {
+                Ok(rules) => rules,
+                Err(e) => {
+                    eprintln!("Error: {} {e}", path.display());
+                    std::process::exit(1);
+                }
+            };
+            eprintln!("Rules: {}", path.display());
+            for program in &rules.programs {
+                eprintln!(
+                    "  program: {} ({} allow, {} ask, {} block, unknown_action = {})",
+                    program.name,
+                    program.allow.len(),
+                    program.ask.len(),
+                    program.block.len(),
+                    format!("{:?}", program.unknown_action).to_lowercase()
+                );
+            }
+            for cmd in &rules.safe_commands {
+                eprintln!("  safe command: {cmd}");
+            }
+            repo_policy::hash_contents(content.as_bytes())
+        };
+        store.trust(path, &hash);
+    }
+
+    if let Err(e) = store.save() {
+        eprintln!("Error saving trust store: {e}");
+        std::process::exit(1);
+    }
+    eprintln!();
+    eprintln!("Trusted. Editing a file will require running `tool-gates trust` again.");
+}
+
+fn print_trust_help() {
+    eprintln!("tool-gates trust - Trust a repository policy file and project rule files");
+    eprintln!();
+    eprintln!("USAGE:");
+    eprintln!("  tool-gates trust [path] [--revoke]");
+    eprintln!();
+    eprintln!("ARGUMENTS:");
+    eprintln!("  [path]      A .tool-gates.toml or rule file, or a repository directory");
+    eprintln!("              (default: nearest .tool-gates.toml and .tool-gates/rules from cwd)");
+    eprintln!();
+    eprintln!("OPTIONS:");
+    eprintln!("      --revoke    Forget previously trusted files");
+    eprintln!();
+    eprintln!("Block and ask rules in .tool-gates.toml always apply. Allow rules and");
+    eprintln!("security_reminders.disable_rules only apply once the file is trusted,");
+    eprintln!("and trust is tied to the file's contents: any edit needs a new trust.");
+    eprintln!("Rule files in .tool-gates/rules/ work the same way: until trusted, only");
+    eprintln!("their ask and block results apply.");
+}
+
+// === Review subcommand ===
+
+fn handle_review_subcommand(show_all: bool, declined: bool) {
+    if let Err(e) = run_review(show_all, declined) {
+        eprintln!("Error running review TUI: {}", e);
+        std::process::exit(1);
+    }
+}
+
+// === Doctor subcommand ===
+
+fn handle_doctor_subcommand() {
+    let mut issues: Vec<String> = Vec::new();
+    let mut ok_count = 0;
+
+    eprintln!("tool-gates doctor\n");
+
+    // 1. Version
+    eprintln!("  Version: {}", env!("GIT_VERSION"));
+
+    // 2. Config file
+    let config_path = dirs::home_dir()
+        .map(|h| h.join(".config/tool-gates/config.toml"))
+        .unwrap_or_default();
+    if config_path.exists() {
+        match std::fs::read_to_string(&config_path) {
             Ok(content) => match toml::from_str::<config::Config>(&content) {
                 Ok(cfg) => {
                     eprintln!("  ✓ Config: {} (valid)", config_path.display());
@@ -1448,6 +2418,7 @@ fn handle_doctor_subcommand() {
         ("hint-tracker.json", "Hint dedup tracker"),
         ("pending.jsonl", "Pending approvals"),
         ("tracking.json", "Ask tracking (PreToolUse->PostToolUse)"),
+        ("trusted-policies.json", "Trusted repository policies"),
     ];
 
     for (file, desc) in &cache_files {
@@ -1489,6 +2460,145 @@ fn handle_doctor_subcommand() {
         issues.push(msg);
     }
 
+    // 6. Repository policy file
+    let cwd = std::env::current_dir()
+        .map(|p| p.to_string_lossy().to_string())
+        .unwrap_or_default();
+    if let Some(path) = tool_gates::repo_policy::find(&cwd) {
+        use tool_gates::repo_policy::{LoadedPolicy, Trust, TrustStore};
+
+        eprintln!();
+        match LoadedPolicy::load(&path, &TrustStore::load()) {
+            Ok(loaded) => {
+                let policy = &loaded.policy;
+                let summary = format!(
+                    "{} allow, {} ask, {} block",
+                    policy.allow.len(),
+                    policy.ask.len(),
+                    policy.block.len()
+                );
+                match (&loaded.error, loaded.trust) {
+                    (Some(e), _) => {
+                        let msg = format!(
+                            "Invalid policy file {}: only its block rules apply and every other command asks. {e}",
+                            path.display()
+                        );
+                        eprintln!(
+                            "  ✗ Policy: {} (invalid, asking for every command; {} block)",
+                            path.display(),
+                            policy.block.len()
+                        );
+                        issues.push(msg);
+                    }
+                    (None, Trust::Trusted) => {
+                        eprintln!("  ✓ Policy: {} (trusted; {summary})", path.display());
+                        ok_count += 1;
+                    }
+                    (None, _) if !policy.has_loosening_entries() => {
+                        eprintln!("  ✓ Policy: {} ({summary})", path.display());
+                        ok_count += 1;
+                    }
+                    (None, Trust::Untrusted) => {
+                        let msg = format!(
+                            "Untrusted policy file {}: allow rules ignored. Run: tool-gates trust",
+                            path.display()
+                        );
+                        eprintln!("  ⚠ Policy: {} (untrusted; {summary})", path.display());
+                        issues.push(msg);
+                    }
+                    (None, Trust::Changed) => {
+                        let msg = format!(
+                            "Policy file {} changed since it was trusted: allow rules ignored. Review it, then run: tool-gates trust",
+                            path.display()
+                        );
+                        eprintln!(
+                            "  ⚠ Policy: {} (changed since trusted; {summary})",
+                            path.display()
+                        );
+                        issues.push(msg);
+                    }
+                }
+            }
+            Err(e) => {
+                let msg = format!("Policy {} {e}", path.display());
+                eprintln!("  ✗ Policy: {} ({e})", path.display());
+                issues.push(msg);
+            }
+        }
+    }
+
+    // 7. Runtime rule files
+    {
+        use tool_gates::repo_policy::{Trust, TrustStore};
+        use tool_gates::user_rules::{self, RuleScope, RuleSet};
+
+        let user_dir = user_rules::user_rules_dir();
+        let project_dir = user_rules::project_rules_dir(&cwd);
+        let rules = RuleSet::load_from(&user_dir, project_dir.as_deref(), &TrustStore::load());
+        if !rules.sources.is_empty() || !rules.errors.is_empty() {
+            eprintln!();
+        }
+        for source in &rules.sources {
+            let path = source.path.display();
+            let summary = format!("{} program(s)", source.rules.programs.len());
+            match source.scope {
+                RuleScope::User | RuleScope::Project(Trust::Trusted) => {
+                    eprintln!("  ✓ Rules: {path} ({summary})");
+                    ok_count += 1;
+                }
+                RuleScope::Project(Trust::Untrusted) => {
+                    eprintln!("  ⚠ Rules: {path} (untrusted; {summary})");
+                    issues.push(format!(
+                        "Untrusted rule file {path}: only ask and block results apply. Run: tool-gates trust"
+                    ));
+                }
+                RuleScope::Project(Trust::Changed) => {
+                    eprintln!("  ⚠ Rules: {path} (changed since trusted; {summary})");
+                    issues.push(format!(
+                        "Rule file {path} changed since it was trusted: only ask and block results apply. Review it, then run: tool-gates trust"
+                    ));
+                }
+            }
+        }
+        for (path, error) in &rules.errors {
+            eprintln!("  ✗ Rules: {} ({error})", path.display());
+            issues.push(format!("Rule file {} ignored: {error}", path.display()));
+        }
+    }
+
+    // 8. Security reminder rule files
+    {
+        use tool_gates::security_reminders::{self, ReminderRules};
+
+        let user_file = security_reminders::user_rules_file();
+        let project_file = security_reminders::project_rules_file(&cwd);
+        let files: Vec<&std::path::Path> = [Some(user_file.as_path()), project_file.as_deref()]
+            .into_iter()
+            .flatten()
+            .filter(|path| path.is_file())
+            .collect();
+        if !files.is_empty() {
+            eprintln!();
+            let loaded = ReminderRules::load_from(Some(&user_file), project_file.as_deref());
+            for path in &files {
+                let failed = loaded.errors.iter().any(|(p, _)| p == *path);
+                let count = loaded
+                    .rules
+                    .iter()
+                    .filter(|rule| rule.source.as_deref() == Some(*path))
+                    .count();
+                if !failed {
+                    eprintln!("  ✓ Security rules: {} ({count} rule(s))", path.display());
+                    ok_count += 1;
+                }
+            }
+            for (path, error) in &loaded.errors {
+                eprintln!("  ✗ Security rules: {} ({error})", path.display());
+                issues.push(format!("Security rule file {}: {error}", path.display()));
+            }
+        }
+    }
+
     // Summary
     eprintln!();
     if issues.is_empty() {
@@ -1547,6 +2657,34 @@ mod tests {
         assert_eq!(input.get_command(), "npm install");
     }
 
+    #[test]
+    fn test_denied_secret_command_redacted() {
+        let token = format!("ghp_{}", "aB3dE5fG7hJ9kL1mN3pQ5rS7tU9vW1xY3zA5b");
+        let json = format!(
+            r#"{{"tool_name": "Bash", "cwd": "/nonexistent", "tool_input": {{"command": "export GITHUB_TOKEN={token} && gh pr list"}}}}"#
+        );
+        let input: HookInput = serde_json::from_str(&json).unwrap();
+        let engine = Engine::builder()
+            .config(config::Config::default())
+            .cwd("/nonexistent")
+            .build();
+        let map = serde_json::Map::new();
+
+        let result = decide_pre_tool_use(&engine, &input, &map, false);
+        assert_eq!(result.decision(), "deny");
+        let logged = result.redacted_command.unwrap();
+        assert!(!logged.contains(&token), "{logged}");
+        assert_eq!(logged, "export GITHUB_TOKEN=[REDACTED] && gh pr list");
+
+        // Other decisions log the command as-is
+        let input: HookInput = serde_json::from_str(
+            r#"{"tool_name": "Bash", "cwd": "/nonexistent", "tool_input": {"command": "git status"}}"#,
+        )
+        .unwrap();
+        let result = decide_pre_tool_use(&engine, &input, &map, false);
+        assert!(result.redacted_command.is_none());
+    }
+
     #[test]
     fn test_check_command_git_status() {
         let output = check_command("git status");
@@ -1687,7 +2825,10 @@ mod tests {
         let result = tool_gates::security_reminders::check_security_reminders(
             "Write",
             &map,
+            None,
             &config,
+            tool_gates::security_reminders::ReminderRules::builtin(),
+            &tool_gates::hint_tracker::MemorySessionStore::new(),
             "integration-sec-test",
         );
         assert!(result.is_some());
@@ -15,9 +15,10 @@ fn sanitize_path(path: &str) -> String {
 }
 
 /// Permission decision types with priority: Block > Ask > Allow > Skip
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
 pub enum Decision {
-    Skip = 0,  // Gate doesn't handle this command
+    #[default]
+    Skip = 0, // Gate doesn't handle this command
     Allow = 1, // Explicitly allowed
     Ask = 2,   // Requires user approval
     Block = 3, // Blocked
@@ -31,6 +32,31 @@ impl Decision {
     }
 }
 
+/// Claude Code permission mode, from the hook input's `permission_mode`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PermissionMode {
+    #[default]
+    Default,
+    /// File edits inside the project are auto-approved
+    AcceptEdits,
+    /// Read-only planning: anything that mutates is denied
+    Plan,
+    /// No prompts: only blocks and settings.json deny rules still apply
+    BypassPermissions,
+}
+
+impl PermissionMode {
+    /// Parse the hook input string. Unknown modes behave like `default`.
+    pub fn parse(mode: &str) -> Self {
+        match mode {
+            "acceptEdits" => Self::AcceptEdits,
+            "plan" => Self::Plan,
+            "bypassPermissions" => Self::BypassPermissions,
+            _ => Self::Default,
+        }
+    }
+}
+
 /// Information about a parsed command
 #[derive(Debug, Clone, Default)]
 pub struct CommandInfo {
@@ -40,6 +66,141 @@ pub struct CommandInfo {
     pub program: String,
     /// Arguments after the program
     pub args: Vec<String>,
+    /// Redirections attached to this command (`> file`, `2>&1`, `<<EOF`, ...)
+    pub redirects: Vec<Redirect>,
+    /// Where the command sits in the shell's control flow
+    pub flow: Flow,
+}
+
+/// Whether a command runs, and whether what it changes in the shell (`cd`)
+/// carries over to the commands after it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Flow {
+    /// Top-level statement, run once in order in the current shell
+    #[default]
+    Sequential,
+    /// Link of the `a && b && c` chain with this id: runs only if every
+    /// earlier link succeeded
+    Chain(usize),
+    /// Pipeline member of a top-level statement or chain (`None`). Runs in
+    /// its own subshell, so nothing it changes carries over.
+    Piped(Option<usize>),
+    /// Behind `||`, in a branch, loop, function, subshell or background job
+    Nested,
+}
+
+impl Flow {
+    /// The `&&` chain the command belongs to
+    pub fn chain(self) -> Option<usize> {
+        match self {
+            Self::Chain(id) | Self::Piped(Some(id)) => Some(id),
+            _ => None,
+        }
+    }
+}
+
+impl CommandInfo {
+    /// Text fed to the command on stdin by a heredoc or here-string.
+    ///
+    /// Returns `None` when stdin comes from a file (`< script.sh`) or isn't redirected.
+    pub fn stdin_text(&self) -> Option<&str> {
+        let stdin = self.redirects.iter().rev().find(|r| {
+            r.fd.is_none_or(|fd| fd == 0)
+                && matches!(
+                    r.op,
+                    RedirectOp::Heredoc
+                        | RedirectOp::HereString
+                        | RedirectOp::Read
+                        | RedirectOp::ReadWrite
+                )
+        })?;
+        match stdin.op {
+            RedirectOp::Heredoc => stdin.body.as_deref(),
+            RedirectOp::HereString => Some(&stdin.target),
+            _ => None,
+        }
+    }
+}
+
+/// Redirection operator kinds recognized by the parser
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RedirectOp {
+    /// `>` - truncate and write
+    Write,
+    /// `>>` - append
+    Append,
+    /// `>|` - write, overriding noclobber
+    Clobber,
+    /// `&>` - stdout and stderr to file
+    WriteAll,
+    /// `&>>` - append stdout and stderr to file
+    AppendAll,
+    /// `<` - read from file
+    Read,
+    /// `<>` - open for reading and writing
+    ReadWrite,
+    /// `>&` / `<&` - duplicate or close a file descriptor
+    Dup,
+    /// `<<` / `<<-` - heredoc (target is the delimiter)
+    Heredoc,
+    /// `<<<` - here-string (target is the string)
+    HereString,
+}
+
+impl RedirectOp {
+    /// Map a tree-sitter operator token to a redirect kind
+    pub fn from_token(token: &str) -> Option<Self> {
+        Some(match token {
+            ">" => Self::Write,
+            ">>" => Self::Append,
+            ">|" => Self::Clobber,
+            "&>" => Self::WriteAll,
+            "&>>" => Self::AppendAll,
+            "<" => Self::Read,
+            "<>" => Self::ReadWrite,
+            ">&" | "<&" | ">&-" | "<&-" => Self::Dup,
+            "<<" | "<<-" => Self::Heredoc,
+            "<<<" => Self::HereString,
+            _ => return None,
+        })
+    }
+
+    /// Whether this operator can create or modify the target file
+    pub fn writes_file(self) -> bool {
+        matches!(
+            self,
+            Self::Write
+                | Self::Append
+                | Self::Clobber
+                | Self::WriteAll
+                | Self::AppendAll
+                | Self::ReadWrite
+        )
+    }
+
+    /// Whether this operator appends rather than truncates
+    pub fn is_append(self) -> bool {
+        matches!(self, Self::Append | Self::AppendAll)
+    }
+}
+
+/// A single parsed redirection
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Redirect {
+    /// Explicit file descriptor (`2` in `2>err.log`), if any
+    pub fd: Option<u32>,
+    pub op: RedirectOp,
+    /// Destination path, fd number, heredoc delimiter or here-string text
+    pub target: String,
+    /// Heredoc body (variables expanded unless the delimiter is quoted)
+    pub body: Option<String>,
+}
+
+impl Redirect {
+    /// Whether this redirect writes to a file path (not an fd duplication)
+    pub fn writes_file(&self) -> bool {
+        self.op.writes_file()
+    }
 }
 
 /// Result from a permission gate check
@@ -417,6 +578,8 @@ pub struct PermissionRequestInput {
     pub cwd: String,
     #[serde(default)]
     pub permission_mode: String,
+    #[serde(default)]
+    pub session_id: String,
 }
 
 impl PermissionRequestInput {
@@ -590,6 +753,15 @@ impl PostToolUseInput {
             })
             .unwrap_or(true)
     }
+
+    /// File content before a Write, from the tool response (`originalFile`).
+    /// `None` for new files and tools that don't report it.
+    pub fn original_file(&self) -> Option<&str> {
+        self.tool_response
+            .as_ref()
+            .and_then(|r| r.get("originalFile"))
+            .and_then(|v| v.as_str())
+    }
 }
 
 /// Hook-specific output for `PostToolUse`
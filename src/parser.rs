@@ -1,10 +1,41 @@
 //! Bash command parser using tree-sitter-bash for AST parsing.
 
-use crate::models::CommandInfo;
+use crate::models::{CommandInfo, Flow, Redirect, RedirectOp};
+use std::collections::{HashMap, HashSet};
 use std::sync::{LazyLock, Mutex};
-use tree_sitter::{Parser, Tree, TreeCursor};
+use tree_sitter::{Node, Parser, Tree, TreeCursor};
 use tree_sitter_bash::LANGUAGE;
 
+/// Shell variables whose values are known while walking the tree
+#[derive(Default)]
+struct Vars {
+    /// Values from unconditional top-level assignments (name -> value)
+    known: HashMap<String, String>,
+    /// Names a function body assigns. A call can change them at any point,
+    /// so they are never known.
+    volatile: HashSet<String>,
+}
+
+impl Vars {
+    fn get(&self, name: &str) -> Option<&String> {
+        self.known.get(name)
+    }
+
+    fn forget(&mut self, name: &str) {
+        self.known.remove(name);
+    }
+}
+
+/// Whether the node being walked runs exactly once, in order, in the current shell
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Scope {
+    /// Top-level statement: assignments are recorded
+    Sequential,
+    /// Behind `&&`/`||`, in a branch, loop, function, subshell, pipeline or
+    /// background job: assignments make the variable unknown
+    Nested,
+}
+
 static PARSER: LazyLock<Mutex<Parser>> = LazyLock::new(|| {
     let mut parser = Parser::new();
     parser
@@ -21,6 +52,8 @@ static PARSER: LazyLock<Mutex<Parser>> = LazyLock::new(|| {
 /// - Pipelines: `gh pr list | head`
 /// - Subshells: `$(gh pr create)`
 /// - Quoted strings: `echo "gh pr create"` (not treated as gh command)
+/// - Redirections: `cmd > out.txt 2>&1` (recorded in `CommandInfo::redirects`)
+/// - Variables: `D=/; rm -rf $D` (known values substituted into later args)
 pub fn extract_commands(command_string: &str) -> Vec<CommandInfo> {
     if command_string.trim().is_empty() {
         return Vec::new();
@@ -46,16 +79,27 @@ pub fn extract_commands(command_string: &str) -> Vec<CommandInfo> {
 
 fn extract_from_tree(tree: &Tree, source: &str, commands: &mut Vec<CommandInfo>) {
     let mut cursor = tree.walk();
-    visit_node(&mut cursor, source, commands);
+    let mut vars = Vars::default();
+    collect_function_writes(tree.root_node(), source, None, &mut vars.volatile);
+    visit_node(&mut cursor, source, &mut vars, Scope::Sequential, commands);
 }
 
-fn visit_node(cursor: &mut TreeCursor, source: &str, commands: &mut Vec<CommandInfo>) {
+fn visit_node(
+    cursor: &mut TreeCursor,
+    source: &str,
+    vars: &mut Vars,
+    scope: Scope,
+    commands: &mut Vec<CommandInfo>,
+) {
     let node = cursor.node();
     let kind = node.kind();
 
     match kind {
         "command" => {
-            if let Some(cmd) = extract_command(cursor, source) {
+            if let Some(cmd) = extract_command(cursor, source, vars) {
+                for name in written_names(&cmd.program, &cmd.args) {
+                    vars.forget(name);
+                }
                 commands.push(cmd);
             }
             // Also check for nested substitutions within command arguments
@@ -64,9 +108,13 @@ fn visit_node(cursor: &mut TreeCursor, source: &str, commands: &mut Vec<CommandI
                     let child = cursor.node();
                     if matches!(
                         child.kind(),
-                        "command_substitution" | "process_substitution"
+                        "command_substitution" | "process_substitution" | "herestring_redirect"
                     ) {
-                        visit_node(cursor, source, commands);
+                        visit_node(cursor, source, vars, Scope::Nested, commands);
+                    } else if child.kind() == "variable_assignment" {
+                        // `VAR=$(...) cmd` - the prefix only applies to this command,
+                        // so don't record it, but still inspect its value
+                        visit_children(cursor, source, vars, Scope::Nested, commands);
                     }
                     if !cursor.goto_next_sibling() {
                         break;
@@ -75,54 +123,101 @@ fn visit_node(cursor: &mut TreeCursor, source: &str, commands: &mut Vec<CommandI
                 cursor.goto_parent();
             }
         }
-        "pipeline" => {
-            // Visit each command in the pipeline
-            if cursor.goto_first_child() {
-                loop {
-                    let child = cursor.node();
-                    if child.kind() == "command" {
-                        if let Some(cmd) = extract_command(cursor, source) {
-                            commands.push(cmd);
-                        }
-                    } else if child.kind() != "|" {
-                        // Recurse into non-pipe children
-                        visit_node(cursor, source, commands);
-                    }
-                    if !cursor.goto_next_sibling() {
-                        break;
-                    }
-                }
-                cursor.goto_parent();
-            }
+        "pipeline" | "list" => {
+            visit_chain(cursor, source, vars, scope, commands);
         }
-        "list"
-        | "program"
-        | "subshell"
+        "program" | "compound_statement" => {
+            visit_children(cursor, source, vars, scope, commands);
+        }
+        "subshell"
         | "command_substitution"
         | "process_substitution" // <(...) and >(...) - must inspect contents
         | "if_statement"
-        | "while_statement"
-        | "for_statement"
-        | "case_statement"
-        | "compound_statement" => {
-            // Visit all children
+        | "case_statement" => {
+            visit_children(cursor, source, vars, Scope::Nested, commands);
+        }
+        "while_statement" | "for_statement" | "c_style_for_statement" => {
+            // A later iteration sees what an earlier one assigned, so anything
+            // the loop writes is unknown from its first command on
+            let mut written = HashSet::new();
+            collect_writes(node, source, &mut written);
+            for name in &written {
+                vars.forget(name);
+            }
+            visit_children(cursor, source, vars, Scope::Nested, commands);
+        }
+        "redirected_statement" => {
+            // Redirects apply to every command in a `{ a; b; } > out.txt` or
+            // `( ... )` body, but only to the last command of a list or pipeline:
+            // `ls && git status > out.txt` parses with the whole list as the body.
+            // Commands nested in redirect targets (`> >(tee log)`, `<<EOF | sh`)
+            // are extracted separately and don't inherit them.
+            let mut redirects = Vec::new();
+            let mut body = Vec::new();
+            let mut nested = Vec::new();
+            let mut redirected_from = 0;
             if cursor.goto_first_child() {
                 loop {
-                    visit_node(cursor, source, commands);
+                    if cursor.field_name() == Some("redirect") {
+                        collect_redirects(cursor.node(), source, vars, &mut redirects);
+                        visit_node(cursor, source, vars, Scope::Nested, &mut nested);
+                    } else if matches!(cursor.node().kind(), "list" | "pipeline") {
+                        redirected_from = visit_chain(cursor, source, vars, scope, &mut body);
+                    } else {
+                        visit_node(cursor, source, vars, scope, &mut body);
+                    }
                     if !cursor.goto_next_sibling() {
                         break;
                     }
                 }
                 cursor.goto_parent();
             }
+            // Bare redirect with no command (`> file` truncates file)
+            if body.is_empty() && !redirects.is_empty() {
+                body.push(CommandInfo {
+                    raw: node.utf8_text(source.as_bytes()).unwrap_or("").to_string(),
+                    ..Default::default()
+                });
+            }
+            for cmd in &mut body[redirected_from..] {
+                cmd.redirects.extend(redirects.iter().cloned());
+            }
+            commands.extend(body);
+            commands.extend(nested);
+        }
+        "variable_assignment" => {
+            // `D=/`, `export T=~`, `local x=1` (declaration_command recurses here)
+            visit_children(cursor, source, vars, Scope::Nested, commands);
+            record_assignment(node, source, vars, scope);
+        }
+        "unset_command" => {
+            let mut walker = node.walk();
+            for child in node.named_children(&mut walker) {
+                vars.forget(&node_text(child, source));
+            }
+            visit_children(cursor, source, vars, scope, commands);
         }
         "function_definition" => {
-            // Visit function body
+            // The body runs at call time, against whatever values the caller
+            // has then, so it starts with nothing known. Only calls to other
+            // functions can change its own assignments behind its back.
+            let mut root = node;
+            while let Some(parent) = root.parent() {
+                root = parent;
+            }
+            let mut body_vars = Vars::default();
+            collect_function_writes(root, source, Some(node.id()), &mut body_vars.volatile);
             if cursor.goto_first_child() {
                 loop {
                     let child = cursor.node();
                     if child.kind() == "compound_statement" {
-                        visit_node(cursor, source, commands);
+                        visit_node(
+                            cursor,
+                            source,
+                            &mut body_vars,
+                            Scope::Sequential,
+                            commands,
+                        );
                     }
                     if !cursor.goto_next_sibling() {
                         break;
@@ -133,57 +228,217 @@ fn visit_node(cursor: &mut TreeCursor, source: &str, commands: &mut Vec<CommandI
         }
         _ => {
             // For other node types, try to visit children
-            if cursor.goto_first_child() {
-                loop {
-                    visit_node(cursor, source, commands);
-                    if !cursor.goto_next_sibling() {
-                        break;
+            visit_children(cursor, source, vars, scope, commands);
+        }
+    }
+}
+
+/// Visit a `list` (`a && b`, `a || b`) or `pipeline` (`a | b`).
+///
+/// Returns the index in `commands` where the commands of its last element start.
+fn visit_chain(
+    cursor: &mut TreeCursor,
+    source: &str,
+    vars: &mut Vars,
+    scope: Scope,
+    commands: &mut Vec<CommandInfo>,
+) -> usize {
+    let is_list = cursor.node().kind() == "list";
+    let mut last_start = commands.len();
+    if cursor.goto_first_child() {
+        // In a list only the left side is sure to run; pipeline members each
+        // run in a subshell
+        let mut child_scope = if is_list { scope } else { Scope::Nested };
+        loop {
+            let child = cursor.node();
+            if child.is_named() {
+                last_start = commands.len();
+            }
+            if matches!(child.kind(), "list" | "pipeline") {
+                last_start = visit_chain(cursor, source, vars, child_scope, commands);
+            } else if !is_list && child.kind() == "command" {
+                if let Some(cmd) = extract_command(cursor, source, vars) {
+                    for name in written_names(&cmd.program, &cmd.args) {
+                        vars.forget(name);
                     }
+                    commands.push(cmd);
                 }
-                cursor.goto_parent();
+            } else if is_list || child.kind() != "|" {
+                visit_node(cursor, source, vars, child_scope, commands);
+            }
+            if child.is_named() {
+                child_scope = Scope::Nested;
+            }
+            if !cursor.goto_next_sibling() {
+                break;
             }
         }
+        cursor.goto_parent();
     }
+    last_start
 }
 
-fn extract_command(cursor: &mut TreeCursor, source: &str) -> Option<CommandInfo> {
+fn visit_children(
+    cursor: &mut TreeCursor,
+    source: &str,
+    vars: &mut Vars,
+    scope: Scope,
+    commands: &mut Vec<CommandInfo>,
+) {
+    if cursor.goto_first_child() {
+        loop {
+            // `X=1 &` runs in a background subshell
+            let backgrounded = cursor
+                .node()
+                .next_sibling()
+                .is_some_and(|n| n.kind() == "&");
+            let child_scope = if backgrounded { Scope::Nested } else { scope };
+            visit_node(cursor, source, vars, child_scope, commands);
+            if !cursor.goto_next_sibling() {
+                break;
+            }
+        }
+        cursor.goto_parent();
+    }
+}
+
+/// Record `NAME=value` (or `NAME+=value`) so later references can be expanded.
+///
+/// Only unconditional top-level assignments are recorded. One that may not
+/// run, may run more than once, or runs in a subshell makes the variable
+/// unknown instead, so `X=rm; true || X=echo; $X` stays a dynamic command.
+fn record_assignment(node: Node, source: &str, vars: &mut Vars, scope: Scope) {
+    let Some(name_node) = node.child_by_field_name("name") else {
+        return;
+    };
+    let name = node_text(name_node, source);
+    if scope == Scope::Nested || vars.volatile.contains(&name) {
+        vars.forget(&name);
+        return;
+    }
+    let value = node
+        .child_by_field_name("value")
+        .map(|v| expand_word(v, source, vars))
+        .unwrap_or_default();
+
+    let mut walker = node.walk();
+    let is_append = node.children(&mut walker).any(|c| c.kind() == "+=");
+    if is_append {
+        // Appending to an unknown value gives an unknown value
+        if let Some(known) = vars.known.get_mut(&name) {
+            known.push_str(&value);
+        }
+    } else {
+        vars.known.insert(name, value);
+    }
+}
+
+/// Builtins that set the variables named in their arguments (`read X`,
+/// `printf -v X`). Any identifier-shaped argument is treated as written.
+fn written_names<'a>(program: &str, args: &'a [String]) -> impl Iterator<Item = &'a String> {
+    let writes = matches!(
+        program,
+        "read" | "readarray" | "mapfile" | "getopts" | "printf"
+    );
+    args.iter().filter(move |arg| {
+        writes
+            && arg.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+            && arg.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+    })
+}
+
+/// Names any statement under `node` may write
+fn collect_writes(node: Node, source: &str, names: &mut HashSet<String>) {
+    let mut walker = node.walk();
+    match node.kind() {
+        "variable_assignment" => {
+            if let Some(name) = node.child_by_field_name("name") {
+                names.insert(node_text(name, source));
+            }
+        }
+        "for_statement" => {
+            if let Some(name) = node.child_by_field_name("variable") {
+                names.insert(node_text(name, source));
+            }
+        }
+        "unset_command" => {
+            names.extend(
+                node.named_children(&mut walker)
+                    .map(|child| node_text(child, source)),
+            );
+        }
+        "command" => {
+            let program = node
+                .child_by_field_name("name")
+                .map(|n| node_text(n, source))
+                .unwrap_or_default();
+            let args: Vec<String> = node
+                .children_by_field_name("argument", &mut walker)
+                .map(|arg| unquote(&node_text(arg, source)))
+                .collect();
+            names.extend(written_names(&program, &args).cloned());
+        }
+        _ => {}
+    }
+    let mut walker = node.walk();
+    for child in node.children(&mut walker) {
+        collect_writes(child, source, names);
+    }
+}
+
+/// Names written inside any function body in the tree, other than `except`
+fn collect_function_writes(
+    node: Node,
+    source: &str,
+    except: Option<usize>,
+    names: &mut HashSet<String>,
+) {
+    if node.kind() == "function_definition" && Some(node.id()) != except {
+        collect_writes(node, source, names);
+        return;
+    }
+    let mut walker = node.walk();
+    for child in node.children(&mut walker) {
+        collect_function_writes(child, source, except, names);
+    }
+}
+
+fn extract_command(cursor: &mut TreeCursor, source: &str, vars: &Vars) -> Option<CommandInfo> {
     let node = cursor.node();
     let raw = node.utf8_text(source.as_bytes()).ok()?.to_string();
 
     let mut parts: Vec<String> = Vec::new();
+    let mut redirects = Vec::new();
 
     // Walk through command children to get words
     if cursor.goto_first_child() {
         loop {
             let child = cursor.node();
             match child.kind() {
-                "word" | "simple_expansion" | "number" => {
+                "word" | "number" => {
                     if let Ok(text) = child.utf8_text(source.as_bytes()) {
                         parts.push(text.to_string());
                     }
                 }
-                "string" | "raw_string" => {
-                    // Handle quoted strings - extract the content without quotes
-                    if let Ok(text) = child.utf8_text(source.as_bytes()) {
-                        let unquoted = unquote(text);
-                        parts.push(unquoted);
-                    }
+                // Variable references and quoted strings - expand known variables,
+                // strip quotes (single-quoted strings are never expanded)
+                "simple_expansion" | "expansion" | "string" | "raw_string" => {
+                    parts.push(expand_word(child, source, vars));
                 }
                 "concatenation" => {
                     // Handle concatenated strings (e.g., "foo"bar)
-                    if let Some(text) = extract_concatenation(cursor, source) {
+                    let text = expand_word(child, source, vars);
+                    if !text.is_empty() {
                         parts.push(text);
                     }
                 }
-                "command_name" => {
-                    // Command name can contain word or string
-                    if cursor.goto_first_child() {
-                        let name_node = cursor.node();
-                        if let Ok(text) = name_node.utf8_text(source.as_bytes()) {
-                            parts.push(unquote(text));
-                        }
-                        cursor.goto_parent();
-                    }
+                // Command name can contain word, string or expansion
+                "command_name" if cursor.goto_first_child() => {
+                    parts.push(expand_word(cursor.node(), source, vars));
+                    cursor.goto_parent();
+                }
+                "file_redirect" | "herestring_redirect" => {
+                    collect_redirects(child, source, vars, &mut redirects);
                 }
                 _ => {}
             }
@@ -201,28 +456,206 @@ fn extract_command(cursor: &mut TreeCursor, source: &str) -> Option<CommandInfo>
     let program = parts.remove(0);
     let args = parts;
 
-    Some(CommandInfo { raw, program, args })
+    Some(CommandInfo {
+        raw,
+        program,
+        args,
+        redirects,
+        flow: command_flow(node),
+    })
 }
 
-fn extract_concatenation(cursor: &mut TreeCursor, source: &str) -> Option<String> {
-    let mut result = String::new();
-    if cursor.goto_first_child() {
-        loop {
-            let child = cursor.node();
-            if let Ok(text) = child.utf8_text(source.as_bytes()) {
-                result.push_str(&unquote(text));
+/// Where a command node sits in the control flow (see [`Flow`])
+fn command_flow(node: Node) -> Flow {
+    let mut piped = false;
+    let mut chain = None;
+    let mut current = node;
+    while let Some(parent) = current.parent() {
+        // `cmd &` runs in a background subshell
+        if current.next_sibling().is_some_and(|n| n.kind() == "&") {
+            return Flow::Nested;
+        }
+        match parent.kind() {
+            "program" => break,
+            "pipeline" => piped = true,
+            "redirected_statement" | "negated_command" => {}
+            "list" => {
+                let mut walker = parent.walk();
+                if parent.children(&mut walker).any(|c| c.kind() == "||") {
+                    return Flow::Nested;
+                }
+                chain = Some(parent.id());
             }
-            if !cursor.goto_next_sibling() {
-                break;
+            _ => return Flow::Nested,
+        }
+        current = parent;
+    }
+    match (piped, chain) {
+        (true, chain) => Flow::Piped(chain),
+        (false, Some(id)) => Flow::Chain(id),
+        (false, None) => Flow::Sequential,
+    }
+}
+
+/// Parse a redirect node (`file_redirect`, `heredoc_redirect`, `herestring_redirect`).
+///
+/// Heredocs can carry further redirects (`cat <<EOF > out.txt`), so this may
+/// push more than one entry.
+fn collect_redirects(node: Node, source: &str, vars: &Vars, redirects: &mut Vec<Redirect>) {
+    let mut walker = node.walk();
+    match node.kind() {
+        "file_redirect" => {
+            let mut fd = None;
+            let mut op = None;
+            let mut target = None;
+            for child in node.children(&mut walker) {
+                match child.kind() {
+                    "file_descriptor" => fd = node_text(child, source).parse().ok(),
+                    // Process substitution (`> >(tee log)`) is not a file target
+                    "process_substitution" => return,
+                    kind if !child.is_named() => {
+                        if op.is_none() {
+                            op = RedirectOp::from_token(kind);
+                        }
+                    }
+                    _ => {
+                        if target.is_none() {
+                            target = Some(expand_word(child, source, vars));
+                        }
+                    }
+                }
+            }
+            if let Some(op) = op {
+                redirects.push(Redirect {
+                    fd,
+                    op,
+                    // `>&-` closes the descriptor and has no destination
+                    target: target.unwrap_or_else(|| "-".to_string()),
+                    body: None,
+                });
             }
         }
-        cursor.goto_parent();
+        "heredoc_redirect" => {
+            let mut delimiter = String::new();
+            let mut quoted = false;
+            let mut strip_tabs = false;
+            let mut body = String::new();
+            let mut nested = Vec::new();
+            for child in node.children(&mut walker) {
+                match child.kind() {
+                    "<<-" => strip_tabs = true,
+                    "heredoc_start" => {
+                        let start = node_text(child, source);
+                        // `<<'EOF'`, `<<"EOF"` and `<<\EOF` disable expansion in the body
+                        quoted = start.contains(['\'', '"', '\\']);
+                        delimiter = unquote(&start).trim_start_matches('\\').to_string();
+                    }
+                    "heredoc_body" => body = node_text(child, source),
+                    "file_redirect" | "herestring_redirect" => {
+                        collect_redirects(child, source, vars, &mut nested);
+                    }
+                    _ => {}
+                }
+            }
+            if strip_tabs {
+                body = body
+                    .split('\n')
+                    .map(|line| line.trim_start_matches('\t'))
+                    .collect::<Vec<_>>()
+                    .join("\n");
+            }
+            if !quoted {
+                body = substitute_vars(&body, vars);
+            }
+            redirects.push(Redirect {
+                fd: None,
+                op: RedirectOp::Heredoc,
+                target: delimiter,
+                body: Some(body),
+            });
+            redirects.extend(nested);
+        }
+        "herestring_redirect" => {
+            let target = node
+                .named_children(&mut walker)
+                .next()
+                .map(|child| expand_word(child, source, vars))
+                .unwrap_or_default();
+            redirects.push(Redirect {
+                fd: None,
+                op: RedirectOp::HereString,
+                target,
+                body: None,
+            });
+        }
+        _ => {}
     }
-    if result.is_empty() {
-        None
-    } else {
-        Some(result)
+}
+
+fn node_text(node: Node, source: &str) -> String {
+    node.utf8_text(source.as_bytes())
+        .unwrap_or_default()
+        .to_string()
+}
+
+/// Text of a word-like node with quotes removed and known variables expanded.
+/// Single-quoted parts are kept literal, as bash does.
+fn expand_word(node: Node, source: &str, vars: &Vars) -> String {
+    match node.kind() {
+        "raw_string" => unquote(&node_text(node, source)),
+        "concatenation" => {
+            let mut walker = node.walk();
+            node.children(&mut walker)
+                .map(|child| expand_word(child, source, vars))
+                .collect()
+        }
+        _ => substitute_vars(&unquote(&node_text(node, source)), vars),
+    }
+}
+
+/// Replace `$NAME` and `${NAME}` with known values. Unknown variables stay literal.
+fn substitute_vars(text: &str, vars: &Vars) -> String {
+    if vars.known.is_empty() || !text.contains('$') {
+        return text.to_string();
     }
+
+    let is_name_char = |c: char| c.is_ascii_alphanumeric() || c == '_';
+    let chars: Vec<char> = text.chars().collect();
+    let mut result = String::with_capacity(text.len());
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        if c == '\\' && i + 1 < chars.len() {
+            result.push(c);
+            result.push(chars[i + 1]);
+            i += 2;
+            continue;
+        }
+        if c == '$' {
+            let braced = chars.get(i + 1) == Some(&'{');
+            let start = if braced { i + 2 } else { i + 1 };
+            let mut end = start;
+            while end < chars.len() && is_name_char(chars[end]) {
+                end += 1;
+            }
+            let valid = end > start
+                && !chars[start].is_ascii_digit()
+                && (!braced || chars.get(end) == Some(&'}'));
+            if valid {
+                let name: String = chars[start..end].iter().collect();
+                if let Some(value) = vars.get(&name) {
+                    result.push_str(value);
+                    i = if braced { end + 1 } else { end };
+                    continue;
+                }
+            }
+        }
+        result.push(c);
+        i += 1;
+    }
+
+    result
 }
 
 /// Remove quotes from a string
@@ -252,6 +685,8 @@ fn fallback_parse(command_string: &str) -> Vec<CommandInfo> {
         raw: command_string.to_string(),
         program,
         args,
+        redirects: Vec::new(),
+        flow: Flow::Sequential,
     });
 
     commands
@@ -321,6 +756,338 @@ mod tests {
         assert_eq!(cmds[1].program, "git");
     }
 
+    mod redirects {
+        use super::*;
+
+        #[test]
+        fn test_output_redirect() {
+            let cmds = extract_commands("echo hi > out.txt");
+            assert_eq!(cmds.len(), 1);
+            assert_eq!(cmds[0].args, vec!["hi"]);
+            assert_eq!(
+                cmds[0].redirects,
+                vec![Redirect {
+                    fd: None,
+                    op: RedirectOp::Write,
+                    target: "out.txt".to_string(),
+                    body: None,
+                }]
+            );
+        }
+
+        #[test]
+        fn test_operators() {
+            for (input, op) in [
+                ("ls >> log", RedirectOp::Append),
+                ("ls >| log", RedirectOp::Clobber),
+                ("ls &> log", RedirectOp::WriteAll),
+                ("ls &>> log", RedirectOp::AppendAll),
+                ("wc < log", RedirectOp::Read),
+                ("ls 2>&1", RedirectOp::Dup),
+            ] {
+                let cmds = extract_commands(input);
+                assert_eq!(cmds[0].redirects.len(), 1, "Failed for: {input}");
+                assert_eq!(cmds[0].redirects[0].op, op, "Failed for: {input}");
+            }
+        }
+
+        #[test]
+        fn test_file_descriptor() {
+            let cmds = extract_commands("make 2> err.log");
+            assert_eq!(cmds[0].redirects[0].fd, Some(2));
+            assert_eq!(cmds[0].redirects[0].target, "err.log");
+            assert!(cmds[0].redirects[0].writes_file());
+        }
+
+        #[test]
+        fn test_dup_is_not_file_write() {
+            let cmds = extract_commands("make 2>&1");
+            assert_eq!(cmds[0].redirects[0].target, "1");
+            assert!(!cmds[0].redirects[0].writes_file());
+        }
+
+        #[test]
+        fn test_quoted_target() {
+            let cmds = extract_commands("echo hi > \"my file.txt\"");
+            assert_eq!(cmds[0].redirects[0].target, "my file.txt");
+        }
+
+        #[test]
+        fn test_redirect_not_in_args() {
+            let cmds = extract_commands("cat a.txt > b.txt");
+            assert_eq!(cmds[0].args, vec!["a.txt"]);
+        }
+
+        #[test]
+        fn test_compound_body_inherits_redirect() {
+            let cmds = extract_commands("{ ls; pwd; } > out.txt");
+            assert_eq!(cmds.len(), 2);
+            assert!(cmds.iter().all(|c| c.redirects[0].target == "out.txt"));
+        }
+
+        #[test]
+        fn test_pipeline_redirect_only_on_last() {
+            let cmds = extract_commands("ls > a.txt | wc");
+            assert_eq!(cmds[0].redirects.len(), 1);
+            assert!(cmds[1].redirects.is_empty());
+        }
+
+        #[test]
+        fn test_subshell_body_inherits_redirect() {
+            let cmds = extract_commands("(ls && pwd) > out.txt");
+            assert_eq!(cmds.len(), 2);
+            assert!(cmds.iter().all(|c| c.redirects[0].target == "out.txt"));
+        }
+
+        #[test]
+        fn test_list_redirect_only_on_last() {
+            let cmds = extract_commands("ls && git status > out.txt");
+            assert_eq!(cmds.len(), 2);
+            assert!(cmds[0].redirects.is_empty());
+            assert_eq!(cmds[1].redirects[0].target, "out.txt");
+
+            let cmds = extract_commands("ls || pwd; echo a && echo b > out.txt");
+            let redirected: Vec<_> = cmds
+                .iter()
+                .filter(|c| !c.redirects.is_empty())
+                .map(|c| c.raw.as_str())
+                .collect();
+            assert_eq!(redirected, vec!["echo b"]);
+        }
+
+        #[test]
+        fn test_trailing_pipeline_redirect_only_on_last() {
+            let cmds = extract_commands("ls | grep x > out.txt");
+            assert_eq!(cmds.len(), 2);
+            assert!(cmds[0].redirects.is_empty());
+            assert_eq!(cmds[1].redirects[0].target, "out.txt");
+
+            let cmds = extract_commands("ls && cat a | wc -l > out.txt");
+            assert_eq!(cmds.len(), 3);
+            assert!(cmds[0].redirects.is_empty());
+            assert!(cmds[1].redirects.is_empty());
+            assert_eq!(cmds[2].redirects[0].target, "out.txt");
+        }
+
+        #[test]
+        fn test_heredoc() {
+            let cmds = extract_commands("cat <<'EOF' > out.txt\nhello\nEOF");
+            assert_eq!(cmds.len(), 1);
+            let ops: Vec<_> = cmds[0].redirects.iter().map(|r| r.op).collect();
+            assert!(ops.contains(&RedirectOp::Heredoc));
+            assert!(ops.contains(&RedirectOp::Write));
+            assert!(cmds[0].redirects.iter().any(|r| r.target == "EOF"));
+        }
+
+        #[test]
+        fn test_heredoc_body() {
+            let cmds = extract_commands("bash <<'EOF'\nrm -rf /\nEOF");
+            assert_eq!(cmds[0].stdin_text(), Some("rm -rf /\n"));
+        }
+
+        #[test]
+        fn test_heredoc_body_expansion() {
+            let cmds = extract_commands("D=/tmp; cat <<EOF\nls $D\nEOF");
+            assert_eq!(cmds[0].stdin_text(), Some("ls /tmp\n"));
+
+            // Quoted delimiter keeps the body literal
+            let cmds = extract_commands("D=/tmp; cat <<'EOF'\nls $D\nEOF");
+            assert_eq!(cmds[0].stdin_text(), Some("ls $D\n"));
+        }
+
+        #[test]
+        fn test_heredoc_strip_tabs() {
+            let cmds = extract_commands("sh <<-EOF\n\tls\n\tpwd\n\tEOF");
+            assert_eq!(cmds[0].stdin_text(), Some("ls\npwd\n"));
+        }
+
+        #[test]
+        fn test_herestring_stdin_text() {
+            let cmds = extract_commands("sh -s <<< \"rm -rf ~\"");
+            assert_eq!(cmds[0].stdin_text(), Some("rm -rf ~"));
+        }
+
+        #[test]
+        fn test_file_stdin_has_no_text() {
+            let cmds = extract_commands("bash < script.sh");
+            assert_eq!(cmds[0].stdin_text(), None);
+        }
+
+        #[test]
+        fn test_heredoc_piped_command_extracted() {
+            let cmds = extract_commands("cat <<EOF | sh\nrm -rf /\nEOF");
+            assert!(cmds.iter().any(|c| c.program == "sh"));
+        }
+
+        #[test]
+        fn test_herestring() {
+            let cmds = extract_commands("grep foo <<< \"$text\"");
+            assert_eq!(cmds[0].redirects[0].op, RedirectOp::HereString);
+            assert_eq!(cmds[0].redirects[0].target, "$text");
+        }
+
+        #[test]
+        fn test_herestring_substitution_extracted() {
+            let cmds = extract_commands("cat <<< $(rm -rf /)");
+            assert!(cmds.iter().any(|c| c.program == "rm"));
+        }
+
+        #[test]
+        fn test_process_substitution_target() {
+            let cmds = extract_commands("make > >(tee build.log)");
+            assert!(cmds[0].redirects.is_empty());
+            assert!(cmds.iter().any(|c| c.program == "tee"));
+        }
+
+        #[test]
+        fn test_bare_redirect() {
+            let cmds = extract_commands("> out.txt");
+            assert_eq!(cmds.len(), 1);
+            assert!(cmds[0].program.is_empty());
+            assert_eq!(cmds[0].redirects[0].target, "out.txt");
+        }
+    }
+
+    mod variables {
+        use super::*;
+
+        #[test]
+        fn test_assignment_expanded_in_args() {
+            let cmds = extract_commands("D=/; rm -rf $D");
+            assert_eq!(cmds.len(), 1);
+            assert_eq!(cmds[0].program, "rm");
+            assert_eq!(cmds[0].args, vec!["-rf", "/"]);
+        }
+
+        #[test]
+        fn test_braced_and_quoted_expansion() {
+            let cmds = extract_commands("D=build; rm -rf ${D} \"$D/out\"");
+            assert_eq!(cmds[0].args, vec!["-rf", "build", "build/out"]);
+        }
+
+        #[test]
+        fn test_export_concatenation() {
+            let cmds = extract_commands("export T=~; rm -rf \"$T\"/");
+            let rm = cmds.iter().find(|c| c.program == "rm").unwrap();
+            assert_eq!(rm.args, vec!["-rf", "~/"]);
+        }
+
+        #[test]
+        fn test_local_and_declare() {
+            for input in ["f() { local D=/; rm -rf $D; }", "declare D=/; rm -rf $D"] {
+                let cmds = extract_commands(input);
+                let rm = cmds.iter().find(|c| c.program == "rm").unwrap();
+                assert_eq!(rm.args, vec!["-rf", "/"], "Failed for: {input}");
+            }
+        }
+
+        #[test]
+        fn test_command_position_expanded() {
+            let cmds = extract_commands("X=rm; $X -rf /");
+            assert_eq!(cmds[0].program, "rm");
+            assert_eq!(cmds[0].args, vec!["-rf", "/"]);
+        }
+
+        #[test]
+        fn test_unknown_variable_stays_literal() {
+            let cmds = extract_commands("rm -rf $UNKNOWN");
+            assert_eq!(cmds[0].args, vec!["-rf", "$UNKNOWN"]);
+        }
+
+        #[test]
+        fn test_single_quotes_not_expanded() {
+            let cmds = extract_commands("D=/; echo '$D'");
+            assert_eq!(cmds[0].args, vec!["$D"]);
+        }
+
+        #[test]
+        fn test_prefix_assignment_is_command_scoped() {
+            // bash expands $D before the prefix assignment takes effect
+            let cmds = extract_commands("D=/ rm -rf $D; ls $D");
+            assert_eq!(cmds[0].args, vec!["-rf", "$D"]);
+            assert_eq!(cmds[1].args, vec!["$D"]);
+        }
+
+        #[test]
+        fn test_reassignment_and_append() {
+            let cmds = extract_commands("D=/tmp; D=/; P=/usr; P+=/bin; ls $D $P");
+            assert_eq!(cmds[0].args, vec!["/", "/usr/bin"]);
+        }
+
+        #[test]
+        fn test_unset_forgets_value() {
+            let cmds = extract_commands("D=/; unset D; rm -rf $D");
+            let rm = cmds.iter().find(|c| c.program == "rm").unwrap();
+            assert_eq!(rm.args, vec!["-rf", "$D"]);
+        }
+
+        #[test]
+        fn test_nested_variable_values() {
+            let cmds = extract_commands("A=/; B=$A; rm -rf $B");
+            assert_eq!(cmds[0].args, vec!["-rf", "/"]);
+        }
+
+        #[test]
+        fn test_assignment_after_or_is_unknown() {
+            let cmds = extract_commands("X=rm; true || X=echo; $X -rf /");
+            assert_eq!(cmds.last().unwrap().program, "$X");
+
+            // The left side of `&&` always runs
+            let cmds = extract_commands("X=rm && $X -rf /");
+            assert_eq!(cmds[0].program, "rm");
+        }
+
+        #[test]
+        fn test_assignment_in_if_is_unknown() {
+            let cmds = extract_commands("X=rm; if true; then X=echo; fi; $X -rf /");
+            assert_eq!(cmds.last().unwrap().program, "$X");
+        }
+
+        #[test]
+        fn test_assignment_in_loop_is_unknown_inside_and_after() {
+            let cmds = extract_commands("X=echo; while true; do $X -rf /; X=rm; done; $X");
+            let dynamic = cmds.iter().filter(|c| c.program == "$X").count();
+            assert_eq!(dynamic, 2);
+
+            let cmds = extract_commands("X=echo; for X in rm; do $X -rf /; done");
+            assert_eq!(cmds.last().unwrap().program, "$X");
+        }
+
+        #[test]
+        fn test_subshell_pipeline_and_background_assignments_are_unknown() {
+            for input in [
+                "X=echo; (X=rm); $X -rf /",
+                "X=echo; X=rm | cat; $X -rf /",
+                "X=rm; X=echo & $X -rf /",
+            ] {
+                let cmds = extract_commands(input);
+                assert_eq!(cmds.last().unwrap().program, "$X", "Failed for: {input}");
+            }
+        }
+
+        #[test]
+        fn test_function_writes_are_unknown() {
+            let cmds = extract_commands("X=echo; f() { X=rm; }; f; $X -rf /");
+            assert_eq!(cmds.last().unwrap().program, "$X");
+
+            // The body can't see the caller's values
+            let cmds = extract_commands("X=echo; f() { $X -rf /; }; X=rm; f");
+            assert_eq!(cmds[0].program, "$X");
+        }
+
+        #[test]
+        fn test_read_forgets_value() {
+            let cmds = extract_commands("X=echo; read X <<< rm; $X -rf /");
+            assert_eq!(cmds.last().unwrap().program, "$X");
+        }
+
+        #[test]
+        fn test_substitution_in_prefix_extracted() {
+            let cmds = extract_commands("X=$(rm -rf /) ls");
+            assert!(cmds.iter().any(|c| c.program == "rm"));
+        }
+    }
+
     #[test]
     fn test_pipeline() {
         let cmds = extract_commands("gh pr list | head");
@@ -1,8 +1,28 @@
 //! Pattern suggestion for commands that return "ask".
 //!
-//! Generates appropriate patterns for settings.json based on command structure.
+//! Generates appropriate patterns for settings.json based on command structure,
+//! and merges pending entries of the same shape into one covering pattern.
 
 use crate::models::CommandInfo;
+use crate::pending::PendingApproval;
+use crate::rules_lint::DANGEROUS_SAMPLES;
+use crate::settings::Settings;
+use crate::tracking::CommandPart;
+
+/// How many commands outside a cluster to list per merged suggestion
+const MAX_ALSO_ALLOWS: usize = 3;
+
+/// One pattern covering several pending entries with the same program and subcommand
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MergedSuggestion {
+    /// Narrowest pattern that matches every entry in the cluster
+    pub pattern: String,
+    /// IDs of the pending entries the pattern covers
+    pub entry_ids: Vec<String>,
+    /// Commands outside the cluster the pattern would also allow: other pending
+    /// commands with a matching segment, and dangerous samples it matches
+    pub also_allows: Vec<String>,
+}
 
 /// Generate suggested approval patterns for a command.
 ///
@@ -10,6 +30,11 @@ use crate::models::CommandInfo;
 pub fn suggest_patterns(cmd: &CommandInfo) -> Vec<String> {
     let mut patterns = Vec::new();
 
+    // Bare redirect (`> file`) has no program to build a pattern from
+    if cmd.program.is_empty() {
+        return patterns;
+    }
+
     // Package managers - suggest subcommand-specific first
     match cmd.program.as_str() {
         "npm" | "pnpm" | "yarn" | "bun" => {
@@ -161,6 +186,139 @@ pub fn suggest_patterns(cmd: &CommandInfo) -> Vec<String> {
     patterns
 }
 
+/// Group pending entries by program and subcommand, and propose one pattern per group.
+///
+/// Only entries with exactly one segment needing approval take part (a pattern
+/// can't cover `cargo test && ./deploy.sh`). Commands starting with a flag
+/// group by program alone. Groups need at least two entries.
+/// The proposed pattern is the narrowest of the word-level common prefix
+/// (`cargo test -p foo`, `cargo test -p bar --lib` -> `cargo test -p:*`, minus
+/// trailing flags -> `cargo test:*`) and the members' own suggestions that still
+/// match every member (`npm run test*` for `test:unit` and `test:e2e`).
+/// Largest clusters come first.
+pub fn merge_suggestions(entries: &[PendingApproval]) -> Vec<MergedSuggestion> {
+    let mut groups: Vec<((&str, &str), Vec<ClusterMember>)> = Vec::new();
+    for entry in entries {
+        let Some(part) = single_asked_part(entry) else {
+            continue;
+        };
+        let subcommand = part
+            .args
+            .first()
+            .filter(|a| !a.starts_with('-'))
+            .map_or("", String::as_str);
+        let key = (part.program.as_str(), subcommand);
+        match groups.iter_mut().find(|(k, _)| *k == key) {
+            Some((_, members)) => members.push((entry, part)),
+            None => groups.push((key, vec![(entry, part)])),
+        }
+    }
+
+    let mut merged: Vec<MergedSuggestion> = groups
+        .into_iter()
+        .filter(|(_, members)| members.len() > 1)
+        .filter_map(|(_, members)| {
+            let commands: Vec<String> = members
+                .iter()
+                .map(|(_, part)| part.command_line())
+                .collect();
+            let pattern = narrowest_covering(&members, &commands)?;
+            let entry_ids: Vec<String> = members.iter().map(|(e, _)| e.id.clone()).collect();
+            let also_allows = also_allows(&pattern, entries, &entry_ids);
+            Some(MergedSuggestion {
+                pattern,
+                entry_ids,
+                also_allows,
+            })
+        })
+        .collect();
+    merged.sort_by_key(|m| std::cmp::Reverse(m.entry_ids.len()));
+    merged
+}
+
+/// A pending entry and its one segment that needed approval
+type ClusterMember<'a> = (&'a PendingApproval, &'a CommandPart);
+
+/// The only segment of an entry that needed approval
+fn single_asked_part(entry: &PendingApproval) -> Option<&CommandPart> {
+    let mut asked = entry.breakdown.iter().filter(|p| p.decision != "allow");
+    let part = asked.next()?;
+    asked.next().is_none().then_some(part)
+}
+
+/// Narrowest candidate pattern matching every command in the cluster
+fn narrowest_covering(members: &[ClusterMember], commands: &[String]) -> Option<String> {
+    let words: Vec<Vec<&str>> = commands.iter().map(|c| c.split(' ').collect()).collect();
+    let mut common: Vec<&str> = words[0].clone();
+    for other in &words[1..] {
+        let shared = common.iter().zip(other).take_while(|(a, b)| a == b).count();
+        common.truncate(shared);
+    }
+    // `cargo test -p:*` reads as a typo; drop trailing flags whose values differ
+    while common.len() > 2 && common.last().is_some_and(|w| w.starts_with('-')) {
+        common.pop();
+    }
+
+    // A bare `program:*` only comes from the members' own suggestions, so programs
+    // suggest_patterns keeps narrow (git) stay narrow
+    let mut candidates = Vec::new();
+    if common.len() > 1 {
+        candidates.push(format!("{}:*", common.join(" ")));
+    }
+    for (entry, part) in members {
+        candidates.extend(entry.patterns.iter().cloned());
+        candidates.extend(suggest_patterns(&CommandInfo {
+            raw: part.command_line(),
+            program: part.program.clone(),
+            args: part.args.clone(),
+            redirects: Vec::new(),
+            flow: Default::default(),
+        }));
+    }
+
+    candidates
+        .into_iter()
+        .filter(|p| {
+            commands
+                .iter()
+                .all(|c| Settings::matches_bash_pattern(p, c))
+        })
+        .max_by_key(|p| narrowness(p))
+}
+
+/// Sort key: longer literal prefix first, then exact > `:*` > `*`
+fn narrowness(pattern: &str) -> (usize, u8) {
+    if let Some(prefix) = pattern.strip_suffix(":*") {
+        (prefix.len(), 1)
+    } else if let Some(prefix) = pattern.strip_suffix('*') {
+        (prefix.len(), 0)
+    } else {
+        (pattern.len(), 2)
+    }
+}
+
+/// Commands outside the cluster the pattern would also allow
+fn also_allows(pattern: &str, entries: &[PendingApproval], cluster: &[String]) -> Vec<String> {
+    let pending = entries
+        .iter()
+        .filter(|e| !cluster.contains(&e.id))
+        .filter(|e| {
+            e.breakdown
+                .iter()
+                .filter(|p| p.decision != "allow")
+                .any(|p| Settings::matches_bash_pattern(pattern, &p.command_line()))
+        })
+        .map(|e| e.command.clone());
+    let dangerous = DANGEROUS_SAMPLES
+        .iter()
+        .filter(|sample| Settings::matches_bash_pattern(pattern, sample))
+        .map(|sample| sample.to_string());
+    let mut samples: Vec<String> = dangerous.chain(pending).collect();
+    samples.dedup();
+    samples.truncate(MAX_ALSO_ALLOWS);
+    samples
+}
+
 /// Extract domain from a URL
 fn extract_domain(url: &str) -> Option<String> {
     // Simple extraction - strip protocol and path
@@ -188,6 +346,8 @@ mod tests {
             raw: format!("{} {}", program, args.join(" ")),
             program: program.to_string(),
             args: args.iter().map(|s| s.to_string()).collect(),
+            redirects: Vec::new(),
+            flow: Default::default(),
         }
     }
 
@@ -236,6 +396,112 @@ mod tests {
         assert!(patterns.contains(&"aws ec2:*".to_string()));
     }
 
+    fn pending(command: &str) -> PendingApproval {
+        let parts: Vec<CommandPart> = command
+            .split(" && ")
+            .map(|segment| {
+                let words: Vec<String> = segment.split(' ').map(String::from).collect();
+                let decision = if words[0] == "git" {
+                    crate::models::Decision::Allow
+                } else {
+                    crate::models::Decision::Ask
+                };
+                CommandPart::new(&words[0], &words[1..], decision, "")
+            })
+            .collect();
+        let patterns = parts
+            .iter()
+            .filter(|p| p.decision == "ask")
+            .flat_map(|p| {
+                let args: Vec<&str> = p.args.iter().map(String::as_str).collect();
+                suggest_patterns(&cmd(&p.program, &args))
+            })
+            .collect();
+        PendingApproval::new(
+            command.to_string(),
+            patterns,
+            parts,
+            "/tmp/project".to_string(),
+            "/tmp/project".to_string(),
+            "session1".to_string(),
+        )
+    }
+
+    fn commands_of(merged: &MergedSuggestion, entries: &[PendingApproval]) -> Vec<String> {
+        entries
+            .iter()
+            .filter(|e| merged.entry_ids.contains(&e.id))
+            .map(|e| e.command.clone())
+            .collect()
+    }
+
+    #[test]
+    fn test_merge_common_prefix() {
+        let entries = vec![
+            pending("cargo test -p foo --lib"),
+            pending("cargo test -p bar"),
+            pending("cargo test -p baz --doc"),
+            pending("cargo build"),
+        ];
+        let merged = merge_suggestions(&entries);
+        assert_eq!(merged.len(), 1);
+        assert_eq!(merged[0].pattern, "cargo test:*");
+        assert_eq!(merged[0].entry_ids.len(), 3);
+        assert!(merged[0].also_allows.is_empty());
+    }
+
+    #[test]
+    fn test_merge_keeps_longer_prefix() {
+        let entries = vec![
+            pending("docker compose up -d api"),
+            pending("docker compose up -d web"),
+        ];
+        assert_eq!(
+            merge_suggestions(&entries)[0].pattern,
+            "docker compose up:*"
+        );
+
+        // Members' own glob suggestions win when narrower than the word prefix
+        let entries = vec![pending("npm run test:unit"), pending("npm run test:e2e")];
+        assert_eq!(merge_suggestions(&entries)[0].pattern, "npm run test*");
+    }
+
+    #[test]
+    fn test_merge_uses_only_asked_segment() {
+        let entries = vec![
+            pending("git status && make build"),
+            pending("make build -j4"),
+            pending("make build && make install"),
+        ];
+        let merged = merge_suggestions(&entries);
+        assert_eq!(merged.len(), 1);
+        assert_eq!(merged[0].pattern, "make build:*");
+        assert_eq!(
+            commands_of(&merged[0], &entries),
+            ["git status && make build", "make build -j4"]
+        );
+        // The compound entry with two asked segments isn't covered, but shares one
+        assert_eq!(merged[0].also_allows, ["make build && make install"]);
+    }
+
+    #[test]
+    fn test_merge_reports_dangerous_samples() {
+        let entries = vec![pending("rm -rf build"), pending("rm -rf dist")];
+        let merged = merge_suggestions(&entries);
+        assert_eq!(merged[0].pattern, "rm -rf:*");
+        assert_eq!(merged[0].also_allows, ["rm -rf /", "rm -rf /*", "rm -rf ~"]);
+    }
+
+    #[test]
+    fn test_merge_needs_two_entries() {
+        let entries = vec![pending("cargo test"), pending("cargo build")];
+        assert!(merge_suggestions(&entries).is_empty());
+
+        // Flag-first commands group by program
+        let entries = vec![pending("pytest -x"), pending("pytest -k foo")];
+        assert_eq!(merge_suggestions(&entries)[0].pattern, "pytest:*");
+    }
+
     #[test]
     fn test_extract_domain() {
         assert_eq!(
@@ -277,7 +277,7 @@ pub fn derive_projects(entries: &[PendingApproval]) -> Vec<ProjectInfo> {
     }
 
     let mut result: Vec<ProjectInfo> = projects.into_values().collect();
-    result.sort_by(|a, b| b.count.cmp(&a.count));
+    result.sort_by_key(|b| std::cmp::Reverse(b.count));
     result
 }
 
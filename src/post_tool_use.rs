@@ -1,8 +1,10 @@
 //! PostToolUse hook handler.
 //!
 //! Detects when commands that returned "ask" complete successfully,
-//! and adds them to the pending approval queue.
+//! and adds them to the pending approval queue. Failed ones go to the
+//! declined queue instead.
 
+use crate::declined::{DeclineKind, DeclinedCommand, record_declined};
 use crate::models::{PostToolUseInput, PostToolUseOutput};
 use crate::pending::{PendingApproval, append_pending};
 use crate::tracking::take_tracked_command;
@@ -10,17 +12,21 @@ use crate::tracking::take_tracked_command;
 /// Handle a PostToolUse hook event.
 ///
 /// If the tool_use_id was tracked (command returned "ask") and the command
-/// succeeded, add it to the pending approval queue.
+/// succeeded, add it to the pending approval queue; if it failed, record it
+/// as failed in the declined queue.
 ///
 /// Returns `Some(output)` with optional additional context, or `None` to pass through.
 pub fn handle_post_tool_use(input: &PostToolUseInput) -> Option<PostToolUseOutput> {
     // Atomically remove-and-return the tracked command in a single lock scope.
     // This avoids the TOCTOU race of peek-then-take (two separate lock acquisitions).
-    let tracked = take_tracked_command(&input.tool_use_id)?;
+    let tracked = take_tracked_command(&input.tool_use_id, &input.session_id)?;
 
     // Only add to pending if the command succeeded
     if !input.is_success() {
-        // Already removed from tracking above — nothing more to do
+        let failed = DeclinedCommand::from_tracked(tracked, DeclineKind::Failed);
+        if let Err(e) = record_declined(vec![failed]) {
+            eprintln!("Warning: Failed to save failed command: {e}");
+        }
         return None;
     }
 
@@ -0,0 +1,398 @@
+//! PreToolUse hook decision.
+//!
+//! Routes a tool call by tool type: configurable `block_tools` rules for every
+//! tool, secret detection and the gate engine for Bash, file guards and
+//! security reminders for file tools, `auto_approve_skills` for Skill, and settings.json rules for the rest.
+//!
+//! The hook and `tool-gates replay` share this. The hook adds the side effects
+//! (printing, ask tracking for the pending queue, the audit log) around it.
+
+use crate::config::{Config, SecurityRemindersConfig};
+use crate::engine::{Engine, SegmentVerdict};
+use crate::explain;
+use crate::file_guards::check_file_guard;
+use crate::models::{HookInput, HookOutput, PermissionMode};
+use crate::repo_policy;
+use crate::security_reminders::{ReminderRules, check_bash_secrets, check_security_reminders};
+use crate::settings::{EDIT_TOOLS, READ_TOOLS, Settings, SettingsDecision, ToolUse};
+use crate::tool_blocks::check_tool_block;
+use crate::trace;
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::Path;
+
+/// A PreToolUse output plus what the audit log records about it
+#[derive(Debug, Default)]
+pub struct PreToolUseResult {
+    /// `None` prints nothing (pass through)
+    pub output: Option<HookOutput>,
+    /// Rule or mechanism that decided
+    pub source: Option<String>,
+    /// Modern CLI hints shown (`cat -> bat`)
+    pub hints: Vec<String>,
+    /// Parsed Bash segments and their gate results
+    pub segments: Vec<SegmentVerdict>,
+    /// The Bash command with its secrets redacted, when denied for containing one
+    pub redacted_command: Option<String>,
+}
+
+impl PreToolUseResult {
+    pub fn new(output: HookOutput, source: impl Into<String>) -> Self {
+        Self {
+            output: Some(output),
+            source: Some(source.into()),
+            ..Default::default()
+        }
+    }
+
+    pub fn pass_through() -> Self {
+        Self::default()
+    }
+
+    fn no_opinion() -> Self {
+        Self {
+            output: Some(HookOutput::no_opinion()),
+            ..Default::default()
+        }
+    }
+
+    /// `allow`, `ask`, `deny`, or `none` for no opinion
+    pub fn decision(&self) -> &str {
+        self.output
+            .as_ref()
+            .and_then(|o| o.hook_specific_output.as_ref())
+            .map_or("none", |hso| hso.permission_decision.as_str())
+    }
+}
+
+/// Decide a PreToolUse call with the engine's config.
+///
+/// The engine supplies cwd, permission mode and session for Bash. `trace`
+/// collects a trace to name the deciding rule in [`PreToolUseResult::source`].
+pub fn decide_pre_tool_use(
+    engine: &Engine,
+    hook_input: &HookInput,
+    tool_input_map: &serde_json::Map<String, serde_json::Value>,
+    trace: bool,
+) -> PreToolUseResult {
+    let config = engine.config();
+
+    // Check configurable block rules first (applies to ALL tool types)
+    if let Some(output) =
+        check_tool_block(&hook_input.tool_name, tool_input_map, config.block_rules())
+    {
+        return PreToolUseResult::new(output, "block_tools (config.toml)");
+    }
+
+    // settings.json rules for other tools (the router applies them to Bash).
+    // tool-gates must never allow what a deny or ask rule covers.
+    let settings_decision = if hook_input.tool_name == "Bash" {
+        SettingsDecision::NoMatch
+    } else {
+        check_tool_settings(
+            engine,
+            &hook_input.tool_name,
+            tool_input_map,
+            &hook_input.cwd,
+        )
+    };
+    if settings_decision == SettingsDecision::Deny {
+        return PreToolUseResult::new(
+            HookOutput::deny("Matched settings.json deny rule"),
+            "settings.json",
+        );
+    }
+    // bypassPermissions keeps deny rules but never prompts
+    let bypass =
+        PermissionMode::parse(&hook_input.permission_mode) == PermissionMode::BypassPermissions;
+    let settings_ask = || {
+        if settings_decision == SettingsDecision::Ask && !bypass {
+            PreToolUseResult::new(
+                HookOutput::ask("Matched settings.json ask rule"),
+                "settings.json",
+            )
+        } else {
+            PreToolUseResult::pass_through()
+        }
+    };
+
+    // Route by tool type
+    match hook_input.tool_name.as_str() {
+        // Bash tools: full gate engine
+        "Bash" => {
+            // Tier 1 secrets in the command (curl tokens, exported keys)
+            if config.features.security_reminders {
+                let command = hook_input.get_command();
+                let rules = ReminderRules::load(&hook_input.cwd);
+                if let Some(output) =
+                    check_bash_secrets(&command, &reminder_config(config, &hook_input.cwd), &rules)
+                {
+                    let mut result = PreToolUseResult::new(output, "security_reminders");
+                    result.redacted_command = Some(rules.redact_command(&command));
+                    return result;
+                }
+            }
+            if !config.features.bash_gates {
+                return PreToolUseResult::no_opinion();
+            }
+            decide_bash(engine, hook_input, trace)
+        }
+        // File tools: symlink guard + security reminders
+        "Read" | "Write" | "Edit" | "MultiEdit" => {
+            // 1. File guards: symlink check for AI config files
+            if config.features.file_guards {
+                let file_paths = extract_file_paths_from_map(tool_input_map);
+                for file_path in &file_paths {
+                    if let Some(output) =
+                        check_file_guard(file_path, &hook_input.tool_name, &config.file_guards)
+                    {
+                        return PreToolUseResult::new(output, "file_guards");
+                    }
+                }
+            }
+
+            // 2. Security reminders: content scanning for Write/Edit/MultiEdit
+            if config.features.security_reminders && hook_input.tool_name != "Read" {
+                let reminders = reminder_config(config, &hook_input.cwd);
+                let original = if hook_input.tool_name == "Write" {
+                    original_file(&hook_input.cwd, tool_input_map)
+                } else {
+                    None
+                };
+                if let Some(output) = check_security_reminders(
+                    &hook_input.tool_name,
+                    tool_input_map,
+                    original.as_deref(),
+                    &reminders,
+                    &ReminderRules::load(&hook_input.cwd),
+                    engine.sessions(),
+                    &hook_input.session_id,
+                ) {
+                    return PreToolUseResult::new(output, "security_reminders");
+                }
+            }
+            // No output = allow (pass through)
+            settings_ask()
+        }
+        // Skill tool: auto-approve based on config rules
+        "Skill"
+            if !config.auto_approve_skills.is_empty()
+                && settings_decision == SettingsDecision::NoMatch =>
+        {
+            let skill_name = tool_input_map
+                .get("skill")
+                .and_then(|v| v.as_str())
+                .unwrap_or("");
+            let project_dir = std::env::var("CLAUDE_PROJECT_DIR").unwrap_or_default();
+
+            for rule in &config.auto_approve_skills {
+                if rule.matches_skill(skill_name) && rule.conditions_met(&project_dir) {
+                    let reason = rule
+                        .message
+                        .as_deref()
+                        .and_then(|m| if m.is_empty() { None } else { Some(m) });
+                    return PreToolUseResult::new(
+                        HookOutput::allow(reason),
+                        format!("auto_approve_skills: {} (config.toml)", rule.skill),
+                    );
+                }
+            }
+            // No match = pass through (no opinion)
+            PreToolUseResult::pass_through()
+        }
+        // All other tools: pass through (blocks already checked above)
+        _ => settings_ask(),
+    }
+}
+
+/// Security reminder settings, plus rules a trusted `.tool-gates.toml` disables for its repository
+fn reminder_config(config: &Config, cwd: &str) -> SecurityRemindersConfig {
+    let mut reminders = config.security_reminders.clone();
+    if let Some(policy) = repo_policy::discover(cwd) {
+        reminders
+            .disable_rules
+            .extend_from_slice(policy.disabled_reminders());
+    }
+    reminders
+}
+
+/// Bash: the gate engine with settings.json awareness, mode detection and hint dedup
+fn decide_bash(engine: &Engine, hook_input: &HookInput, trace: bool) -> PreToolUseResult {
+    let command = hook_input.get_command();
+    if command.is_empty() {
+        return PreToolUseResult::no_opinion();
+    }
+
+    let (mut verdict, trace) = if trace {
+        trace::collect(|| engine.check(&command))
+    } else {
+        (engine.check(&command), Vec::new())
+    };
+
+    let segments = std::mem::take(&mut verdict.segments);
+    let hints: Vec<String> = verdict
+        .hints
+        .iter()
+        .map(|h| format!("{} -> {}", h.legacy_command, h.modern_command))
+        .collect();
+    let output = verdict.into_hook_output();
+
+    let source = output
+        .hook_specific_output
+        .as_ref()
+        .and_then(|hso| explain::decision_source(trace, &hook_input.cwd, &hso.permission_decision));
+    PreToolUseResult {
+        output: Some(output),
+        source,
+        hints,
+        segments,
+        ..Default::default()
+    }
+}
+
+/// settings.json decision for a non-Bash tool call, strictest across its paths.
+pub fn check_tool_settings(
+    engine: &Engine,
+    tool_name: &str,
+    map: &serde_json::Map<String, serde_json::Value>,
+    cwd: &str,
+) -> SettingsDecision {
+    let is_file_tool = EDIT_TOOLS.contains(&tool_name) || READ_TOOLS.contains(&tool_name);
+    let mut paths = Vec::new();
+    if is_file_tool {
+        paths = extract_file_paths_from_map(map);
+        // NotebookEdit, and the search path of Glob/Grep/LS
+        for key in ["notebook_path", "path"] {
+            if let Some(path) = map.get(key).and_then(|v| v.as_str()) {
+                if !path.is_empty() {
+                    paths.push(path.to_string());
+                }
+            }
+        }
+    }
+    let url = map.get("url").and_then(|v| v.as_str());
+
+    let root = Settings::project_root(cwd);
+    let uses: Vec<ToolUse> = match url {
+        Some(url) if tool_name == "WebFetch" => vec![ToolUse::Fetch(url)],
+        _ if paths.is_empty() => vec![ToolUse::Tool(tool_name)],
+        _ => paths
+            .iter()
+            .map(|path| ToolUse::File {
+                tool: tool_name,
+                path,
+                cwd,
+                root: &root,
+            })
+            .collect(),
+    };
+
+    let settings = engine.settings_for(cwd);
+    let decisions: Vec<SettingsDecision> = uses.iter().map(|u| settings.check_tool(u)).collect();
+    [
+        SettingsDecision::Deny,
+        SettingsDecision::Ask,
+        SettingsDecision::Allow,
+    ]
+    .into_iter()
+    .find(|d| decisions.contains(d))
+    .unwrap_or(SettingsDecision::NoMatch)
+}
+
+/// Largest Write target read for comparison; the content of a bigger one is scanned whole
+const MAX_ORIGINAL_FILE_BYTES: u64 = 1024 * 1024;
+
+/// Current content of a Write's target, so security reminders only report what the write adds.
+///
+/// Only regular files up to [`MAX_ORIGINAL_FILE_BYTES`] are read: a FIFO or
+/// device would block the hook or never end.
+fn original_file(
+    cwd: &str,
+    tool_input_map: &serde_json::Map<String, serde_json::Value>,
+) -> Option<String> {
+    let file_path = tool_input_map.get("file_path").and_then(|v| v.as_str())?;
+    let path = Path::new(cwd).join(file_path);
+    let metadata = fs::metadata(&path).ok()?;
+    if !metadata.is_file() || metadata.len() > MAX_ORIGINAL_FILE_BYTES {
+        return None;
+    }
+    // The file can grow after the check
+    let mut content = String::new();
+    File::open(&path)
+        .ok()?
+        .take(MAX_ORIGINAL_FILE_BYTES + 1)
+        .read_to_string(&mut content)
+        .ok()?;
+    (content.len() as u64 <= MAX_ORIGINAL_FILE_BYTES).then_some(content)
+}
+
+/// Extract all file paths from a raw tool_input map.
+/// Handles both single-file tools (file_path) and MultiEdit (files[].file_path).
+pub fn extract_file_paths_from_map(
+    map: &serde_json::Map<String, serde_json::Value>,
+) -> Vec<String> {
+    let mut paths = Vec::new();
+
+    // Single file_path (Read/Write/Edit)
+    if let Some(fp) = map.get("file_path").and_then(|v| v.as_str()) {
+        if !fp.is_empty() {
+            paths.push(fp.to_string());
+        }
+    }
+
+    // MultiEdit: files[].file_path
+    if let Some(files) = map.get("files").and_then(|v| v.as_array()) {
+        for file in files {
+            if let Some(fp) = file.get("file_path").and_then(|v| v.as_str()) {
+                if !fp.is_empty() {
+                    paths.push(fp.to_string());
+                }
+            }
+        }
+    }
+
+    paths
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn write_input(file_path: &str) -> serde_json::Map<String, serde_json::Value> {
+        let mut map = serde_json::Map::new();
+        map.insert("file_path".to_string(), file_path.into());
+        map
+    }
+
+    #[test]
+    fn test_original_file_reads_regular_files() {
+        let dir = TempDir::new().unwrap();
+        let cwd = dir.path().to_string_lossy();
+        fs::write(dir.path().join("app.py"), "x = 1\n").unwrap();
+        assert_eq!(
+            original_file(&cwd, &write_input("app.py")).as_deref(),
+            Some("x = 1\n")
+        );
+        assert_eq!(original_file(&cwd, &write_input("missing.py")), None);
+    }
+
+    #[test]
+    fn test_original_file_skips_special_and_large_files() {
+        let dir = TempDir::new().unwrap();
+        let cwd = dir.path().to_string_lossy();
+        fs::create_dir(dir.path().join("src")).unwrap();
+        assert_eq!(original_file(&cwd, &write_input("src")), None);
+
+        let large = vec![b'a'; MAX_ORIGINAL_FILE_BYTES as usize + 1];
+        fs::write(dir.path().join("large.txt"), large).unwrap();
+        assert_eq!(original_file(&cwd, &write_input("large.txt")), None);
+
+        // Reading a FIFO with no writer would block forever
+        let fifo = dir.path().join("fifo");
+        let made = std::process::Command::new("mkfifo").arg(&fifo).status();
+        if made.is_ok_and(|status| status.success()) {
+            assert_eq!(original_file(&cwd, &write_input("fifo")), None);
+        }
+    }
+}
@@ -0,0 +1,391 @@
+//! Transcript replay for `tool-gates replay`.
+//!
+//! Claude Code transcripts (`~/.claude/projects/<project>/<session>.jsonl`)
+//! record every tool call. Replay re-runs each one through the PreToolUse
+//! decision with the current config, settings and rules, and reports the
+//! decision distribution. With a second config it lists the calls whose
+//! decision would change, to try out rule changes against real history.
+//!
+//! Replay has no side effects: nothing is tracked for the pending queue and
+//! hint or warning dedup stays in memory. Session-scoped and time-boxed
+//! approvals are left out, since they belonged to the original sessions.
+
+use crate::config::Config;
+use crate::engine::{Engine, EngineBuilder};
+use crate::hint_tracker::MemorySessionStore;
+use crate::models::HookInput;
+use crate::pre_tool_use::decide_pre_tool_use;
+use crate::session_approvals::SessionApprovals;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+
+/// One tool call from a transcript
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RecordedCall {
+    pub tool_name: String,
+    pub tool_input: serde_json::Map<String, serde_json::Value>,
+    pub cwd: String,
+    pub session_id: String,
+    pub permission_mode: String,
+    pub transcript: PathBuf,
+}
+
+impl RecordedCall {
+    /// The command, or the file path or pattern for other tools
+    pub fn subject(&self) -> String {
+        [
+            "command",
+            "file_path",
+            "notebook_path",
+            "pattern",
+            "url",
+            "skill",
+        ]
+        .iter()
+        .find_map(|key| self.tool_input.get(*key).and_then(|v| v.as_str()))
+        .unwrap_or("")
+        .to_string()
+    }
+
+    fn hook_input(&self) -> HookInput {
+        // No session: security warnings would otherwise be deduped on disk
+        serde_json::from_value(serde_json::json!({
+            "hook_event_name": "PreToolUse",
+            "tool_name": self.tool_name,
+            "tool_input": self.tool_input,
+            "cwd": self.cwd,
+            "permission_mode": self.permission_mode,
+            "transcript_path": self.transcript,
+        }))
+        .unwrap_or_default()
+    }
+}
+
+/// Transcript files under `path`: the file itself, or every `.jsonl` below a directory
+pub fn transcript_files(path: &Path) -> Vec<PathBuf> {
+    if path.is_file() {
+        return vec![path.to_path_buf()];
+    }
+    let mut files = Vec::new();
+    let mut dirs = vec![path.to_path_buf()];
+    while let Some(dir) = dirs.pop() {
+        let Ok(entries) = std::fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.is_dir() {
+                dirs.push(path);
+            } else if path.extension().is_some_and(|ext| ext == "jsonl") {
+                files.push(path);
+            }
+        }
+    }
+    files.sort();
+    files
+}
+
+/// Tool calls recorded in a transcript, in order. Unparseable lines are skipped.
+pub fn read_transcript(path: &Path) -> std::io::Result<Vec<RecordedCall>> {
+    let reader = BufReader::new(File::open(path)?);
+    let mut calls = Vec::new();
+    let mut permission_mode = String::from("default");
+
+    for line in reader.lines().map_while(Result::ok) {
+        let Ok(value) = serde_json::from_str::<serde_json::Value>(&line) else {
+            continue;
+        };
+        let field = |key: &str| {
+            value
+                .get(key)
+                .and_then(|v| v.as_str())
+                .unwrap_or("")
+                .to_string()
+        };
+        // User messages carry the mode in effect from then on
+        if let Some(mode) = value.get("permissionMode").and_then(|v| v.as_str()) {
+            permission_mode = mode.to_string();
+        }
+        let Some(content) = value.pointer("/message/content").and_then(|c| c.as_array()) else {
+            continue;
+        };
+        for block in content {
+            if block.get("type").and_then(|t| t.as_str()) != Some("tool_use") {
+                continue;
+            }
+            let (Some(name), Some(serde_json::Value::Object(input))) = (
+                block.get("name").and_then(|n| n.as_str()),
+                block.get("input"),
+            ) else {
+                continue;
+            };
+            calls.push(RecordedCall {
+                tool_name: name.to_string(),
+                tool_input: input.clone(),
+                cwd: field("cwd"),
+                session_id: field("sessionId"),
+                permission_mode: permission_mode.clone(),
+                transcript: path.to_path_buf(),
+            });
+        }
+    }
+    Ok(calls)
+}
+
+/// What the PreToolUse hook decides for a call
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Outcome {
+    /// `allow`, `ask`, `deny`, or `none` for no opinion
+    pub decision: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+/// Re-runs recorded calls through the PreToolUse decision with one config
+pub struct Replayer {
+    base: EngineBuilder,
+}
+
+impl Replayer {
+    pub fn new(config: Config) -> Self {
+        Self {
+            base: Engine::builder()
+                .config(config)
+                .session_store(MemorySessionStore::new())
+                .session_approvals(SessionApprovals::default()),
+        }
+    }
+
+    pub fn decide(&self, call: &RecordedCall) -> Outcome {
+        let engine = self
+            .base
+            .clone()
+            .cwd(call.cwd.as_str())
+            .permission_mode(call.permission_mode.as_str())
+            .session_id(call.session_id.as_str())
+            .build();
+        let result = decide_pre_tool_use(&engine, &call.hook_input(), &call.tool_input, false);
+        let reason = result
+            .output
+            .as_ref()
+            .and_then(|o| o.hook_specific_output.as_ref())
+            .and_then(|hso| hso.permission_decision_reason.clone());
+        Outcome {
+            decision: result.decision().to_string(),
+            reason,
+        }
+    }
+}
+
+/// A call whose decision differs under the compared config
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Change {
+    pub call: RecordedCall,
+    pub current: Outcome,
+    pub compared: Outcome,
+}
+
+/// Decision distribution over replayed calls, plus changes under a compared config
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct ReplayReport {
+    pub transcripts: usize,
+    pub calls: usize,
+    /// decision -> count
+    pub decisions: BTreeMap<String, usize>,
+    /// tool -> decision -> count
+    pub by_tool: BTreeMap<String, BTreeMap<String, usize>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub compared_config: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub changes: Vec<Change>,
+}
+
+impl ReplayReport {
+    /// Replay `calls` under `current`, and under `compared` when given
+    pub fn compute(
+        transcripts: usize,
+        calls: Vec<RecordedCall>,
+        current: &Replayer,
+        compared: Option<(&str, &Replayer)>,
+    ) -> Self {
+        let mut report = Self {
+            transcripts,
+            calls: calls.len(),
+            compared_config: compared.map(|(name, _)| name.to_string()),
+            ..Default::default()
+        };
+        for call in calls {
+            let outcome = current.decide(&call);
+            *report
+                .decisions
+                .entry(outcome.decision.clone())
+                .or_default() += 1;
+            *report
+                .by_tool
+                .entry(call.tool_name.clone())
+                .or_default()
+                .entry(outcome.decision.clone())
+                .or_default() += 1;
+
+            if let Some((_, other)) = compared {
+                let other_outcome = other.decide(&call);
+                if other_outcome.decision != outcome.decision {
+                    report.changes.push(Change {
+                        call,
+                        current: outcome,
+                        compared: other_outcome,
+                    });
+                }
+            }
+        }
+        report
+    }
+
+    /// Human-readable report
+    pub fn render(&self) -> String {
+        let mut out = format!(
+            "Replayed {} tool calls from {} transcript(s)\n",
+            self.calls, self.transcripts
+        );
+        if self.calls == 0 {
+            return out;
+        }
+
+        out.push_str("\nDecisions:\n");
+        for (decision, count) in &self.decisions {
+            out.push_str(&format!(
+                "  {decision:<6} {count:>6}  {:>5.1}%\n",
+                *count as f64 * 100.0 / self.calls as f64
+            ));
+        }
+
+        out.push_str("\nBy tool:\n");
+        for (tool, decisions) in &self.by_tool {
+            let counts: Vec<String> = decisions
+                .iter()
+                .map(|(decision, count)| format!("{decision} {count}"))
+                .collect();
+            out.push_str(&format!("  {tool:<14} {}\n", counts.join("  ")));
+        }
+
+        if let Some(compared) = &self.compared_config {
+            out.push_str(&format!(
+                "\nWith {compared}: {} of {} calls change\n",
+                self.changes.len(),
+                self.calls
+            ));
+            for change in &self.changes {
+                out.push_str(&format!(
+                    "  {:>5} -> {:<5}  {:<10} {}\n",
+                    change.current.decision,
+                    change.compared.decision,
+                    change.call.tool_name,
+                    change.call.subject()
+                ));
+            }
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::settings::Settings;
+    use crate::user_rules::RuleSet;
+    use std::io::Write;
+    use tempfile::TempDir;
+
+    /// No settings.json, user rules or repo policy from disk
+    fn hermetic(config: Config) -> Replayer {
+        Replayer {
+            base: Replayer::new(config)
+                .base
+                .settings(Settings::default())
+                .repo_policy(None)
+                .rules(RuleSet::default()),
+        }
+    }
+
+    fn tool_use(name: &str, input: serde_json::Value) -> String {
+        serde_json::json!({
+            "type": "assistant",
+            "cwd": "/tmp",
+            "sessionId": "s1",
+            "message": {"content": [
+                {"type": "text", "text": "Running it"},
+                {"type": "tool_use", "id": "toolu_1", "name": name, "input": input},
+            ]},
+        })
+        .to_string()
+    }
+
+    fn write_transcript(dir: &TempDir, lines: &[String]) -> PathBuf {
+        let project = dir.path().join("-tmp-project");
+        std::fs::create_dir_all(&project).unwrap();
+        let path = project.join("session.jsonl");
+        let mut file = File::create(&path).unwrap();
+        for line in lines {
+            writeln!(file, "{line}").unwrap();
+        }
+        path
+    }
+
+    #[test]
+    fn test_read_transcript() {
+        let dir = TempDir::new().unwrap();
+        let path = write_transcript(
+            &dir,
+            &[
+                tool_use("Bash", serde_json::json!({"command": "git status"})),
+                "not json".to_string(),
+                r#"{"type":"user","permissionMode":"acceptEdits","message":{"content":"ok"}}"#
+                    .to_string(),
+                tool_use("Read", serde_json::json!({"file_path": "/tmp/a.rs"})),
+            ],
+        );
+
+        assert_eq!(transcript_files(dir.path()), vec![path.clone()]);
+        let calls = read_transcript(&path).unwrap();
+        assert_eq!(calls.len(), 2);
+        assert_eq!(calls[0].subject(), "git status");
+        assert_eq!(calls[0].permission_mode, "default");
+        assert_eq!(calls[1].tool_name, "Read");
+        assert_eq!(calls[1].permission_mode, "acceptEdits");
+        assert_eq!(calls[1].session_id, "s1");
+    }
+
+    #[test]
+    fn test_report_and_compare() {
+        let dir = TempDir::new().unwrap();
+        let path = write_transcript(
+            &dir,
+            &[
+                tool_use("Bash", serde_json::json!({"command": "git status"})),
+                tool_use("Bash", serde_json::json!({"command": "rm -rf /"})),
+                tool_use("Bash", serde_json::json!({"command": "npm install"})),
+            ],
+        );
+        let calls = read_transcript(&path).unwrap();
+
+        let current = hermetic(Config::default());
+        let compared = hermetic(toml::from_str("[features]\nbash_gates = false").unwrap());
+        let report = ReplayReport::compute(1, calls, &current, Some(("other.toml", &compared)));
+
+        assert_eq!(report.calls, 3);
+        assert_eq!(report.decisions.get("deny"), Some(&1));
+        assert_eq!(report.by_tool["Bash"].values().sum::<usize>(), 3);
+        // Without the Bash gates every call loses its decision
+        assert_eq!(report.changes.len(), 3);
+        assert_eq!(report.changes[1].current.decision, "deny");
+        assert_eq!(report.changes[1].compared.decision, "none");
+        assert!(
+            report
+                .render()
+                .contains("With other.toml: 3 of 3 calls change")
+        );
+    }
+}
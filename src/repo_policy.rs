@@ -0,0 +1,409 @@
+//! Repository-local policy file (`.tool-gates.toml`).
+//!
+//! A project can commit a policy file that layers its own rules on top of the
+//! user's config:
+//!
+//! ```toml
+//! allow = ["./scripts/dev.sh:*"]
+//! ask = ["kubectl apply:*"]
+//! block = ["terraform apply:*", "terraform destroy:*"]
+//!
+//! [security_reminders]
+//! disable_rules = ["eval_injection"]
+//! ```
+//!
+//! Patterns use the settings.json syntax without the `Bash(...)` wrapper:
+//! `cmd:*` (word-boundary prefix), `cmd*` (glob prefix), or an exact command.
+//!
+//! Because anyone can commit this file, entries that loosen the rules (`allow`,
+//! `security_reminders.disable_rules`) only take effect once the user has run
+//! `tool-gates trust`, which records the file's SHA-256 in the cache dir. Any
+//! edit to the file invalidates the trust. Tightening entries (`ask`, `block`)
+//! always apply.
+//!
+//! A file that doesn't parse (a typo'd key, a wrong type) fails closed: the
+//! `block` list still applies if it can be read, every other command asks, and
+//! nothing loosens until the file is fixed. `tool-gates doctor` shows the error.
+
+use crate::cache::cache_dir;
+use crate::settings::Settings;
+use crate::trace::{self, TraceEvent};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Policy file name, looked up from the working directory upwards.
+pub const POLICY_FILE: &str = ".tool-gates.toml";
+
+/// Contents of a `.tool-gates.toml`.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RepoPolicy {
+    /// Commands to allow (loosening, requires trust)
+    pub allow: Vec<String>,
+    /// Commands to always ask about
+    pub ask: Vec<String>,
+    /// Commands to always block
+    pub block: Vec<String>,
+    pub security_reminders: PolicyReminders,
+}
+
+/// `[security_reminders]` section of a policy file.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PolicyReminders {
+    /// Security reminder rule IDs to disable (loosening, requires trust)
+    pub disable_rules: Vec<String>,
+}
+
+impl RepoPolicy {
+    /// Whether the file contains anything that needs trust to take effect.
+    pub fn has_loosening_entries(&self) -> bool {
+        !self.allow.is_empty() || !self.security_reminders.disable_rules.is_empty()
+    }
+}
+
+/// Whether a policy file's loosening entries are in effect.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Trust {
+    /// Trusted at its current contents
+    Trusted,
+    /// Never trusted
+    Untrusted,
+    /// Trusted once, but edited since
+    Changed,
+}
+
+/// A policy file with its hash and trust state.
+#[derive(Debug, Clone)]
+pub struct LoadedPolicy {
+    pub path: PathBuf,
+    pub policy: RepoPolicy,
+    /// SHA-256 of the file contents (hex)
+    pub hash: String,
+    pub trust: Trust,
+    /// Parse error. `policy` then holds only the block and ask lists that could be read.
+    pub error: Option<String>,
+}
+
+/// Decision from a policy file's block and ask lists
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PolicyDecision {
+    Block,
+    Ask,
+    NoMatch,
+}
+
+impl LoadedPolicy {
+    /// Parse a policy file and look up its trust state in `store`.
+    ///
+    /// Only a read error is an `Err`. A file that doesn't parse loads with
+    /// [`LoadedPolicy::error`] set and whatever tightening entries it has.
+    pub fn load(path: &Path, store: &TrustStore) -> Result<Self, String> {
+        let content = fs::read_to_string(path).map_err(|e| format!("read error: {e}"))?;
+        let (policy, error) = match toml::from_str::<RepoPolicy>(&content) {
+            Ok(policy) => (policy, None),
+            Err(e) => (
+                tightening_entries(&content),
+                Some(format!("parse error: {e}")),
+            ),
+        };
+        let hash = hash_contents(content.as_bytes());
+        let trust = store.trust_of(path, &hash);
+        Ok(Self {
+            path: path.to_path_buf(),
+            policy,
+            hash,
+            trust,
+            error,
+        })
+    }
+
+    /// Whether the loosening entries are in effect: trusted, and parsed cleanly.
+    pub fn is_trusted(&self) -> bool {
+        self.trust == Trust::Trusted && self.error.is_none()
+    }
+
+    /// Allow patterns in effect: the file's `allow` list once trusted, otherwise none.
+    pub fn allow_rules(&self) -> &[String] {
+        if self.is_trusted() {
+            &self.policy.allow
+        } else {
+            &[]
+        }
+    }
+
+    /// Security reminder rules disabled by this file, once trusted.
+    pub fn disabled_reminders(&self) -> &[String] {
+        if self.is_trusted() {
+            &self.policy.security_reminders.disable_rules
+        } else {
+            &[]
+        }
+    }
+
+    /// Check a command against the block and ask lists. A file that didn't
+    /// parse asks about everything it doesn't block.
+    ///
+    /// Allow rules are applied per segment by the router (see [`LoadedPolicy::allows`]).
+    pub fn check_tightening(&self, command: &str) -> PolicyDecision {
+        if matches_any("block", &self.policy.block, command) {
+            PolicyDecision::Block
+        } else if self.error.is_some() || matches_any("ask", &self.policy.ask, command) {
+            PolicyDecision::Ask
+        } else {
+            PolicyDecision::NoMatch
+        }
+    }
+
+    /// Whether a trusted allow rule matches the command.
+    pub fn allows(&self, command: &str) -> bool {
+        matches_any("allow", self.allow_rules(), command)
+    }
+}
+
+/// The `block` and `ask` lists of a file that didn't parse as a [`RepoPolicy`].
+///
+/// Other fields are dropped, and so is a list that isn't an array of strings.
+fn tightening_entries(content: &str) -> RepoPolicy {
+    let Ok(table) = content.parse::<toml::Table>() else {
+        return RepoPolicy::default();
+    };
+    let list = |key: &str| -> Vec<String> {
+        table
+            .get(key)
+            .and_then(|value| value.clone().try_into().ok())
+            .unwrap_or_default()
+    };
+    RepoPolicy {
+        block: list("block"),
+        ask: list("ask"),
+        ..Default::default()
+    }
+}
+
+fn matches_any(list: &'static str, patterns: &[String], command: &str) -> bool {
+    let Some(pattern) = patterns
+        .iter()
+        .find(|p| Settings::matches_bash_pattern(p, command))
+    else {
+        return false;
+    };
+    trace::record(|| TraceEvent::PolicyRule {
+        list,
+        rule: pattern.clone(),
+        command: command.to_string(),
+    });
+    true
+}
+
+/// Find the nearest policy file at or above `cwd`.
+pub fn find(cwd: &str) -> Option<PathBuf> {
+    if cwd.is_empty() {
+        return None;
+    }
+    Path::new(cwd)
+        .ancestors()
+        .map(|dir| dir.join(POLICY_FILE))
+        .find(|path| path.is_file())
+}
+
+/// Load the policy file for `cwd`, if any.
+///
+/// A file that can't be parsed still loads, with [`LoadedPolicy::error`] set
+/// and a warning: the router then fails closed, keeping the block and ask
+/// entries it could read and asking for every other command. A file that
+/// can't be read at all is ignored with a warning.
+pub fn discover(cwd: &str) -> Option<LoadedPolicy> {
+    let path = find(cwd)?;
+    match LoadedPolicy::load(&path, &TrustStore::load()) {
+        Ok(policy) => {
+            if let Some(error) = &policy.error {
+                eprintln!("tool-gates: warning: {} {error}", path.display());
+            }
+            Some(policy)
+        }
+        Err(e) => {
+            eprintln!("tool-gates: warning: {} {e}", path.display());
+            None
+        }
+    }
+}
+
+/// SHA-256 of a policy file's contents, as lowercase hex.
+pub fn hash_contents(content: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(content))
+}
+
+/// Trusted policy hashes, keyed by canonical file path.
+///
+/// Stored in `~/.cache/tool-gates/trusted-policies.json`.
+#[derive(Debug, Clone, Default)]
+pub struct TrustStore {
+    path: PathBuf,
+    entries: BTreeMap<String, String>,
+}
+
+impl TrustStore {
+    /// Load the store from the cache dir. Missing or corrupt files load as empty.
+    pub fn load() -> Self {
+        Self::load_from(cache_dir().join("trusted-policies.json"))
+    }
+
+    /// Load the store from an explicit path.
+    pub fn load_from(path: PathBuf) -> Self {
+        let entries = fs::read_to_string(&path)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default();
+        Self { path, entries }
+    }
+
+    pub fn trust_of(&self, policy: &Path, hash: &str) -> Trust {
+        match self.entries.get(&key(policy)) {
+            Some(trusted) if trusted == hash => Trust::Trusted,
+            Some(_) => Trust::Changed,
+            None => Trust::Untrusted,
+        }
+    }
+
+    /// Record a policy file as trusted at the given hash.
+    pub fn trust(&mut self, policy: &Path, hash: &str) {
+        self.entries.insert(key(policy), hash.to_string());
+    }
+
+    /// Forget a policy file. Returns false if it wasn't trusted.
+    pub fn revoke(&mut self, policy: &Path) -> bool {
+        self.entries.remove(&key(policy)).is_some()
+    }
+
+    pub fn save(&self) -> Result<(), std::io::Error> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let content = serde_json::to_string_pretty(&self.entries)?;
+        fs::write(&self.path, content)
+    }
+}
+
+/// Store key for a policy file: its canonical path, so symlinked checkouts share trust.
+fn key(policy: &Path) -> String {
+    policy
+        .canonicalize()
+        .unwrap_or_else(|_| policy.to_path_buf())
+        .to_string_lossy()
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    const POLICY: &str = r#"
+allow = ["./scripts/dev.sh:*"]
+block = ["terraform apply:*"]
+ask = ["kubectl apply:*"]
+
+[security_reminders]
+disable_rules = ["eval_injection"]
+"#;
+
+    fn setup(content: &str) -> (TempDir, PathBuf, TrustStore) {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join(POLICY_FILE);
+        fs::write(&path, content).unwrap();
+        let store = TrustStore::load_from(dir.path().join("trusted.json"));
+        (dir, path, store)
+    }
+
+    #[test]
+    fn test_find_walks_up() {
+        let (dir, path, _) = setup(POLICY);
+        let nested = dir.path().join("infra/modules");
+        fs::create_dir_all(&nested).unwrap();
+        assert_eq!(find(&nested.to_string_lossy()), Some(path));
+        assert_eq!(find(""), None);
+    }
+
+    #[test]
+    fn test_untrusted_only_tightens() {
+        let (_dir, path, store) = setup(POLICY);
+        let policy = LoadedPolicy::load(&path, &store).unwrap();
+        assert_eq!(policy.trust, Trust::Untrusted);
+        assert_eq!(
+            policy.check_tightening("terraform apply -auto-approve"),
+            PolicyDecision::Block
+        );
+        assert_eq!(
+            policy.check_tightening("kubectl apply -f x.yaml"),
+            PolicyDecision::Ask
+        );
+        assert!(!policy.allows("./scripts/dev.sh --watch"));
+        assert!(policy.disabled_reminders().is_empty());
+    }
+
+    #[test]
+    fn test_trust_and_change() {
+        let (_dir, path, mut store) = setup(POLICY);
+        let policy = LoadedPolicy::load(&path, &store).unwrap();
+        store.trust(&path, &policy.hash);
+        store.save().unwrap();
+
+        let store = TrustStore::load_from(store.path.clone());
+        let policy = LoadedPolicy::load(&path, &store).unwrap();
+        assert!(policy.is_trusted());
+        assert!(policy.allows("./scripts/dev.sh --watch"));
+        assert_eq!(policy.disabled_reminders(), ["eval_injection"]);
+
+        fs::write(&path, POLICY.replace("dev.sh", "anything")).unwrap();
+        let policy = LoadedPolicy::load(&path, &store).unwrap();
+        assert_eq!(policy.trust, Trust::Changed);
+        assert!(policy.allow_rules().is_empty());
+    }
+
+    #[test]
+    fn test_revoke() {
+        let (_dir, path, mut store) = setup(POLICY);
+        let hash = LoadedPolicy::load(&path, &store).unwrap().hash;
+        store.trust(&path, &hash);
+        assert!(store.revoke(&path));
+        assert!(!store.revoke(&path));
+        assert_eq!(store.trust_of(&path, &hash), Trust::Untrusted);
+    }
+
+    #[test]
+    fn test_invalid_file_fails_closed() {
+        let content = format!("{POLICY}\nalow = [\"make:*\"]\n");
+        let (_dir, path, mut store) = setup(&content);
+        let policy = LoadedPolicy::load(&path, &store).unwrap();
+        store.trust(&path, &policy.hash);
+        let policy = LoadedPolicy::load(&path, &store).unwrap();
+
+        let error = policy.error.as_deref().unwrap();
+        assert!(error.starts_with("parse error"), "got: {error}");
+        assert_eq!(policy.trust, Trust::Trusted);
+        assert!(!policy.is_trusted());
+        assert!(!policy.allows("./scripts/dev.sh"));
+        assert!(policy.disabled_reminders().is_empty());
+        assert_eq!(
+            policy.check_tightening("terraform apply"),
+            PolicyDecision::Block
+        );
+        assert_eq!(policy.check_tightening("git status"), PolicyDecision::Ask);
+    }
+
+    #[test]
+    fn test_unparseable_file_asks() {
+        let (_dir, path, store) = setup("block = [\"terraform apply:*\"\n");
+        let policy = LoadedPolicy::load(&path, &store).unwrap();
+        assert!(policy.error.is_some());
+        assert!(policy.policy.block.is_empty());
+        assert_eq!(
+            policy.check_tightening("terraform apply"),
+            PolicyDecision::Ask
+        );
+    }
+}
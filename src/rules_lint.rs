@@ -0,0 +1,451 @@
+//! Lint for settings.json permission rules (`tool-gates rules lint`).
+//!
+//! Rules pile up from `tool-gates approve` and the review TUI across the user,
+//! project and local settings files. The lint looks at all of them together:
+//!
+//! - `malformed`: the rule doesn't parse, or uses `:*` somewhere other than the end
+//! - `duplicate`: the same rule in the same list twice, or in another scope
+//! - `shadowed`: a broader rule of the same kind already covers it
+//! - `broad_allow`: an allow in a more specific scope covers a deny or ask rule
+//!   from a broader one. Deny and ask always win, so the allow doesn't override
+//!   it, and it approves everything around the command that was meant to prompt.
+//! - `dangerous_allow`: an allow rule matches a command the gates block
+//! - `home_path`: an allow rule in the shared project scope names someone's home directory
+//!
+//! Only duplicates are fixable: copies within one list, and local rules the
+//! user or project settings already have. A project rule that repeats a user
+//! rule stays, since teammates don't share the user file.
+
+use crate::config::Config;
+use crate::engine::Engine;
+use crate::hint_tracker::MemorySessionStore;
+use crate::models::Decision;
+use crate::settings::{PermissionRule, Settings, ToolUse};
+use crate::settings_writer::{self, RuleType, Scope};
+use crate::user_rules::RuleSet;
+use regex::Regex;
+use serde::Serialize;
+use std::sync::LazyLock;
+
+/// Commands the gates block, for testing allow rules against.
+/// Entries the gates stop blocking are skipped, never reported.
+pub const DANGEROUS_SAMPLES: &[&str] = &[
+    "rm -rf /",
+    "rm -rf /*",
+    "rm -rf ~",
+    "dd if=/dev/zero of=/dev/sda",
+    "dd if=/dev/urandom of=/dev/nvme0n1",
+    "mkfs /dev/sda",
+    "cat /dev/zero > /dev/sda",
+    "echo x > /dev/sda",
+    "shutdown -h now",
+    "reboot",
+    "poweroff",
+    "halt",
+    "init 0",
+    "gh repo delete owner/repo --yes",
+];
+
+/// Absolute paths into a home directory (`/home/alice`, `/Users/bob`, `/root`)
+static HOME_PATH: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?:^|[^\w.~-])(?:/home/[\w.-]+|/Users/[\w.-]+|/root)(?:/|\s|\)|$)").unwrap()
+});
+
+/// Message for a rule repeated within one list
+const SAME_LIST: &str = "repeated in the same list";
+
+/// What a finding is about
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FindingKind {
+    Malformed,
+    Duplicate,
+    Shadowed,
+    BroadAllow,
+    DangerousAllow,
+    HomePath,
+}
+
+impl FindingKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Malformed => "malformed",
+            Self::Duplicate => "duplicate",
+            Self::Shadowed => "shadowed",
+            Self::BroadAllow => "broad_allow",
+            Self::DangerousAllow => "dangerous_allow",
+            Self::HomePath => "home_path",
+        }
+    }
+
+    /// Errors can let a command through that shouldn't be; the rest are hygiene
+    pub fn is_error(&self) -> bool {
+        matches!(self, Self::Malformed | Self::DangerousAllow)
+    }
+}
+
+/// One problem with one rule
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Finding {
+    pub kind: FindingKind,
+    /// Settings scope the rule is in ("user", "project", "local")
+    pub scope: &'static str,
+    /// Rule list ("allow", "ask", "deny")
+    pub list: &'static str,
+    /// The rule as written in settings.json
+    pub rule: String,
+    pub message: String,
+    /// Removed by `--fix`
+    pub fixable: bool,
+}
+
+/// Lint rules from any number of scopes, in the order [`settings_writer::list_all_rules`] returns them
+pub fn lint(rules: &[settings_writer::PermissionRule]) -> Vec<Finding> {
+    let blocked = blocked_samples();
+    let parsed: Vec<Option<PermissionRule>> = rules
+        .iter()
+        .map(|r| PermissionRule::parse(&r.pattern))
+        .collect();
+    let mut findings = Vec::new();
+
+    for (i, entry) in rules.iter().enumerate() {
+        let finding = |kind: FindingKind, message: String, fixable: bool| Finding {
+            kind,
+            scope: entry.scope.as_str(),
+            list: entry.rule_type.as_str(),
+            rule: entry.pattern.clone(),
+            message,
+            fixable,
+        };
+
+        let Some(rule) = parsed[i] else {
+            findings.push(finding(
+                FindingKind::Malformed,
+                "not a valid rule (expected `Tool` or `Tool(specifier)`)".to_string(),
+                false,
+            ));
+            continue;
+        };
+        if rule.tool == "Bash" && rule.specifier.is_some_and(misplaced_prefix_wildcard) {
+            findings.push(finding(
+                FindingKind::Malformed,
+                "`:*` only works at the end of a pattern; here it matches literally".to_string(),
+                false,
+            ));
+        }
+
+        // Duplicates and shadowing: compare against every other rule of the same kind
+        let is_copy = |other: &&settings_writer::PermissionRule| {
+            other.rule_type == entry.rule_type && other.pattern.trim() == entry.pattern.trim()
+        };
+        let other_scope_copy = if entry.scope == Scope::Local {
+            rules
+                .iter()
+                .filter(is_copy)
+                .find(|other| other.scope != Scope::Local)
+        } else {
+            rules[..i]
+                .iter()
+                .filter(is_copy)
+                .find(|other| other.scope != entry.scope)
+        };
+        if rules[..i]
+            .iter()
+            .filter(is_copy)
+            .any(|other| other.scope == entry.scope)
+        {
+            findings.push(finding(FindingKind::Duplicate, SAME_LIST.to_string(), true));
+        } else if let Some(other) = other_scope_copy {
+            findings.push(finding(
+                FindingKind::Duplicate,
+                format!("same rule in {} settings", other.scope.as_str()),
+                entry.scope == Scope::Local,
+            ));
+        } else if let Some(other) = rules.iter().zip(&parsed).find_map(|(other, parsed)| {
+            parsed.filter(|broad| {
+                other.rule_type == entry.rule_type
+                    && other.pattern.trim() != entry.pattern.trim()
+                    && broad.covers(&rule)
+            })?;
+            Some(other)
+        }) {
+            findings.push(finding(
+                FindingKind::Shadowed,
+                format!(
+                    "already covered by {} ({} settings)",
+                    other.pattern,
+                    other.scope.as_str()
+                ),
+                false,
+            ));
+        }
+
+        if entry.rule_type != RuleType::Allow {
+            continue;
+        }
+
+        // An allow in a more specific scope reads as an override, but deny and ask always win
+        for (other, other_rule) in rules.iter().zip(&parsed) {
+            let Some(other_rule) = other_rule else {
+                continue;
+            };
+            if other.rule_type == RuleType::Allow
+                || specificity(other.scope) >= specificity(entry.scope)
+                || !rule.covers(other_rule)
+            {
+                continue;
+            }
+            let effect = if other.pattern.trim() == entry.pattern.trim() {
+                "so this allow never applies"
+            } else {
+                "so this allow doesn't override it and approves everything around it"
+            };
+            findings.push(finding(
+                FindingKind::BroadAllow,
+                format!(
+                    "covers {} rule {} from {} settings; {} rules always win, {effect}",
+                    other.rule_type.as_str(),
+                    other.pattern,
+                    other.scope.as_str(),
+                    other.rule_type.as_str()
+                ),
+                false,
+            ));
+        }
+
+        if let Some(sample) = blocked
+            .iter()
+            .find(|sample| rule.matches(&ToolUse::Bash(sample)))
+        {
+            findings.push(finding(
+                FindingKind::DangerousAllow,
+                format!("matches `{sample}`, which the gates block"),
+                false,
+            ));
+        }
+
+        if entry.scope == Scope::Project && HOME_PATH.is_match(&entry.pattern) {
+            findings.push(finding(
+                FindingKind::HomePath,
+                "shared project settings name an absolute home directory; use a relative or `~/` path, or move it to local settings".to_string(),
+                false,
+            ));
+        }
+    }
+
+    findings
+}
+
+/// The `(list, rule)` entries `--fix` removes from one scope
+pub fn fixes_for(findings: &[Finding], scope: Scope) -> Vec<(RuleType, String)> {
+    findings
+        .iter()
+        .filter(|f| f.fixable && f.scope == scope.as_str())
+        // Copies in the same list are handled by keeping the first one
+        .filter(|f| f.message != SAME_LIST)
+        .map(|f| {
+            let rule_type = match f.list {
+                "allow" => RuleType::Allow,
+                "ask" => RuleType::Ask,
+                _ => RuleType::Deny,
+            };
+            (rule_type, f.rule.clone())
+        })
+        .collect()
+}
+
+/// `Bash(git:* push)`: `:*` only means "any arguments" at the end of a pattern
+fn misplaced_prefix_wildcard(pattern: &str) -> bool {
+    pattern.strip_suffix(":*").unwrap_or(pattern).contains(":*")
+}
+
+/// How specific a scope is: local overrides project, project overrides user
+fn specificity(scope: Scope) -> u8 {
+    match scope {
+        Scope::User => 0,
+        Scope::Project => 1,
+        Scope::Local => 2,
+    }
+}
+
+/// The [`DANGEROUS_SAMPLES`] the built-in gates currently block
+fn blocked_samples() -> Vec<&'static str> {
+    let engine = Engine::builder()
+        .config(Config::default())
+        .settings(Settings::default())
+        .repo_policy(None)
+        .rules(RuleSet::default())
+        .tools(|_: &str| false)
+        .session_store(MemorySessionStore::new())
+        .build();
+    DANGEROUS_SAMPLES
+        .iter()
+        .copied()
+        .filter(|sample| engine.check_gates(sample).decision == Decision::Block)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(scope: Scope, rule_type: RuleType, pattern: &str) -> settings_writer::PermissionRule {
+        settings_writer::PermissionRule {
+            pattern: pattern.to_string(),
+            rule_type,
+            scope,
+        }
+    }
+
+    fn kinds(findings: &[Finding]) -> Vec<(FindingKind, &str)> {
+        findings.iter().map(|f| (f.kind, f.rule.as_str())).collect()
+    }
+
+    #[test]
+    fn test_samples_are_blocked() {
+        assert_eq!(blocked_samples(), DANGEROUS_SAMPLES);
+    }
+
+    #[test]
+    fn test_clean_rules() {
+        let rules = [
+            rule(Scope::User, RuleType::Allow, "Bash(git status)"),
+            rule(Scope::User, RuleType::Ask, "Bash(git push:*)"),
+            rule(Scope::Project, RuleType::Allow, "Bash(cargo test:*)"),
+            rule(Scope::Local, RuleType::Allow, "Read(~/notes/**)"),
+        ];
+        assert_eq!(lint(&rules), vec![]);
+    }
+
+    #[test]
+    fn test_duplicates() {
+        let rules = [
+            rule(Scope::User, RuleType::Allow, "Bash(git status)"),
+            rule(Scope::Project, RuleType::Allow, "Bash(git status)"),
+            rule(Scope::Local, RuleType::Allow, "Bash(cargo build)"),
+            rule(Scope::Local, RuleType::Allow, "Bash(cargo build)"),
+            rule(Scope::Local, RuleType::Allow, "Bash(git status)"),
+        ];
+        let findings = lint(&rules);
+        let fixable: Vec<_> = findings
+            .iter()
+            .map(|f| (f.scope, f.rule.as_str(), f.fixable))
+            .collect();
+        assert_eq!(
+            fixable,
+            vec![
+                // Teammates don't have the user file, so the project copy stays
+                ("project", "Bash(git status)", false),
+                ("local", "Bash(cargo build)", true),
+                ("local", "Bash(git status)", true),
+            ]
+        );
+        assert_eq!(
+            fixes_for(&findings, Scope::Local),
+            vec![(RuleType::Allow, "Bash(git status)".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_shadowed() {
+        let rules = [
+            rule(Scope::User, RuleType::Allow, "Bash(git:*)"),
+            rule(Scope::Local, RuleType::Allow, "Bash(git log:*)"),
+            rule(Scope::Local, RuleType::Deny, "Bash(git log:*)"),
+        ];
+        assert_eq!(
+            kinds(&lint(&rules)),
+            vec![(FindingKind::Shadowed, "Bash(git log:*)")]
+        );
+    }
+
+    #[test]
+    fn test_broad_allow() {
+        let rules = [
+            rule(Scope::User, RuleType::Ask, "Bash(git push:*)"),
+            rule(Scope::User, RuleType::Allow, "Bash(npm:*)"),
+            rule(Scope::User, RuleType::Deny, "Bash(npm publish:*)"),
+            rule(Scope::Local, RuleType::Allow, "Bash(git:*)"),
+            rule(Scope::Local, RuleType::Allow, "Bash(git push:*)"),
+        ];
+        let findings = lint(&rules);
+        assert_eq!(
+            kinds(&findings),
+            vec![
+                (FindingKind::BroadAllow, "Bash(git:*)"),
+                (FindingKind::Shadowed, "Bash(git push:*)"),
+                (FindingKind::BroadAllow, "Bash(git push:*)"),
+            ]
+        );
+        assert!(findings[2].message.contains("never applies"));
+    }
+
+    #[test]
+    fn test_dangerous_allow() {
+        let rules = [
+            rule(Scope::User, RuleType::Allow, "Bash(rm:*)"),
+            rule(Scope::Project, RuleType::Allow, "Bash(shutdown:*)"),
+            rule(Scope::Local, RuleType::Allow, "Bash(rm -rf ./build)"),
+        ];
+        let findings = lint(&rules);
+        assert_eq!(
+            kinds(&findings),
+            vec![
+                (FindingKind::DangerousAllow, "Bash(rm:*)"),
+                (FindingKind::DangerousAllow, "Bash(shutdown:*)"),
+                (FindingKind::Shadowed, "Bash(rm -rf ./build)"),
+            ]
+        );
+        assert_eq!(
+            findings[0].message,
+            "matches `rm -rf /`, which the gates block"
+        );
+    }
+
+    #[test]
+    fn test_malformed() {
+        let rules = [
+            rule(Scope::User, RuleType::Allow, "Bash(git status"),
+            rule(Scope::User, RuleType::Deny, "Bash(git:* push)"),
+            rule(Scope::User, RuleType::Allow, "Bash(git diff:*)"),
+        ];
+        assert_eq!(
+            kinds(&lint(&rules)),
+            vec![
+                (FindingKind::Malformed, "Bash(git status"),
+                (FindingKind::Malformed, "Bash(git:* push)"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_home_path_in_project() {
+        let rules = [
+            rule(
+                Scope::Project,
+                RuleType::Allow,
+                "Bash(/home/alice/bin/deploy:*)",
+            ),
+            rule(
+                Scope::Project,
+                RuleType::Allow,
+                "Read(//Users/bob/notes/**)",
+            ),
+            rule(
+                Scope::Project,
+                RuleType::Allow,
+                "Bash(./scripts/home/run.sh)",
+            ),
+            rule(Scope::Project, RuleType::Allow, "Read(~/.config/app/**)"),
+            rule(Scope::Local, RuleType::Allow, "Bash(/home/alice/bin/sync)"),
+            rule(Scope::Project, RuleType::Deny, "Read(//root/**)"),
+        ];
+        assert_eq!(
+            kinds(&lint(&rules)),
+            vec![
+                (FindingKind::HomePath, "Bash(/home/alice/bin/deploy:*)"),
+                (FindingKind::HomePath, "Read(//Users/bob/notes/**)"),
+            ]
+        );
+    }
+}
@@ -0,0 +1,351 @@
+//! Session-scoped and time-boxed approvals.
+//!
+//! `tool-gates approve --session` and `--for 2h` (and the review TUI's session
+//! scope) allow a command pattern without writing a permanent settings.json
+//! rule. Approvals live in `~/.cache/tool-gates/session-approvals.json`, each
+//! tied to a project directory and to a Claude Code session, an expiry, or both.
+//!
+//! The router consults them after settings.json and before the gate result:
+//! they only turn an ask into an allow, never override a block, a deny rule
+//! or a settings.json ask rule.
+//!
+//! Expired approvals are dropped whenever the file is read or written. Session
+//! approvals without an expiry are dropped after [`MAX_SESSION_AGE_DAYS`], since
+//! nothing reports when a session ends.
+
+use crate::settings::Settings;
+use crate::trace::{self, TraceEvent};
+use chrono::{DateTime, Duration, Utc};
+use fs2::FileExt;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File, OpenOptions};
+use std::io::{Read, Seek, Write};
+use std::path::PathBuf;
+
+/// Session approvals older than this are assumed to belong to a finished session
+pub const MAX_SESSION_AGE_DAYS: i64 = 7;
+
+/// A temporary allow rule
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SessionApproval {
+    /// Bash pattern, same grammar as settings.json (`docker compose up:*`)
+    pub pattern: String,
+    /// Project directory the approval applies in (and below)
+    pub project: String,
+    /// Only for this Claude Code session
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session_id: Option<String>,
+    /// Stops applying after this time
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl SessionApproval {
+    /// Whether the approval has run out at `now`
+    pub fn is_expired(&self, now: DateTime<Utc>) -> bool {
+        match self.expires_at {
+            Some(expires_at) => expires_at <= now,
+            None => now - self.created_at > Duration::days(MAX_SESSION_AGE_DAYS),
+        }
+    }
+
+    /// Whether the approval covers a command run by `session_id` in `cwd`
+    pub fn applies(&self, session_id: &str, cwd: &str, now: DateTime<Utc>) -> bool {
+        !self.is_expired(now)
+            && self
+                .session_id
+                .as_deref()
+                .is_none_or(|id| !session_id.is_empty() && id == session_id)
+            && is_within(cwd, &self.project)
+    }
+
+    /// Remaining lifetime for display: "this session", "1h 42m left", or both
+    pub fn lifetime(&self, now: DateTime<Utc>) -> String {
+        let remaining = self
+            .expires_at
+            .map(|expires_at| format!("{} left", format_remaining(expires_at - now)));
+        match (&self.session_id, remaining) {
+            (Some(_), Some(remaining)) => format!("this session, {remaining}"),
+            (Some(_), None) => "this session".to_string(),
+            (None, Some(remaining)) => remaining,
+            (None, None) => "no expiry".to_string(),
+        }
+    }
+}
+
+/// Unexpired approvals, as loaded for one hook call
+#[derive(Debug, Clone, Default)]
+pub struct SessionApprovals {
+    entries: Vec<SessionApproval>,
+}
+
+impl SessionApprovals {
+    pub fn new(entries: Vec<SessionApproval>) -> Self {
+        Self { entries }
+    }
+
+    /// Load from the cache file. A missing or unreadable file means no approvals.
+    pub fn load() -> Self {
+        let now = Utc::now();
+        let entries = fs::read_to_string(approvals_path())
+            .ok()
+            .and_then(|content| serde_json::from_str::<Vec<SessionApproval>>(&content).ok())
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|approval| !approval.is_expired(now))
+            .collect();
+        Self { entries }
+    }
+
+    pub fn entries(&self) -> &[SessionApproval] {
+        &self.entries
+    }
+
+    /// The approval allowing `command` for `session_id` in `cwd`, if any
+    pub fn allows(&self, command: &str, session_id: &str, cwd: &str) -> Option<&SessionApproval> {
+        let now = Utc::now();
+        let approval = self.entries.iter().find(|approval| {
+            approval.applies(session_id, cwd, now)
+                && Settings::matches_bash_pattern(&approval.pattern, command)
+        })?;
+        trace::record(|| TraceEvent::SessionApproval {
+            rule: approval.pattern.clone(),
+            command: command.to_string(),
+            lifetime: approval.lifetime(now),
+        });
+        Some(approval)
+    }
+}
+
+/// Path to the approvals file
+pub fn approvals_path() -> PathBuf {
+    crate::cache::cache_dir().join("session-approvals.json")
+}
+
+/// Add an approval, replacing one with the same pattern, project and session.
+/// Drops expired approvals while the file is locked.
+pub fn add(approval: SessionApproval) -> std::io::Result<()> {
+    with_exclusive_approvals(|entries| {
+        entries.retain(|e| {
+            !(e.pattern == approval.pattern
+                && e.project == approval.project
+                && e.session_id == approval.session_id)
+        });
+        entries.push(approval);
+    })
+}
+
+/// Atomically modify the approvals file, dropping expired entries.
+fn with_exclusive_approvals<F, R>(f: F) -> std::io::Result<R>
+where
+    F: FnOnce(&mut Vec<SessionApproval>) -> R,
+{
+    let path = approvals_path();
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let mut file: File = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(&path)?;
+
+    #[allow(clippy::incompatible_msrv)] // fs2 crate method, not std
+    file.lock_exclusive()?;
+
+    let mut contents = String::new();
+    file.read_to_string(&mut contents)?;
+    let mut entries: Vec<SessionApproval> = serde_json::from_str(&contents).unwrap_or_default();
+
+    let now = Utc::now();
+    entries.retain(|e| !e.is_expired(now));
+    let result = f(&mut entries);
+
+    file.set_len(0)?;
+    file.seek(std::io::SeekFrom::Start(0))?;
+    let json = serde_json::to_string_pretty(&entries)? + "\n";
+    file.write_all(json.as_bytes())?;
+    file.flush()?;
+
+    #[allow(clippy::incompatible_msrv)] // fs2 crate method, not std
+    file.unlock()?;
+
+    Ok(result)
+}
+
+/// The most recent session seen asking for approval in `project`, from the pending queue.
+///
+/// Used by `approve --session` when no session ID is given.
+pub fn latest_session(project: &str) -> Option<String> {
+    crate::pending::read_pending(None)
+        .into_iter()
+        .filter(|entry| !entry.session_id.is_empty() && is_within(&entry.cwd, project))
+        .max_by_key(|entry| entry.last_seen)
+        .map(|entry| entry.session_id)
+}
+
+/// Parse a duration like `30m`, `2h`, `1h30m` or `1d`.
+pub fn parse_duration(s: &str) -> Result<Duration, String> {
+    let invalid = || format!("'{s}' isn't a duration like 30m, 2h, 1h30m or 1d");
+    let out_of_range = || format!("'{s}' is out of range");
+    let mut total = Duration::zero();
+    let mut number = String::new();
+    for c in s.trim().chars() {
+        if c.is_ascii_digit() {
+            number.push(c);
+            continue;
+        }
+        if number.is_empty() {
+            return Err(invalid());
+        }
+        let n: i64 = number.parse().map_err(|_| out_of_range())?;
+        number.clear();
+        let unit = match c {
+            'd' => Duration::try_days(n),
+            'h' => Duration::try_hours(n),
+            'm' => Duration::try_minutes(n),
+            's' => Duration::try_seconds(n),
+            _ => return Err(invalid()),
+        };
+        total = unit
+            .and_then(|unit| total.checked_add(&unit))
+            .ok_or_else(out_of_range)?;
+    }
+    // A trailing number without a unit is ambiguous
+    if !number.is_empty() || total <= Duration::zero() {
+        return Err(invalid());
+    }
+    Ok(total)
+}
+
+/// When an approval made at `now` for a duration like `2h` expires.
+pub fn expires_at(now: DateTime<Utc>, duration: &str) -> Result<DateTime<Utc>, String> {
+    now.checked_add_signed(parse_duration(duration)?)
+        .ok_or_else(|| format!("'{duration}' is out of range"))
+}
+
+/// Format a remaining duration: `2d 3h`, `1h 42m`, `12m`, `<1m`.
+pub fn format_remaining(remaining: Duration) -> String {
+    let minutes = remaining.num_minutes();
+    if minutes < 1 {
+        "<1m".to_string()
+    } else if minutes < 60 {
+        format!("{minutes}m")
+    } else if minutes < 24 * 60 {
+        format!("{}h {}m", minutes / 60, minutes % 60)
+    } else {
+        format!("{}d {}h", minutes / (24 * 60), minutes % (24 * 60) / 60)
+    }
+}
+
+/// Whether `path` is `dir` or below it
+fn is_within(path: &str, dir: &str) -> bool {
+    let dir = dir.trim_end_matches('/');
+    path == dir || path.starts_with(&format!("{dir}/")) || dir.is_empty()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn approval(session_id: Option<&str>, expires_in: Option<Duration>) -> SessionApproval {
+        let now = Utc::now();
+        SessionApproval {
+            pattern: "docker compose up:*".to_string(),
+            project: "/work/app".to_string(),
+            session_id: session_id.map(String::from),
+            expires_at: expires_in.map(|d| now + d),
+            created_at: now,
+        }
+    }
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("2h"), Ok(Duration::hours(2)));
+        assert_eq!(parse_duration("30m"), Ok(Duration::minutes(30)));
+        assert_eq!(parse_duration("1h30m"), Ok(Duration::minutes(90)));
+        assert_eq!(parse_duration("1d"), Ok(Duration::days(1)));
+        for invalid in ["90", "2x", "0m", "", "h"] {
+            assert!(parse_duration(invalid).is_err(), "{invalid}");
+        }
+    }
+
+    #[test]
+    fn test_out_of_range_duration_rejected() {
+        let now = Utc::now();
+        for duration in [
+            "99999999999h",
+            "999999999999999d",
+            "99999999999999999999m",
+            "9223372036854775807s9223372036854775807s",
+        ] {
+            let err = expires_at(now, duration).unwrap_err();
+            assert!(err.contains("out of range"), "{duration}: {err}");
+        }
+        assert_eq!(expires_at(now, "2h"), Ok(now + Duration::hours(2)));
+    }
+
+    #[test]
+    fn test_format_remaining() {
+        assert_eq!(format_remaining(Duration::seconds(20)), "<1m");
+        assert_eq!(format_remaining(Duration::minutes(12)), "12m");
+        assert_eq!(format_remaining(Duration::minutes(102)), "1h 42m");
+        assert_eq!(format_remaining(Duration::hours(51)), "2d 3h");
+    }
+
+    #[test]
+    fn test_session_approval_applies() {
+        let approvals = SessionApprovals::new(vec![approval(Some("s1"), None)]);
+        assert!(
+            approvals
+                .allows("docker compose up -d", "s1", "/work/app/api")
+                .is_some()
+        );
+        assert!(
+            approvals
+                .allows("docker compose up", "s2", "/work/app")
+                .is_none()
+        );
+        assert!(
+            approvals
+                .allows("docker compose up", "", "/work/app")
+                .is_none()
+        );
+        assert!(
+            approvals
+                .allows("docker compose up", "s1", "/work/other")
+                .is_none()
+        );
+        assert!(
+            approvals
+                .allows("docker compose down", "s1", "/work/app")
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_timed_approval_expires() {
+        let now = Utc::now();
+        let live = approval(None, Some(Duration::hours(2)));
+        assert!(live.applies("any", "/work/app", now));
+        assert!(!live.applies("any", "/work/app", now + Duration::hours(3)));
+        assert_eq!(live.lifetime(now), "2h 0m left");
+
+        let both = approval(Some("s1"), Some(Duration::minutes(30)));
+        assert_eq!(both.lifetime(now), "this session, 30m left");
+    }
+
+    #[test]
+    fn test_stale_session_approval_expires() {
+        let mut old = approval(Some("s1"), None);
+        old.created_at = Utc::now() - Duration::days(MAX_SESSION_AGE_DAYS + 1);
+        assert!(old.is_expired(Utc::now()));
+        assert_eq!(
+            approval(Some("s1"), None).lifetime(Utc::now()),
+            "this session"
+        );
+    }
+}
@@ -1,8 +1,26 @@
 //! Settings.json parsing and matching for Claude Code permissions.
 //!
 //! Loads user (~/.claude/settings.json) and project (.claude/settings.json)
-//! settings to check if a command matches any allow/deny/ask rules.
+//! settings to check if a command matches any allow/deny/ask rules. Project
+//! settings are found at or above the working directory.
+//!
+//! Rules use Claude Code's permission grammar:
+//!
+//! - `Tool` - every use of a tool (`WebSearch`, `Bash`, `mcp__server`)
+//! - `Bash(cmd:*)` - Bash command pattern (see [`Settings::matches_bash_pattern`])
+//! - `Read(path)`, `Edit(path)`, `Write(path)` - gitignore-style path glob
+//! - `WebFetch(domain:example.com)` - fetches from a host
+//!
+//! Path globs are rooted by their prefix: `//path` is an absolute path, `~/path`
+//! is under the home directory, `/path` is under the project root, and `path` or
+//! `./path` is under the current directory. `*` matches within a path segment and
+//! `**` across segments. A pattern without a `/` matches at any depth, and a
+//! pattern that matches a directory covers everything under it.
+//!
+//! `Edit` and `Write` rules apply to every file-editing tool, `Read` rules to
+//! every file-reading tool.
 
+use crate::trace::{self, TraceEvent};
 use serde::Deserialize;
 use std::fs;
 use std::path::{Component, Path, PathBuf};
@@ -61,6 +79,167 @@ pub enum SettingsDecision {
     NoMatch,
 }
 
+/// File-editing tools. `Edit` and `Write` rules cover all of them.
+pub const EDIT_TOOLS: &[&str] = &["Edit", "Write", "MultiEdit", "NotebookEdit"];
+/// File-reading tools. `Read` rules cover all of them.
+pub const READ_TOOLS: &[&str] = &["Read", "Glob", "Grep", "LS", "NotebookRead"];
+
+/// A tool call to match permission rules against
+#[derive(Debug, Clone, Copy)]
+pub enum ToolUse<'a> {
+    /// A Bash command
+    Bash(&'a str),
+    /// A file tool on one path. Relative paths and rules resolve against `cwd`,
+    /// `/path` rules against the project `root` (see [`Settings::project_root`]).
+    File {
+        tool: &'a str,
+        path: &'a str,
+        cwd: &'a str,
+        root: &'a str,
+    },
+    /// A WebFetch of a URL
+    Fetch(&'a str),
+    /// Any other tool, matched by name only
+    Tool(&'a str),
+}
+
+impl ToolUse<'_> {
+    /// What the rule matched, for traces
+    fn describe(&self) -> String {
+        match self {
+            ToolUse::Bash(command) => command.to_string(),
+            ToolUse::File { tool, path, .. } => format!("{tool} {path}"),
+            ToolUse::Fetch(url) => format!("WebFetch {url}"),
+            ToolUse::Tool(tool) => tool.to_string(),
+        }
+    }
+}
+
+/// A parsed permission rule: `Tool` or `Tool(specifier)`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PermissionRule<'a> {
+    pub tool: &'a str,
+    pub specifier: Option<&'a str>,
+}
+
+impl<'a> PermissionRule<'a> {
+    /// Parse a rule. Returns None for malformed rules (`Bash(git`, `(x)`).
+    pub fn parse(rule: &'a str) -> Option<Self> {
+        let rule = rule.trim();
+        let (tool, specifier) = match rule.split_once('(') {
+            Some((tool, rest)) => (tool, Some(rest.strip_suffix(')')?)),
+            None => (rule, None),
+        };
+        if tool.is_empty()
+            || !tool
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '*'))
+        {
+            return None;
+        }
+        Some(Self {
+            tool,
+            // `Tool()` is the same as `Tool`
+            specifier: specifier.filter(|s| !s.is_empty()),
+        })
+    }
+
+    pub fn matches(&self, tool_use: &ToolUse) -> bool {
+        match *tool_use {
+            ToolUse::Bash(command) => {
+                self.tool == "Bash"
+                    && self
+                        .specifier
+                        .is_none_or(|pattern| Settings::matches_bash_pattern(pattern, command))
+            }
+            ToolUse::File {
+                tool,
+                path,
+                cwd,
+                root,
+            } => {
+                same_tool_family(self.tool, tool)
+                    && self
+                        .specifier
+                        .is_none_or(|pattern| path_matches(pattern, path, cwd, root))
+            }
+            ToolUse::Fetch(url) => {
+                self.tool == "WebFetch"
+                    && self.specifier.is_none_or(|spec| {
+                        spec.strip_prefix("domain:")
+                            .is_some_and(|domain| domain_matches(domain, &url_host(url)))
+                    })
+            }
+            ToolUse::Tool(tool) => self.specifier.is_none() && tool_name_matches(self.tool, tool),
+        }
+    }
+
+    /// Whether this rule matches every call `other` does.
+    ///
+    /// Exact for Bash patterns; other specifiers only cover themselves.
+    pub fn covers(&self, other: &PermissionRule) -> bool {
+        if self.tool != other.tool {
+            return false;
+        }
+        match (self.specifier, other.specifier) {
+            (None, _) => true,
+            (Some(_), None) => false,
+            (Some(broad), Some(narrow)) if self.tool == "Bash" => {
+                bash_pattern_covers(broad, narrow)
+            }
+            (Some(broad), Some(narrow)) => broad == narrow,
+        }
+    }
+}
+
+/// Whether Bash pattern `broad` matches every command `narrow` does
+/// (see [`Settings::matches_bash_pattern`] for the pattern forms).
+fn bash_pattern_covers(broad: &str, narrow: &str) -> bool {
+    // Every command `narrow` matches starts with its prefix; exact patterns match only it
+    let (narrow_prefix, narrow_exact) = match narrow.strip_suffix(":*") {
+        Some(prefix) => (prefix, false),
+        None => match narrow.strip_suffix('*') {
+            Some(prefix) => (prefix, false),
+            None => (narrow, true),
+        },
+    };
+    if let Some(prefix) = broad.strip_suffix(":*") {
+        if narrow_exact || narrow.ends_with(":*") {
+            // `git:*` covers `git status` and `git status:*`, and `git:*` itself
+            narrow_prefix == prefix || narrow_prefix.starts_with(&format!("{prefix} "))
+        } else {
+            // `git st*` also matches `git stx`, so only a prefix past the word boundary is covered
+            narrow_prefix.starts_with(&format!("{prefix} "))
+        }
+    } else if let Some(prefix) = broad.strip_suffix('*') {
+        narrow_prefix.starts_with(prefix)
+    } else {
+        narrow_exact && narrow == broad
+    }
+}
+
+/// Whether a rule's tool name covers the tool being used
+fn same_tool_family(rule_tool: &str, tool: &str) -> bool {
+    rule_tool == tool
+        || [EDIT_TOOLS, READ_TOOLS]
+            .iter()
+            .any(|family| family.contains(&rule_tool) && family.contains(&tool))
+}
+
+/// Bare tool-name match. `mcp__server` and `mcp__server__*` cover every tool on the server.
+fn tool_name_matches(rule_tool: &str, tool: &str) -> bool {
+    if rule_tool == tool {
+        return true;
+    }
+    let Some(rule_server) = rule_tool.strip_prefix("mcp__") else {
+        return false;
+    };
+    let rule_server = rule_server.strip_suffix("__*").unwrap_or(rule_server);
+    tool.strip_prefix("mcp__")
+        .and_then(|rest| rest.strip_prefix(rule_server))
+        .is_some_and(|rest| rest.starts_with("__"))
+}
+
 impl Settings {
     /// Load and merge settings from all locations.
     ///
@@ -72,55 +251,92 @@ impl Settings {
     ///
     /// We load in reverse order and merge, so higher priority settings override.
     pub fn load(cwd: &str) -> Self {
-        let mut merged = Settings::default();
+        let paths: Vec<PathBuf> = Self::scope_paths(cwd)
+            .into_iter()
+            .map(|(_, path)| path)
+            .collect();
+        Self::load_from(&paths)
+    }
 
-        // 4. User settings (~/.claude/settings.json) - lowest priority
-        // Check CLAUDE_CONFIG_DIR env var first, fall back to ~/.claude
-        let user_config_dir = std::env::var("CLAUDE_CONFIG_DIR")
-            .map(PathBuf::from)
-            .ok()
-            .or_else(|| dirs::home_dir().map(|h| h.join(".claude")));
-        if let Some(config_dir) = user_config_dir {
-            let user_path = config_dir.join("settings.json");
-            if let Ok(s) = Self::load_file(&user_path) {
+    /// Load and merge settings from explicit files, lowest priority first.
+    ///
+    /// Missing or unparseable files are skipped.
+    pub fn load_from(paths: &[PathBuf]) -> Self {
+        let mut merged = Settings::default();
+        for path in paths {
+            if let Ok(s) = Self::load_file(path) {
                 merged.merge(s);
             }
         }
+        merged
+    }
 
-        // 3. Shared project settings (.claude/settings.json)
-        if !cwd.is_empty() {
-            let project_path = Path::new(cwd).join(".claude/settings.json");
-            if let Ok(s) = Self::load_file(&project_path) {
-                merged.merge(s);
-            }
+    /// Settings file locations labeled by scope, lowest priority first.
+    pub fn scope_paths(cwd: &str) -> Vec<(&'static str, PathBuf)> {
+        let mut paths = Vec::new();
+
+        // 4. User settings (~/.claude/settings.json) - lowest priority
+        if let Some(config_dir) = user_config_dir() {
+            paths.push(("user", config_dir.join("settings.json")));
         }
 
+        // 3. Shared project settings (.claude/settings.json)
         // 2. Local project settings (.claude/settings.local.json)
         if !cwd.is_empty() {
-            let local_path = Path::new(cwd).join(".claude/settings.local.json");
-            if let Ok(s) = Self::load_file(&local_path) {
-                merged.merge(s);
-            }
+            let root = Self::project_root(cwd);
+            paths.push(("project", Path::new(&root).join(".claude/settings.json")));
+            paths.push((
+                "local",
+                Path::new(&root).join(".claude/settings.local.json"),
+            ));
         }
 
         // 1. Enterprise managed settings - highest priority
         #[cfg(target_os = "linux")]
-        {
-            let managed_path = Path::new("/etc/claude-code/managed-settings.json");
-            if let Ok(s) = Self::load_file(managed_path) {
-                merged.merge(s);
-            }
-        }
+        paths.push((
+            "managed",
+            PathBuf::from("/etc/claude-code/managed-settings.json"),
+        ));
         #[cfg(target_os = "macos")]
-        {
-            let managed_path =
-                Path::new("/Library/Application Support/ClaudeCode/managed-settings.json");
-            if let Ok(s) = Self::load_file(managed_path) {
-                merged.merge(s);
-            }
-        }
+        paths.push((
+            "managed",
+            PathBuf::from("/Library/Application Support/ClaudeCode/managed-settings.json"),
+        ));
 
-        merged
+        paths
+    }
+
+    /// Project root for `cwd`: the nearest directory at or above it with a
+    /// `.claude/settings.json` or `.claude/settings.local.json`, or `cwd` itself.
+    ///
+    /// Project settings load from here, and `/path` rules are rooted here.
+    pub fn project_root(cwd: &str) -> String {
+        let user_dir = user_config_dir();
+        Path::new(cwd)
+            .ancestors()
+            .find(|dir| {
+                let claude_dir = dir.join(".claude");
+                Some(&claude_dir) != user_dir.as_ref()
+                    && (claude_dir.join("settings.json").is_file()
+                        || claude_dir.join("settings.local.json").is_file())
+            })
+            .map_or_else(|| cwd.to_string(), |dir| dir.to_string_lossy().to_string())
+    }
+
+    /// Find which settings file a rule came from (highest priority wins).
+    ///
+    /// `list` is "allow", "ask" or "deny". Returns the scope label and path.
+    pub fn rule_source(cwd: &str, list: &str, rule: &str) -> Option<(&'static str, PathBuf)> {
+        Self::scope_paths(cwd).into_iter().rev().find(|(_, path)| {
+            Self::load_file(path).is_ok_and(|s| {
+                let rules = match list {
+                    "allow" => &s.permissions.allow,
+                    "ask" => &s.permissions.ask,
+                    _ => &s.permissions.deny,
+                };
+                rules.iter().any(|r| r == rule)
+            })
+        })
     }
 
     fn load_file(path: &Path) -> Result<Self, Box<dyn std::error::Error>> {
@@ -175,58 +391,67 @@ impl Settings {
 
     /// Check if command matches any deny rules.
     pub fn is_denied(&self, command: &str) -> bool {
-        self.matches_any(&self.permissions.deny, command)
+        self.matches_any("deny", &self.permissions.deny, &ToolUse::Bash(command))
     }
 
     /// Check command against settings rules.
     /// Priority: deny > ask > allow
     pub fn check_command(&self, command: &str) -> SettingsDecision {
-        // Check deny first (highest priority)
-        if self.matches_any(&self.permissions.deny, command) {
-            return SettingsDecision::Deny;
-        }
+        self.check_tool(&ToolUse::Bash(command))
+    }
+
+    /// Check command against settings rules, excluding deny (for use after deny check).
+    /// Returns Ask, Allow, or NoMatch.
+    pub fn check_command_excluding_deny(&self, command: &str) -> SettingsDecision {
+        let tool_use = ToolUse::Bash(command);
 
         // Check ask
-        if self.matches_any(&self.permissions.ask, command) {
+        if self.matches_any("ask", &self.permissions.ask, &tool_use) {
             return SettingsDecision::Ask;
         }
 
         // Check allow
-        if self.matches_any(&self.permissions.allow, command) {
+        if self.matches_any("allow", &self.permissions.allow, &tool_use) {
             return SettingsDecision::Allow;
         }
 
         SettingsDecision::NoMatch
     }
 
-    /// Check command against settings rules, excluding deny (for use after deny check).
-    /// Returns Ask, Allow, or NoMatch.
-    pub fn check_command_excluding_deny(&self, command: &str) -> SettingsDecision {
+    /// Check any tool call against settings rules.
+    /// Priority: deny > ask > allow
+    pub fn check_tool(&self, tool_use: &ToolUse) -> SettingsDecision {
+        // Check deny first (highest priority)
+        if self.matches_any("deny", &self.permissions.deny, tool_use) {
+            return SettingsDecision::Deny;
+        }
+
         // Check ask
-        if self.matches_any(&self.permissions.ask, command) {
+        if self.matches_any("ask", &self.permissions.ask, tool_use) {
             return SettingsDecision::Ask;
         }
 
         // Check allow
-        if self.matches_any(&self.permissions.allow, command) {
+        if self.matches_any("allow", &self.permissions.allow, tool_use) {
             return SettingsDecision::Allow;
         }
 
         SettingsDecision::NoMatch
     }
 
-    /// Match command against Bash(...) patterns
-    fn matches_any(&self, patterns: &[String], command: &str) -> bool {
-        for pattern in patterns {
-            if let Some(bash_pattern) = pattern.strip_prefix("Bash(") {
-                if let Some(inner) = bash_pattern.strip_suffix(')') {
-                    if Self::matches_bash_pattern(inner, command) {
-                        return true;
-                    }
-                }
-            }
-        }
-        false
+    /// Match a tool call against the rules from one permission list
+    fn matches_any(&self, list: &'static str, patterns: &[String], tool_use: &ToolUse) -> bool {
+        let Some(pattern) = patterns.iter().find(|pattern| {
+            PermissionRule::parse(pattern).is_some_and(|rule| rule.matches(tool_use))
+        }) else {
+            return false;
+        };
+        trace::record(|| TraceEvent::SettingsRule {
+            list,
+            rule: pattern.clone(),
+            command: tool_use.describe(),
+        });
+        true
     }
 
     /// Check if an MCP tool is allowed in settings.json.
@@ -238,55 +463,14 @@ impl Settings {
     ///
     /// Returns: Allow, Deny, Ask, or NoMatch
     pub fn check_mcp_tool(&self, server: &str, tool: &str) -> SettingsDecision {
-        // Check deny rules first
-        if self.matches_mcp_pattern(&self.permissions.deny, server, tool) {
-            return SettingsDecision::Deny;
-        }
-
-        // Check ask rules
-        if self.matches_mcp_pattern(&self.permissions.ask, server, tool) {
-            return SettingsDecision::Ask;
-        }
-
-        // Check allow rules
-        if self.matches_mcp_pattern(&self.permissions.allow, server, tool) {
-            return SettingsDecision::Allow;
-        }
-
-        SettingsDecision::NoMatch
-    }
-
-    /// Check if an MCP server/tool matches any mcp__ patterns in the list.
-    fn matches_mcp_pattern(&self, patterns: &[String], server: &str, tool: &str) -> bool {
-        for pattern in patterns {
-            // Check for mcp__ prefix
-            if let Some(mcp_pattern) = pattern.strip_prefix("mcp__") {
-                // mcp__server - entire server
-                if mcp_pattern == server {
-                    return true;
-                }
-
-                // mcp__server__tool - specific tool
-                let specific = format!("{}__{}", server, tool);
-                if mcp_pattern == specific {
-                    return true;
-                }
-
-                // mcp__server__* - wildcard for server
-                let wildcard = format!("{}__*", server);
-                if mcp_pattern == wildcard {
-                    return true;
-                }
-            }
-        }
-        false
+        self.check_tool(&ToolUse::Tool(&format!("mcp__{server}__{tool}")))
     }
 
     /// Match Bash pattern:
     /// - "cmd:*" - prefix match with word boundary (git:* matches "git status")
     /// - "cmd*" - glob prefix match (cat /dev/zero* matches "cat /dev/zero | head")
     /// - "cmd" - exact match
-    fn matches_bash_pattern(pattern: &str, command: &str) -> bool {
+    pub fn matches_bash_pattern(pattern: &str, command: &str) -> bool {
         if let Some(prefix) = pattern.strip_suffix(":*") {
             // Word-boundary prefix match: "git:*" matches "git", "git status"
             command == prefix || command.starts_with(&format!("{prefix} "))
@@ -300,6 +484,135 @@ impl Settings {
     }
 }
 
+/// User settings directory: `CLAUDE_CONFIG_DIR`, or `~/.claude`.
+fn user_config_dir() -> Option<PathBuf> {
+    std::env::var("CLAUDE_CONFIG_DIR")
+        .map(PathBuf::from)
+        .ok()
+        .or_else(|| dirs::home_dir().map(|h| h.join(".claude")))
+}
+
+/// Whether a path glob from a rule covers `path`.
+fn path_matches(pattern: &str, path: &str, cwd: &str, root: &str) -> bool {
+    let Some(pattern) = rule_path(pattern, cwd, root) else {
+        return false;
+    };
+    let Some(path) = resolve_path(path, cwd) else {
+        return false;
+    };
+    let pattern: Vec<&str> = pattern.split('/').filter(|s| !s.is_empty()).collect();
+    let path: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+    // A match on the path or any of its parent directories
+    (1..=path.len()).any(|n| glob_segments(&pattern, &path[..n]))
+}
+
+/// Whether `path` matches a glob (`**` spans directories, `*` and `?` match within one).
+pub fn glob_match(pattern: &str, path: &str) -> bool {
+    let pattern: Vec<&str> = pattern.split('/').filter(|s| !s.is_empty()).collect();
+    let path: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+    glob_segments(&pattern, &path)
+}
+
+/// Absolute glob for a rule's path specifier.
+fn rule_path(pattern: &str, cwd: &str, root: &str) -> Option<String> {
+    let absolute = if let Some(rest) = pattern.strip_prefix("//") {
+        format!("/{rest}")
+    } else if pattern == "~" || pattern.starts_with("~/") {
+        let home = dirs::home_dir()?;
+        format!("{}/{}", home.to_string_lossy(), &pattern[1..])
+    } else if let Some(rest) = pattern.strip_prefix('/') {
+        format!("{}/{rest}", root.trim_end_matches('/'))
+    } else {
+        let rest = pattern.strip_prefix("./").unwrap_or(pattern);
+        let anchored = pattern.starts_with("./") || rest.trim_end_matches('/').contains('/');
+        if anchored {
+            format!("{cwd}/{rest}")
+        } else {
+            format!("{cwd}/**/{rest}")
+        }
+    };
+    // `dir/` covers the directory's contents
+    Some(match absolute.strip_suffix('/') {
+        Some(dir) => format!("{dir}/**"),
+        None => absolute,
+    })
+}
+
+/// Absolute, normalized form of a tool's file path.
+fn resolve_path(path: &str, cwd: &str) -> Option<String> {
+    let absolute = if path == "~" || path.starts_with("~/") {
+        dirs::home_dir()?.join(path[1..].trim_start_matches('/'))
+    } else {
+        Path::new(cwd).join(path)
+    };
+    Some(normalize_path(&absolute))
+}
+
+/// Match path segments against glob segments (`**` spans any number of segments).
+fn glob_segments(pattern: &[&str], path: &[&str]) -> bool {
+    match pattern.split_first() {
+        None => path.is_empty(),
+        Some((&"**", rest)) => (0..=path.len()).any(|i| glob_segments(rest, &path[i..])),
+        Some((segment, rest)) => path
+            .split_first()
+            .is_some_and(|(name, tail)| glob_segment(segment, name) && glob_segments(rest, tail)),
+    }
+}
+
+/// Match one path segment against `*` / `?` wildcards.
+fn glob_segment(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    let (mut p, mut n) = (0, 0);
+    // Last `*` seen and the name position it was tried at
+    let mut star: Option<(usize, usize)> = None;
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p, n));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match star {
+                Some((sp, sn)) => {
+                    p = sp + 1;
+                    n = sn + 1;
+                    star = Some((sp, sn + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// Lowercased host of a URL (no scheme, credentials or port).
+fn url_host(url: &str) -> String {
+    let rest = url.split_once("://").map_or(url, |(_, rest)| rest);
+    let authority = rest.split(['/', '?', '#']).next().unwrap_or("");
+    let host = authority
+        .rsplit_once('@')
+        .map_or(authority, |(_, host)| host);
+    let host = match host.strip_prefix('[') {
+        // IPv6 literal
+        Some(v6) => v6.split(']').next().unwrap_or(""),
+        None => host.split(':').next().unwrap_or(""),
+    };
+    host.to_ascii_lowercase()
+}
+
+/// `domain:example.com` matches that host; `domain:*.example.com` matches its subdomains.
+fn domain_matches(domain: &str, host: &str) -> bool {
+    let domain = domain.to_ascii_lowercase();
+    match domain.strip_prefix("*.") {
+        Some(parent) => host.len() > parent.len() && host.ends_with(&format!(".{parent}")),
+        None => host == domain,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -618,6 +931,246 @@ mod tests {
         );
     }
 
+    // === Permission Rule Grammar Tests ===
+
+    fn rules(allow: &[&str], ask: &[&str], deny: &[&str]) -> Settings {
+        let owned = |rules: &[&str]| rules.iter().map(|r| r.to_string()).collect();
+        Settings {
+            permissions: Permissions {
+                allow: owned(allow),
+                ask: owned(ask),
+                deny: owned(deny),
+                ..Default::default()
+            },
+        }
+    }
+
+    fn file<'a>(tool: &'a str, path: &'a str) -> ToolUse<'a> {
+        ToolUse::File {
+            tool,
+            path,
+            cwd: "/project",
+            root: "/project",
+        }
+    }
+
+    #[test]
+    fn test_parse_rule() {
+        let rule = PermissionRule::parse("Bash(git log:*)").unwrap();
+        assert_eq!((rule.tool, rule.specifier), ("Bash", Some("git log:*")));
+        let rule = PermissionRule::parse("WebSearch").unwrap();
+        assert_eq!((rule.tool, rule.specifier), ("WebSearch", None));
+        assert_eq!(PermissionRule::parse("Read()").unwrap().specifier, None);
+        assert!(PermissionRule::parse("Bash(git").is_none());
+        assert!(PermissionRule::parse("(x)").is_none());
+    }
+
+    #[test]
+    fn test_rule_covers() {
+        let covers = |broad: &str, narrow: &str| {
+            PermissionRule::parse(broad)
+                .unwrap()
+                .covers(&PermissionRule::parse(narrow).unwrap())
+        };
+        assert!(covers("Bash(git:*)", "Bash(git status)"));
+        assert!(covers("Bash(git:*)", "Bash(git log:*)"));
+        assert!(covers("Bash(git:*)", "Bash(git log --oneline*)"));
+        assert!(covers("Bash(git*)", "Bash(gitk:*)"));
+        assert!(covers("Bash", "Bash(rm:*)"));
+        assert!(covers("Read", "Read(./src/**)"));
+        assert!(!covers("Bash(git:*)", "Bash(git*)"));
+        assert!(!covers("Bash(git:*)", "Bash(gitk)"));
+        assert!(!covers("Bash(git status)", "Bash(git status:*)"));
+        assert!(!covers("Bash(git log:*)", "Bash(git)"));
+        assert!(!covers("Bash(rm:*)", "Bash"));
+        assert!(!covers("Read(./src/**)", "Read(./src/main.rs)"));
+        assert!(!covers("Edit", "Write(x)"));
+    }
+
+    #[test]
+    fn test_bare_tool_names() {
+        let settings = rules(&["WebSearch"], &[], &["Bash"]);
+        assert_eq!(settings.check_command("ls"), SettingsDecision::Deny);
+        assert_eq!(
+            settings.check_tool(&ToolUse::Tool("WebSearch")),
+            SettingsDecision::Allow
+        );
+        assert_eq!(
+            settings.check_tool(&ToolUse::Tool("Task")),
+            SettingsDecision::NoMatch
+        );
+        // A Bash(...) rule doesn't cover other tools
+        let settings = rules(&["Bash(ls:*)"], &[], &[]);
+        assert_eq!(
+            settings.check_tool(&ToolUse::Tool("Bash")),
+            SettingsDecision::NoMatch
+        );
+    }
+
+    #[test]
+    fn test_path_roots() {
+        let home = dirs::home_dir().unwrap().to_string_lossy().to_string();
+        let settings = rules(
+            &["Edit(src/**)"],
+            &[],
+            &["Read(~/.ssh/**)", "Write(//etc/**)", "Edit(/secrets)"],
+        );
+        let ssh_key = format!("{home}/.ssh/id_ed25519");
+        assert_eq!(
+            settings.check_tool(&file("Read", &ssh_key)),
+            SettingsDecision::Deny
+        );
+        assert_eq!(
+            settings.check_tool(&file("Read", "~/.ssh/config")),
+            SettingsDecision::Deny
+        );
+        assert_eq!(
+            settings.check_tool(&file("Edit", "/etc/hosts")),
+            SettingsDecision::Deny
+        );
+        // `/path` is project-relative, and a directory covers its contents
+        assert_eq!(
+            settings.check_tool(&file("Write", "secrets/prod.env")),
+            SettingsDecision::Deny
+        );
+        assert_eq!(
+            settings.check_tool(&file("Write", "/secrets/prod.env")),
+            SettingsDecision::NoMatch
+        );
+        assert_eq!(
+            settings.check_tool(&file("Edit", "src/lib.rs")),
+            SettingsDecision::Allow
+        );
+        assert_eq!(
+            settings.check_tool(&file("Edit", "/project/src/a/b.rs")),
+            SettingsDecision::Allow
+        );
+        assert_eq!(
+            settings.check_tool(&file("Edit", "docs/src/a.md")),
+            SettingsDecision::NoMatch
+        );
+    }
+
+    #[test]
+    fn test_project_root_from_subdirectory() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let root = dir.path().to_string_lossy().to_string();
+        fs::create_dir_all(dir.path().join(".claude")).unwrap();
+        fs::create_dir_all(dir.path().join("web/src")).unwrap();
+        fs::write(
+            dir.path().join(".claude/settings.json"),
+            r#"{"permissions": {"deny": ["Edit(/web/config)"]}}"#,
+        )
+        .unwrap();
+
+        let cwd = format!("{root}/web/src");
+        assert_eq!(Settings::project_root(&cwd), root);
+        let settings = Settings::load(&cwd);
+        let edit = |path: &str| {
+            settings.check_tool(&ToolUse::File {
+                tool: "Edit",
+                path,
+                cwd: &cwd,
+                root: &Settings::project_root(&cwd),
+            })
+        };
+        // `/web/config` is under the project root, not the subdirectory
+        assert_eq!(edit("../config/app.json"), SettingsDecision::Deny);
+        assert_eq!(edit("web/config/app.json"), SettingsDecision::NoMatch);
+    }
+
+    #[test]
+    fn test_path_globs() {
+        let settings = rules(&[], &["Edit(*.lock)", "Read(./config/*.json)"], &[]);
+        // No slash: any depth
+        assert_eq!(
+            settings.check_tool(&file("Edit", "web/yarn.lock")),
+            SettingsDecision::Ask
+        );
+        assert_eq!(
+            settings.check_tool(&file("Read", "config/app.json")),
+            SettingsDecision::Ask
+        );
+        assert_eq!(
+            settings.check_tool(&file("Read", "config/nested/app.json")),
+            SettingsDecision::NoMatch
+        );
+        assert_eq!(
+            settings.check_tool(&file("Read", "../project/config/app.json")),
+            SettingsDecision::Ask
+        );
+        assert!(glob_segment("*.test.?s", "a.test.ts"));
+        assert!(glob_segment("a*b*c", "aXbYbc"));
+        assert!(!glob_segment("*.rs", "main.rsx"));
+    }
+
+    #[test]
+    fn test_edit_family() {
+        let settings = rules(&[], &[], &["Edit(//etc/**)", "Read(.env)"]);
+        for tool in ["Edit", "Write", "MultiEdit", "NotebookEdit"] {
+            assert_eq!(
+                settings.check_tool(&file(tool, "/etc/passwd")),
+                SettingsDecision::Deny,
+                "{tool}"
+            );
+        }
+        // Edit rules don't cover reads, and Read rules don't cover edits
+        assert_eq!(
+            settings.check_tool(&file("Read", "/etc/passwd")),
+            SettingsDecision::NoMatch
+        );
+        assert_eq!(
+            settings.check_tool(&file("Write", ".env")),
+            SettingsDecision::NoMatch
+        );
+        assert_eq!(
+            settings.check_tool(&file("Grep", "app/.env")),
+            SettingsDecision::Deny
+        );
+    }
+
+    #[test]
+    fn test_webfetch_domain() {
+        let settings = rules(
+            &[
+                "WebFetch(domain:github.com)",
+                "WebFetch(domain:*.rust-lang.org)",
+            ],
+            &[],
+            &["WebFetch(domain:evil.example)"],
+        );
+        let fetch = |url| settings.check_tool(&ToolUse::Fetch(url));
+        assert_eq!(fetch("https://github.com/a/b"), SettingsDecision::Allow);
+        assert_eq!(fetch("https://GitHub.com:443/x"), SettingsDecision::Allow);
+        assert_eq!(fetch("https://api.github.com/x"), SettingsDecision::NoMatch);
+        assert_eq!(
+            fetch("https://doc.rust-lang.org/std"),
+            SettingsDecision::Allow
+        );
+        assert_eq!(fetch("https://rust-lang.org/"), SettingsDecision::NoMatch);
+        assert_eq!(
+            fetch("https://github.com@evil.example/"),
+            SettingsDecision::Deny
+        );
+    }
+
+    #[test]
+    fn test_precedence_across_tools() {
+        let settings = rules(&["Edit"], &["Edit(src/**)"], &["Edit(src/generated/**)"]);
+        assert_eq!(
+            settings.check_tool(&file("Edit", "src/generated/rules.rs")),
+            SettingsDecision::Deny
+        );
+        assert_eq!(
+            settings.check_tool(&file("Edit", "src/main.rs")),
+            SettingsDecision::Ask
+        );
+        assert_eq!(
+            settings.check_tool(&file("Write", "README.md")),
+            SettingsDecision::Allow
+        );
+    }
+
     #[test]
     fn test_mcp_no_match() {
         // Empty settings - no match
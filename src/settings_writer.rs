@@ -276,6 +276,46 @@ pub fn remove_rule(scope: Scope, pattern: &str) -> std::io::Result<bool> {
     })
 }
 
+/// Remove duplicate permission rules from settings.json.
+///
+/// Keeps the first copy of a rule that appears more than once in the same list,
+/// and drops every copy of the `redundant` rules (exact settings.json strings,
+/// e.g. ones another scope already has). Returns how many entries were removed.
+pub fn remove_duplicate_rules(
+    scope: Scope,
+    redundant: &[(RuleType, String)],
+) -> std::io::Result<usize> {
+    with_exclusive_settings(scope, |settings| {
+        let Some(permissions) = settings.get_mut("permissions") else {
+            return 0;
+        };
+
+        let mut removed = 0;
+        for rule_type in [RuleType::Allow, RuleType::Ask, RuleType::Deny] {
+            let Some(arr) = permissions
+                .get_mut(rule_type.as_str())
+                .and_then(|v| v.as_array_mut())
+            else {
+                continue;
+            };
+            let mut seen = std::collections::HashSet::new();
+            let len_before = arr.len();
+            arr.retain(|r| {
+                let Some(rule) = r.as_str() else {
+                    return true;
+                };
+                let is_redundant = redundant
+                    .iter()
+                    .any(|(t, pattern)| *t == rule_type && pattern == rule);
+                !is_redundant && seen.insert(rule.to_string())
+            });
+            removed += len_before - arr.len();
+        }
+
+        removed
+    })
+}
+
 /// List all permission rules from a scope
 pub fn list_rules(scope: Scope) -> Vec<PermissionRule> {
     let settings = load_settings(scope);
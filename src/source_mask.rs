@@ -0,0 +1,571 @@
+//! Comment and string masking for security reminder scanning.
+//!
+//! A docstring mentioning `pickle.load` or a `// never use eval()` comment is
+//! not a call. Before rules match, the content is lexed by the language its
+//! file extension names, and comments (and for call-style rules, string
+//! contents) are blanked out. Masked characters become spaces and newlines are
+//! kept, so line structure and offsets stay the same. String delimiters stay
+//! in place, so `f"SELECT` still reads as an f-string. Code inside strings
+//! (JS `${...}`, Python f-string `{...}` fields) is not masked, and neither is
+//! a JS regex literal's delimiter, so `/"/` doesn't open a string.
+//!
+//! The lexers are deliberately small: enough to find comment and string
+//! boundaries, not to parse. Edit fragments are lexed as if they were whole
+//! files.
+
+/// Languages with comment and string masking
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Language {
+    Python,
+    /// JavaScript and TypeScript
+    JavaScript,
+    Go,
+    Rust,
+    Ruby,
+    Php,
+    Shell,
+    Yaml,
+}
+
+impl Language {
+    /// Language from a file's extension (or name, for `Rakefile` and `Gemfile`)
+    pub fn from_path(path: &str) -> Option<Self> {
+        let path = path.replace('\\', "/");
+        let basename = path.rsplit('/').next().unwrap_or(&path);
+        if matches!(basename, "Rakefile" | "Gemfile") {
+            return Some(Self::Ruby);
+        }
+        let (_, ext) = basename.rsplit_once('.')?;
+        Some(match ext.to_ascii_lowercase().as_str() {
+            "py" | "pyi" | "pyw" => Self::Python,
+            "js" | "jsx" | "mjs" | "cjs" | "ts" | "tsx" | "mts" | "cts" => Self::JavaScript,
+            "go" => Self::Go,
+            "rs" => Self::Rust,
+            "rb" | "rake" | "gemspec" => Self::Ruby,
+            "php" => Self::Php,
+            "sh" | "bash" | "zsh" | "ksh" => Self::Shell,
+            "yml" | "yaml" => Self::Yaml,
+            _ => return None,
+        })
+    }
+
+    /// Whether `"` and `'` strings can span lines
+    fn multiline_strings(self) -> bool {
+        matches!(
+            self,
+            Self::Rust | Self::Ruby | Self::Php | Self::Shell | Self::Yaml
+        )
+    }
+}
+
+/// Blank out comments and/or string contents in `content`.
+pub fn mask(content: &str, language: Language, comments: bool, strings: bool) -> String {
+    if !comments && !strings {
+        return content.to_string();
+    }
+    let mut masker = Masker {
+        chars: content.chars().collect(),
+        i: 0,
+        out: String::with_capacity(content.len()),
+        language,
+        comments,
+        strings,
+    };
+    masker.code(false);
+    masker.out
+}
+
+struct Masker {
+    chars: Vec<char>,
+    i: usize,
+    out: String,
+    language: Language,
+    comments: bool,
+    strings: bool,
+}
+
+impl Masker {
+    fn starts_with(&self, s: &str) -> bool {
+        s.chars()
+            .enumerate()
+            .all(|(n, c)| self.chars.get(self.i + n) == Some(&c))
+    }
+
+    fn peek(&self, offset: usize) -> Option<char> {
+        self.chars.get(self.i + offset).copied()
+    }
+
+    fn prev(&self) -> Option<char> {
+        self.i.checked_sub(1).map(|i| self.chars[i])
+    }
+
+    /// Copy `n` characters, masked or not. Newlines are always kept.
+    fn emit(&mut self, n: usize, masked: bool) {
+        for _ in 0..n {
+            let Some(&c) = self.chars.get(self.i) else {
+                return;
+            };
+            self.out.push(if masked && c != '\n' { ' ' } else { c });
+            self.i += 1;
+        }
+    }
+
+    /// Code until the end, or until the `}` closing a template `${` expression.
+    fn code(&mut self, template_expr: bool) {
+        let mut depth = 0usize;
+        while let Some(c) = self.peek(0) {
+            if template_expr {
+                match c {
+                    '{' => depth += 1,
+                    '}' if depth == 0 => return,
+                    '}' => depth -= 1,
+                    _ => {}
+                }
+            }
+            if self.line_comment_start() {
+                while self.peek(0).is_some_and(|c| c != '\n') {
+                    self.emit(1, self.comments);
+                }
+            } else if let Some((close, nested)) = self.block_comment_start() {
+                self.block_comment(close, nested);
+            } else if !self.string() {
+                self.emit(1, false);
+            }
+        }
+    }
+
+    fn line_comment_start(&self) -> bool {
+        match self.language {
+            Language::Python | Language::Ruby => self.starts_with("#"),
+            Language::Php => self.starts_with("#") || self.starts_with("//"),
+            Language::JavaScript | Language::Go | Language::Rust => self.starts_with("//"),
+            // `$#` and `a#b` aren't comments
+            Language::Shell | Language::Yaml => {
+                self.starts_with("#") && self.prev().is_none_or(char::is_whitespace)
+            }
+        }
+    }
+
+    /// The closing delimiter, and whether the comment nests
+    fn block_comment_start(&self) -> Option<(&'static str, bool)> {
+        match self.language {
+            Language::JavaScript | Language::Go | Language::Php if self.starts_with("/*") => {
+                Some(("*/", false))
+            }
+            Language::Rust if self.starts_with("/*") => Some(("*/", true)),
+            Language::Ruby if self.starts_with("=begin") && self.at_line_start() => {
+                Some(("\n=end", false))
+            }
+            _ => None,
+        }
+    }
+
+    fn at_line_start(&self) -> bool {
+        self.prev().is_none_or(|c| c == '\n')
+    }
+
+    fn block_comment(&mut self, close: &str, nested: bool) {
+        let open_len = if close == "*/" { 2 } else { 1 };
+        self.emit(open_len, self.comments);
+        let mut depth = 1;
+        while self.peek(0).is_some() {
+            if nested && self.starts_with("/*") {
+                depth += 1;
+                self.emit(2, self.comments);
+            } else if self.starts_with(close) {
+                self.emit(close.chars().count(), self.comments);
+                depth -= 1;
+                if depth == 0 {
+                    break;
+                }
+            } else {
+                self.emit(1, self.comments);
+            }
+        }
+        // Ruby's `=end` line is part of the comment
+        if close == "\n=end" {
+            while self.peek(0).is_some_and(|c| c != '\n') {
+                self.emit(1, self.comments);
+            }
+        }
+    }
+
+    /// Lex a string literal starting here. False when none starts.
+    fn string(&mut self) -> bool {
+        let c = self.peek(0).unwrap_or_default();
+        match self.language {
+            Language::Python if c == '"' || c == '\'' => {
+                let triple: String = [c; 3].iter().collect();
+                let (close, multiline) = if self.starts_with(&triple) {
+                    (triple, true)
+                } else {
+                    (c.to_string(), false)
+                };
+                let f_string = self.python_f_prefix();
+                self.emit(close.len(), false);
+                if f_string {
+                    self.f_string(&close, multiline);
+                } else {
+                    self.quoted(&close, true, multiline);
+                }
+            }
+            Language::JavaScript if c == '`' => self.template(),
+            Language::JavaScript if c == '/' && self.js_regex_start() => self.regex_literal(),
+            Language::Go if c == '`' => {
+                self.emit(1, false);
+                self.quoted("`", false, true);
+            }
+            Language::Rust if c == 'r' || c == 'b' => return self.rust_raw_string(),
+            // 'a' and '\n' are chars; 'a alone is a lifetime
+            Language::Rust
+                if c == '\'' && (self.peek(1) == Some('\\') || self.peek(2) == Some('\'')) =>
+            {
+                self.emit(1, false);
+                self.quoted("'", true, false);
+            }
+            Language::Rust if c == '\'' => return false,
+            Language::Shell if c == '\'' => {
+                self.emit(1, false);
+                self.quoted("'", false, true);
+            }
+            Language::Yaml if (c == '"' || c == '\'') && self.yaml_value_start() => {
+                self.emit(1, false);
+                // '' inside a single-quoted scalar reads as close-then-open
+                self.quoted(&c.to_string(), c == '"', true);
+            }
+            Language::Yaml => return false,
+            _ if c == '"' || c == '\'' => {
+                self.emit(1, false);
+                self.quoted(&c.to_string(), true, self.language.multiline_strings());
+            }
+            _ => return false,
+        }
+        true
+    }
+
+    /// String contents up to and including `close`
+    fn quoted(&mut self, close: &str, escapes: bool, multiline: bool) {
+        let start = self.mark();
+        while let Some(c) = self.peek(0) {
+            if escapes && c == '\\' {
+                self.emit(2, self.strings);
+            } else if self.starts_with(close) {
+                self.emit(close.chars().count(), false);
+                return;
+            } else if c == '\n' && !multiline {
+                // Unterminated: stop at the end of the line
+                return;
+            } else {
+                self.emit(1, self.strings);
+            }
+        }
+        self.unterminated(start);
+    }
+
+    /// JS template literal; `${...}` expressions are code
+    fn template(&mut self) {
+        self.emit(1, false);
+        let start = self.mark();
+        while let Some(c) = self.peek(0) {
+            if c == '\\' {
+                self.emit(2, self.strings);
+            } else if c == '`' {
+                self.emit(1, false);
+                return;
+            } else if self.starts_with("${") {
+                self.emit(2, false);
+                self.code(true);
+                self.emit(1, false);
+            } else {
+                self.emit(1, self.strings);
+            }
+        }
+        self.unterminated(start);
+    }
+
+    /// Python f-string contents after the opening quote; `{...}` fields are code
+    fn f_string(&mut self, close: &str, multiline: bool) {
+        let start = self.mark();
+        while let Some(c) = self.peek(0) {
+            if c == '\\' || self.starts_with("{{") || self.starts_with("}}") {
+                self.emit(2, self.strings);
+            } else if self.starts_with(close) {
+                self.emit(close.chars().count(), false);
+                return;
+            } else if c == '\n' && !multiline {
+                return;
+            } else if c == '{' {
+                self.emit(1, false);
+                self.code(true);
+                self.emit(1, false);
+            } else {
+                self.emit(1, self.strings);
+            }
+        }
+        self.unterminated(start);
+    }
+
+    /// Whether the quote here follows an f-string prefix (`f`, `rf`, `Fr`, ...)
+    fn python_f_prefix(&self) -> bool {
+        let prefix: Vec<char> = self.chars[..self.i]
+            .iter()
+            .rev()
+            .take_while(|c| c.is_ascii_alphanumeric() || **c == '_')
+            .copied()
+            .collect();
+        prefix.len() <= 2
+            && prefix.iter().any(|c| matches!(c, 'f' | 'F'))
+            && prefix.iter().all(|c| matches!(c, 'f' | 'F' | 'r' | 'R'))
+    }
+
+    /// Whether a `/` here starts a JS regex literal rather than dividing:
+    /// it follows an operator, an opening bracket or a keyword like `return`.
+    fn js_regex_start(&self) -> bool {
+        let mut before = self.chars[..self.i]
+            .iter()
+            .rev()
+            .skip_while(|c| c.is_whitespace())
+            .peekable();
+        // `<` and `>` are left out: `</div>` closes a JSX tag
+        let Some(&&last) = before.peek() else {
+            return true;
+        };
+        if "(,=:[!&|?{};+-*%~^".contains(last) {
+            return true;
+        }
+        let word: String = before
+            .take_while(|c| c.is_ascii_alphanumeric() || **c == '_' || **c == '$')
+            .collect::<Vec<_>>()
+            .into_iter()
+            .rev()
+            .collect();
+        matches!(
+            word.as_str(),
+            "return"
+                | "typeof"
+                | "case"
+                | "do"
+                | "else"
+                | "in"
+                | "of"
+                | "new"
+                | "delete"
+                | "void"
+                | "throw"
+                | "yield"
+                | "await"
+                | "instanceof"
+        )
+    }
+
+    /// JS regex literal; the pattern is masked like string contents. One never
+    /// spans lines.
+    fn regex_literal(&mut self) {
+        self.emit(1, false);
+        let mut class = false;
+        while let Some(c) = self.peek(0) {
+            match c {
+                '\\' if self.peek(1) != Some('\n') => self.emit(2, self.strings),
+                '\n' => return,
+                '/' if !class => {
+                    self.emit(1, false);
+                    return;
+                }
+                '[' => {
+                    class = true;
+                    self.emit(1, self.strings);
+                }
+                ']' => {
+                    class = false;
+                    self.emit(1, self.strings);
+                }
+                _ => self.emit(1, self.strings),
+            }
+        }
+    }
+
+    /// Position to rewind to: input index and output length
+    fn mark(&self) -> (usize, usize) {
+        (self.i, self.out.len())
+    }
+
+    /// A multiline string that never closes. Rather than mask everything after
+    /// it, mask to the end of its line and lex the rest as code again.
+    fn unterminated(&mut self, (i, len): (usize, usize)) {
+        self.i = i;
+        self.out.truncate(len);
+        while self.peek(0).is_some_and(|c| c != '\n') {
+            self.emit(1, self.strings);
+        }
+    }
+
+    /// `r"..."`, `r#"..."#`, `br"..."`, or a plain identifier starting with r/b
+    fn rust_raw_string(&mut self) -> bool {
+        if self.prev().is_some_and(|c| c.is_alphanumeric() || c == '_') {
+            return false;
+        }
+        let prefix = if self.starts_with("br") { 2 } else { 1 };
+        if self.chars[self.i + prefix - 1] != 'r' {
+            return false;
+        }
+        let hashes = (0..)
+            .take_while(|&n| self.peek(prefix + n) == Some('#'))
+            .count();
+        if self.peek(prefix + hashes) != Some('"') {
+            return false;
+        }
+        self.emit(prefix + hashes + 1, false);
+        let close: String = std::iter::once('"')
+            .chain(std::iter::repeat_n('#', hashes))
+            .collect();
+        self.quoted(&close, false, true);
+        true
+    }
+
+    /// Whether a YAML quote here opens a quoted scalar rather than sitting in
+    /// a plain one (`run: echo it's`)
+    fn yaml_value_start(&self) -> bool {
+        let before = self.chars[..self.i]
+            .iter()
+            .rev()
+            .take_while(|&&c| c != '\n')
+            .find(|c| !c.is_whitespace());
+        before.is_none_or(|c| matches!(c, ':' | '-' | '[' | '{' | ',' | '?'))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn comments(content: &str, path: &str) -> String {
+        mask(content, Language::from_path(path).unwrap(), true, false)
+    }
+
+    fn code(content: &str, path: &str) -> String {
+        mask(content, Language::from_path(path).unwrap(), true, true)
+    }
+
+    #[test]
+    fn test_from_path() {
+        assert_eq!(Language::from_path("/a/b.PY"), Some(Language::Python));
+        assert_eq!(Language::from_path("app.tsx"), Some(Language::JavaScript));
+        assert_eq!(Language::from_path("/repo/Rakefile"), Some(Language::Ruby));
+        assert_eq!(
+            Language::from_path(".github/workflows/ci.yml"),
+            Some(Language::Yaml)
+        );
+        assert_eq!(Language::from_path("README.md"), None);
+        assert_eq!(Language::from_path("Makefile"), None);
+    }
+
+    #[test]
+    fn test_python() {
+        let src = "x = pickle.load(f)  # not pickle.load\ndoc = \"\"\"uses\npickle.load\"\"\"\n";
+        let masked = comments(src, "a.py");
+        assert_eq!(masked.matches("pickle.load").count(), 2);
+        assert_eq!(masked.lines().count(), src.lines().count());
+
+        let masked = code(src, "a.py");
+        assert_eq!(masked.matches("pickle.load").count(), 1);
+        assert!(masked.contains("doc = \"\"\""));
+        assert_eq!(masked.len(), src.len());
+    }
+
+    #[test]
+    fn test_javascript() {
+        let src = "// never use eval()\n/* eval( */ const s = 'eval(x)';\nconst t = `${eval(y)} eval(z)`;";
+        let masked = code(src, "a.ts");
+        assert_eq!(masked.matches("eval(").count(), 1);
+        assert!(masked.contains("${eval(y)}"));
+        assert!(comments(src, "a.ts").contains("'eval(x)'"));
+    }
+
+    #[test]
+    fn test_rust() {
+        let src = "fn f<'a>(s: &'a str) { let c = '\"'; /* outer /* eval( */ eval( */ eval(r#\"eval(\"#) }";
+        let masked = code(src, "a.rs");
+        assert_eq!(masked.matches("eval(").count(), 1);
+        assert!(masked.contains("<'a>"));
+        assert!(masked.contains("r#\"     \"#"));
+    }
+
+    #[test]
+    fn test_go_ruby_php() {
+        assert_eq!(
+            code("x := `os.system(`  // os.system(", "a.go").trim_end(),
+            "x := `          `"
+        );
+        let ruby = "=begin\neval(\n=end\neval(a) # eval(\n";
+        assert_eq!(comments(ruby, "a.rb").matches("eval(").count(), 1);
+        let php = "<?php # unserialize(\n// unserialize(\nunserialize($x);";
+        assert_eq!(comments(php, "a.php").matches("unserialize(").count(), 1);
+    }
+
+    #[test]
+    fn test_shell_and_yaml() {
+        let sh = "echo $# # chmod 777\nchmod 777 dir";
+        assert_eq!(comments(sh, "a.sh"), "echo $#            \nchmod 777 dir");
+
+        let yaml = "run: echo it's ${{ github.event.issue.title }} # ${{ x }}\nname: 'a # b'\n";
+        let masked = comments(yaml, "ci.yml");
+        assert!(masked.contains("it's ${{ github.event.issue.title }}"));
+        assert!(!masked.contains("${{ x }}"));
+        assert!(masked.contains("'a # b'"));
+    }
+
+    #[test]
+    fn test_unterminated_string_stops_at_line_end() {
+        let masked = code("s = 'oops\neval(x)", "a.py");
+        assert!(masked.contains("eval(x)"));
+
+        // Even where strings can span lines
+        let masked = code("s = \"\"\"oops\neval(x)", "a.py");
+        assert!(masked.contains("eval(x)"));
+        let masked = code("const t = `oops ${a}\neval(x)", "a.js");
+        assert!(masked.contains("eval(x)"));
+        let masked = code("echo don't\neval x", "a.sh");
+        assert!(masked.contains("eval x"));
+    }
+
+    #[test]
+    fn test_python_f_string_fields_are_code() {
+        let masked = code(
+            "x = f\"{pickle.loads(data)}\"\ny = rf'{eval(x)!r:>10}'",
+            "a.py",
+        );
+        assert!(masked.contains("{pickle.loads(data)}"));
+        assert!(masked.contains("{eval(x)!r:>10}"));
+
+        // Literal text and `{{` escapes are still string contents
+        let masked = code("x = f\"eval(a) {{eval(b)}} {c}\"", "a.py");
+        assert!(!masked.contains("eval("));
+        assert!(masked.contains("{c}"));
+
+        // Not an f-string
+        let masked = code("x = \"{eval(a)}\"; elif\"{eval(b)}\"", "a.py");
+        assert!(!masked.contains("eval("));
+        let masked = code("x = f\"\"\"\n{eval(a)}\n\"\"\"", "a.py");
+        assert!(masked.contains("{eval(a)}"));
+    }
+
+    #[test]
+    fn test_javascript_regex_literals() {
+        let masked = code("const re = /`/;\neval(x)", "a.js");
+        assert!(masked.contains("eval(x)"));
+        let masked = code("const q = /\"/; eval(y)", "a.ts");
+        assert!(masked.contains("eval(y)"));
+        let masked = code(
+            "if (/[/'`]eval(/.test(s)) { return /a\\/'/g } eval(z)",
+            "a.js",
+        );
+        assert_eq!(masked.matches("eval(").count(), 1);
+        assert!(masked.contains("eval(z)"));
+
+        // Division and JSX closing tags aren't regexes
+        let masked = code("const r = a / 2; const s = 'eval(x)'; eval(z) / 2", "a.js");
+        assert_eq!(masked.matches("eval(").count(), 1);
+        let masked = code("const el = <b>x</b>; const s = 'eval(x)'; eval(z)", "a.tsx");
+        assert_eq!(masked.matches("eval(").count(), 1);
+    }
+}
@@ -0,0 +1,500 @@
+//! Friction and coverage report for `tool-gates stats`.
+//!
+//! Summarises the audit log: which commands ask most often in each project,
+//! which unknown programs keep coming up (candidates for new gate rules), how
+//! often each modern CLI hint fires, what decided each call (settings, gates,
+//! a permission mode, ...) and how long the hooks take.
+//!
+//! Without an audit log, asks and unknown programs fall back to the pending
+//! approval queue, which only remembers commands that were approved and ran.
+
+use crate::audit::AuditEntry;
+use crate::models::{CommandInfo, Decision};
+use crate::parser::extract_commands;
+use crate::pending::{PendingApproval, display_project_path};
+use crate::repo_policy::POLICY_FILE;
+use crate::router::check_single_command;
+use serde::Serialize;
+use std::collections::HashMap;
+
+/// A name and how often it was seen
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Count {
+    pub name: String,
+    pub count: usize,
+}
+
+/// Most frequent asks in one project
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ProjectAsks {
+    pub project: String,
+    pub total: usize,
+    pub top: Vec<Count>,
+}
+
+/// Hook latency, from entries that recorded it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Latency {
+    pub hook: String,
+    pub samples: usize,
+    pub median_us: u64,
+    pub p95_us: u64,
+}
+
+/// The full report
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Stats {
+    /// Audit log entries considered
+    pub entries: usize,
+    /// `audit log` or `pending queue`
+    pub asks_from: String,
+    pub projects: Vec<ProjectAsks>,
+    pub unknown_programs: Vec<Count>,
+    pub hints: Vec<Count>,
+    /// Decisions by what made them: settings, gates, acceptEdits, ...
+    pub sources: Vec<Count>,
+    pub latency: Vec<Latency>,
+}
+
+impl Stats {
+    /// Build the report, keeping the `top` entries of each ranked list
+    pub fn compute(entries: &[AuditEntry], pending: &[PendingApproval], top: usize) -> Self {
+        let mut keys = KeyCache::default();
+        let mut project_asks: HashMap<String, HashMap<String, usize>> = HashMap::new();
+        let mut unknown: HashMap<String, usize> = HashMap::new();
+        let mut hints: HashMap<String, usize> = HashMap::new();
+        let mut sources: HashMap<String, usize> = HashMap::new();
+        let mut elapsed: HashMap<String, Vec<u64>> = HashMap::new();
+
+        for entry in entries {
+            if let Some(us) = entry.elapsed_us {
+                elapsed.entry(entry.hook.clone()).or_default().push(us);
+            }
+            for hint in &entry.hints {
+                *hints.entry(hint.clone()).or_default() += 1;
+            }
+            if entry.hook == "PostToolUse"
+                || !matches!(entry.decision.as_str(), "allow" | "ask" | "deny")
+            {
+                continue;
+            }
+            *sources
+                .entry(source_category(entry.source.as_deref()))
+                .or_default() += 1;
+
+            if entry.hook != "PreToolUse" || entry.decision != "ask" {
+                continue;
+            }
+            let asks = project_asks
+                .entry(collapse_home(&entry.project))
+                .or_default();
+            match &entry.command {
+                Some(command) => {
+                    for segment in keys.asked(command) {
+                        *asks.entry(segment.key).or_default() += 1;
+                        if let Some(program) = segment.unknown {
+                            *unknown.entry(program).or_default() += 1;
+                        }
+                    }
+                }
+                None => *asks.entry(entry.tool.clone()).or_default() += 1,
+            }
+        }
+
+        let asks_from = if project_asks.is_empty() && !pending.is_empty() {
+            for entry in pending {
+                let asks = project_asks.entry(display_project_path(entry)).or_default();
+                let count = entry.count as usize;
+                for segment in keys.asked(&entry.command) {
+                    *asks.entry(segment.key).or_default() += count;
+                    if let Some(program) = segment.unknown {
+                        *unknown.entry(program).or_default() += count;
+                    }
+                }
+            }
+            "pending queue"
+        } else {
+            "audit log"
+        };
+
+        let mut projects: Vec<ProjectAsks> = project_asks
+            .into_iter()
+            .map(|(project, counts)| ProjectAsks {
+                project,
+                total: counts.values().sum(),
+                top: ranked(counts, top),
+            })
+            .collect();
+        projects.sort_by(|a, b| {
+            b.total
+                .cmp(&a.total)
+                .then_with(|| a.project.cmp(&b.project))
+        });
+
+        let mut latency: Vec<Latency> = elapsed
+            .into_iter()
+            .map(|(hook, mut samples)| {
+                samples.sort_unstable();
+                Latency {
+                    samples: samples.len(),
+                    median_us: percentile(&samples, 50),
+                    p95_us: percentile(&samples, 95),
+                    hook,
+                }
+            })
+            .collect();
+        latency.sort_by(|a, b| a.hook.cmp(&b.hook));
+
+        Self {
+            entries: entries.len(),
+            asks_from: asks_from.to_string(),
+            projects,
+            unknown_programs: ranked(unknown, top),
+            hints: ranked(hints, top),
+            sources: ranked(sources, usize::MAX),
+            latency,
+        }
+    }
+
+    /// Human-readable report
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        out.push_str(&format!("Most frequent asks (from {}):\n", self.asks_from));
+        if self.projects.is_empty() {
+            out.push_str("  none\n");
+        }
+        for project in &self.projects {
+            out.push_str(&format!("  {} ({} asks)\n", project.project, project.total));
+            for count in &project.top {
+                out.push_str(&format!("    {:>5}  {}\n", count.count, count.name));
+            }
+        }
+
+        let sections = [
+            (
+                "Unknown programs (candidates for gate rules)",
+                &self.unknown_programs,
+            ),
+            ("Hints shown", &self.hints),
+            ("Decided by", &self.sources),
+        ];
+        for (title, counts) in sections {
+            out.push_str(&format!("\n{title}:\n"));
+            if counts.is_empty() {
+                out.push_str("  none\n");
+            }
+            for count in counts {
+                out.push_str(&format!("  {:>5}  {}\n", count.count, count.name));
+            }
+        }
+
+        out.push_str("\nHook latency:\n");
+        if self.latency.is_empty() {
+            out.push_str("  no timings recorded\n");
+        }
+        for latency in &self.latency {
+            out.push_str(&format!(
+                "  {:<18} median {}  p95 {}  ({} calls)\n",
+                latency.hook,
+                format_us(latency.median_us),
+                format_us(latency.p95_us),
+                latency.samples
+            ));
+        }
+        out
+    }
+}
+
+/// Bucket a recorded decision source
+pub fn source_category(source: Option<&str>) -> String {
+    let Some(source) = source else {
+        return "unattributed".to_string();
+    };
+    if let Some(mode) = source.strip_prefix("permission mode ") {
+        return mode.to_string();
+    }
+    let category = if source.contains("settings.json") || source.contains("settings.local.json") {
+        "settings"
+    } else if source.ends_with(POLICY_FILE) {
+        "repo policy"
+    } else if source.contains("session-approvals.json") {
+        "session approval"
+    } else if source.starts_with("block_tools") {
+        "block_tools"
+    } else if source.starts_with("auto_approve_skills") {
+        "auto_approve_skills"
+    } else if source == "file_guards" {
+        "file guards"
+    } else if source == "security_reminders" {
+        "security reminders"
+    } else if source.ends_with(".toml") && !source.starts_with("rules/") {
+        "user rules"
+    } else {
+        "gates"
+    };
+    category.to_string()
+}
+
+/// `program subcommand`, or just the program when the first argument is a flag
+fn command_key(program: &str, args: &[String]) -> String {
+    match args.first() {
+        Some(sub) if !sub.starts_with('-') && !sub.contains('/') => format!("{program} {sub}"),
+        _ => program.to_string(),
+    }
+}
+
+/// A command segment the gates didn't allow
+#[derive(Clone)]
+struct AskedSegment {
+    /// `program subcommand`
+    key: String,
+    /// The program, when no gate knows it
+    unknown: Option<String>,
+}
+
+/// Memoized segments of each command that the gates ask about
+#[derive(Default)]
+struct KeyCache(HashMap<String, Vec<AskedSegment>>);
+
+impl KeyCache {
+    fn asked(&mut self, command: &str) -> Vec<AskedSegment> {
+        self.0
+            .entry(command.to_string())
+            .or_insert_with(|| {
+                let commands: Vec<CommandInfo> = extract_commands(command)
+                    .into_iter()
+                    .filter(|cmd| !cmd.program.is_empty())
+                    .collect();
+                let asked: Vec<AskedSegment> = commands
+                    .iter()
+                    .filter_map(|cmd| {
+                        let decision = check_single_command(cmd).decision;
+                        (decision != Decision::Allow).then(|| AskedSegment {
+                            key: command_key(&cmd.program, &cmd.args),
+                            unknown: (decision == Decision::Skip).then(|| cmd.program.clone()),
+                        })
+                    })
+                    .collect();
+                if !asked.is_empty() {
+                    return asked;
+                }
+                // Allowed by the gates but asked anyway (settings, mode): count every segment
+                commands
+                    .iter()
+                    .map(|cmd| AskedSegment {
+                        key: command_key(&cmd.program, &cmd.args),
+                        unknown: None,
+                    })
+                    .collect()
+            })
+            .clone()
+    }
+}
+
+fn ranked(counts: HashMap<String, usize>, top: usize) -> Vec<Count> {
+    let mut counts: Vec<Count> = counts
+        .into_iter()
+        .map(|(name, count)| Count { name, count })
+        .collect();
+    counts.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.name.cmp(&b.name)));
+    counts.truncate(top);
+    counts
+}
+
+/// Nearest-rank percentile of sorted samples
+fn percentile(sorted: &[u64], pct: usize) -> u64 {
+    if sorted.is_empty() {
+        return 0;
+    }
+    let rank = (sorted.len() * pct).div_ceil(100).max(1);
+    sorted[rank - 1]
+}
+
+fn format_us(us: u64) -> String {
+    if us >= 1000 {
+        format!("{:.1}ms", us as f64 / 1000.0)
+    } else {
+        format!("{us}µs")
+    }
+}
+
+fn collapse_home(path: &str) -> String {
+    if let Some(home) = dirs::home_dir() {
+        let home = home.to_string_lossy();
+        if path.starts_with(home.as_ref()) {
+            return path.replacen(home.as_ref(), "~", 1);
+        }
+    }
+    path.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tracking::CommandPart;
+
+    fn entry(command: &str, decision: &str, reason: &str, source: Option<&str>) -> AuditEntry {
+        let mut entry = AuditEntry::new("PreToolUse", "Bash", "s1", "/work/app");
+        entry.command = Some(command.to_string());
+        entry.decision = decision.to_string();
+        entry.reason = Some(reason.to_string());
+        entry.source = source.map(String::from);
+        entry
+    }
+
+    #[test]
+    fn test_asks_unknown_and_sources() {
+        let mut hinted = entry(
+            "cat README.md",
+            "allow",
+            "Read-only",
+            Some("rules/basics.toml"),
+        );
+        hinted.hints = vec!["cat -> bat".to_string()];
+        hinted.elapsed_us = Some(800);
+        let mut slow = entry(
+            "git push origin main",
+            "ask",
+            "git: push",
+            Some("rules/git.toml"),
+        );
+        slow.elapsed_us = Some(3000);
+        let entries = vec![
+            hinted,
+            slow,
+            entry("git push", "ask", "git: push", Some("rules/git.toml")),
+            entry(
+                "frobnicate --all",
+                "ask",
+                "Unknown command: frobnicate",
+                None,
+            ),
+            entry(
+                "frobnicate x && git push",
+                "ask",
+                "Approval needed:\n• Unknown command: frobnicate\n• git: push",
+                Some("rules/git.toml"),
+            ),
+            entry(
+                "rm -rf /",
+                "deny",
+                "Blocked",
+                Some("src/gates/filesystem.rs"),
+            ),
+            entry(
+                "git push",
+                "allow",
+                "",
+                Some("user (/home/u/.claude/settings.json)"),
+            ),
+            entry("touch a", "allow", "", Some("permission mode acceptEdits")),
+        ];
+
+        let stats = Stats::compute(&entries, &[], 10);
+        assert_eq!(stats.asks_from, "audit log");
+        assert_eq!(stats.projects[0].total, 5);
+        assert_eq!(
+            stats.projects[0].top[0],
+            Count {
+                name: "git push".to_string(),
+                count: 3
+            }
+        );
+        assert_eq!(
+            stats.unknown_programs[0],
+            Count {
+                name: "frobnicate".to_string(),
+                count: 2
+            }
+        );
+        assert_eq!(
+            stats.hints[0],
+            Count {
+                name: "cat -> bat".to_string(),
+                count: 1
+            }
+        );
+
+        let source = |name: &str| {
+            stats
+                .sources
+                .iter()
+                .find(|c| c.name == name)
+                .map(|c| c.count)
+        };
+        assert_eq!(source("gates"), Some(5));
+        assert_eq!(source("settings"), Some(1));
+        assert_eq!(source("acceptEdits"), Some(1));
+        assert_eq!(source("unattributed"), Some(1));
+
+        assert_eq!(stats.latency[0].samples, 2);
+        assert_eq!(stats.latency[0].median_us, 800);
+        assert_eq!(stats.latency[0].p95_us, 3000);
+    }
+
+    #[test]
+    fn test_falls_back_to_pending_queue() {
+        let mut pending = PendingApproval::new(
+            "mytool build".to_string(),
+            vec![],
+            vec![CommandPart::new(
+                "mytool",
+                &["build".to_string()],
+                Decision::Ask,
+                "Unknown command: mytool",
+            )],
+            "/work/app".to_string(),
+            "/work/app".to_string(),
+            "s1".to_string(),
+        );
+        pending.count = 3;
+
+        let stats = Stats::compute(&[], &[pending], 10);
+        assert_eq!(stats.asks_from, "pending queue");
+        assert_eq!(
+            stats.projects[0].top[0],
+            Count {
+                name: "mytool build".to_string(),
+                count: 3
+            }
+        );
+        assert_eq!(
+            stats.unknown_programs[0],
+            Count {
+                name: "mytool".to_string(),
+                count: 3
+            }
+        );
+    }
+
+    #[test]
+    fn test_source_category() {
+        assert_eq!(
+            source_category(Some("local (/p/.claude/settings.local.json)")),
+            "settings"
+        );
+        assert_eq!(source_category(Some("/p/.tool-gates.toml")), "repo policy");
+        assert_eq!(
+            source_category(Some("/home/u/.config/tool-gates/rules.toml")),
+            "user rules"
+        );
+        assert_eq!(
+            source_category(Some("custom handler x (rules/git.toml, src/gates/git.rs)")),
+            "gates"
+        );
+        assert_eq!(source_category(Some("permission mode plan")), "plan");
+        assert_eq!(
+            source_category(Some("block_tools (config.toml)")),
+            "block_tools"
+        );
+    }
+
+    #[test]
+    fn test_percentile() {
+        let samples: Vec<u64> = (1..=100).collect();
+        assert_eq!(percentile(&samples, 50), 50);
+        assert_eq!(percentile(&samples, 95), 95);
+        assert_eq!(percentile(&[7], 95), 7);
+        assert_eq!(percentile(&[], 50), 0);
+    }
+}
@@ -55,6 +55,21 @@ const MODERN_TOOLS: &[&str] = &[
     "glow", // markdown rendering in terminal
 ];
 
+/// Source of truth for which CLI tools are installed.
+///
+/// `ToolCache` is the default (disk cache with `which` fallback). Embedders
+/// and tests can supply a fixed set via `tool_gates::Engine::builder().tools(...)`.
+pub trait ToolAvailability: Send + Sync {
+    /// Whether `tool` is installed
+    fn is_available(&self, tool: &str) -> bool;
+}
+
+impl ToolAvailability for ToolCache {
+    fn is_available(&self, tool: &str) -> bool {
+        ToolCache::is_available(self, tool)
+    }
+}
+
 /// Cached tool availability data
 #[derive(Debug, Default, Serialize, Deserialize)]
 pub struct ToolCache {
@@ -0,0 +1,208 @@
+//! Decision trace collection for `tool-gates explain`.
+//!
+//! The router, gates, generated rules and settings matcher call [`record`] at
+//! each decision point. Recording is a no-op unless a [`collect`] call is active
+//! on the current thread, so the hook path pays only a thread-local flag check.
+//!
+//! Events carry the nesting depth at which they were recorded: a command
+//! re-checked by a wrapper or `bash -c` gate shows up one level deeper than
+//! the command that contained it.
+
+use crate::models::{CommandInfo, Decision, GateResult};
+use serde::Serialize;
+use std::cell::{Cell, RefCell};
+
+thread_local! {
+    static EVENTS: RefCell<Option<Vec<TraceEntry>>> = const { RefCell::new(None) };
+    static DEPTH: Cell<usize> = const { Cell::new(0) };
+}
+
+/// A single step in the decision trace
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum TraceEvent {
+    /// A raw-string pattern matched before parsing
+    RawPattern { pattern: String, reason: String },
+    /// A parsed command is about to go through the gates
+    Command {
+        raw: String,
+        program: String,
+        args: Vec<String>,
+    },
+    /// Result of a redirection check (`> file`, `/dev/tcp/...`)
+    Redirect {
+        decision: &'static str,
+        reason: Option<String>,
+    },
+    /// Result returned by one entry of `gates::GATES`
+    Gate {
+        gate: &'static str,
+        decision: &'static str,
+        reason: Option<String>,
+    },
+    /// A declarative rule from `rules/*.toml` matched
+    TomlRule {
+        file: &'static str,
+        program: &'static str,
+        section: &'static str,
+        index: usize,
+    },
+    /// A runtime rule file (`~/.config/tool-gates/rules`, `.tool-gates/rules`) matched
+    UserRule {
+        file: String,
+        program: String,
+        section: &'static str,
+        index: usize,
+    },
+    /// A settings.json permission rule matched
+    SettingsRule {
+        list: &'static str,
+        rule: String,
+        command: String,
+    },
+    /// A `.tool-gates.toml` rule matched
+    PolicyRule {
+        list: &'static str,
+        rule: String,
+        command: String,
+    },
+    /// A session-scoped or time-boxed approval matched
+    SessionApproval {
+        rule: String,
+        command: String,
+        lifetime: String,
+    },
+    /// mise task / package.json script expansion, or a permission mode changing the decision
+    Expansion {
+        source: &'static str,
+        detail: String,
+    },
+}
+
+/// A trace event with the nesting depth it was recorded at
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TraceEntry {
+    pub depth: usize,
+    #[serde(flatten)]
+    pub event: TraceEvent,
+}
+
+/// Run `f` with tracing enabled and return its result with the recorded trace.
+pub fn collect<T>(f: impl FnOnce() -> T) -> (T, Vec<TraceEntry>) {
+    let previous = EVENTS.with(|events| events.borrow_mut().replace(Vec::new()));
+    let depth = DEPTH.with(|d| d.replace(0));
+    let result = f();
+    let trace = EVENTS.with(|events| {
+        let mut events = events.borrow_mut();
+        let trace = events.take().unwrap_or_default();
+        *events = previous;
+        trace
+    });
+    DEPTH.with(|d| d.set(depth));
+    (result, trace)
+}
+
+/// Whether a [`collect`] call is active on this thread
+pub fn is_active() -> bool {
+    EVENTS.with(|events| events.borrow().is_some())
+}
+
+/// Record an event. The closure only runs while tracing is active.
+pub fn record(event: impl FnOnce() -> TraceEvent) {
+    EVENTS.with(|events| {
+        if let Some(events) = events.borrow_mut().as_mut() {
+            events.push(TraceEntry {
+                depth: DEPTH.with(Cell::get),
+                event: event(),
+            });
+        }
+    });
+}
+
+/// Record a matched TOML rule (called from generated code).
+pub fn toml_rule(file: &'static str, program: &'static str, section: &'static str, index: usize) {
+    record(|| TraceEvent::TomlRule {
+        file,
+        program,
+        section,
+        index,
+    });
+}
+
+/// Record a command and nest everything recorded until the guard drops.
+pub fn enter_command(cmd: &CommandInfo) -> DepthGuard {
+    record(|| TraceEvent::Command {
+        raw: cmd.raw.clone(),
+        program: cmd.program.clone(),
+        args: cmd.args.clone(),
+    });
+    DEPTH.with(|d| d.set(d.get() + 1));
+    DepthGuard
+}
+
+/// Restores the trace depth when a command check finishes
+pub struct DepthGuard;
+
+impl Drop for DepthGuard {
+    fn drop(&mut self) {
+        DEPTH.with(|d| d.set(d.get().saturating_sub(1)));
+    }
+}
+
+/// Lowercase label for a decision ("skip", "allow", "ask", "block")
+pub fn decision_label(decision: Decision) -> &'static str {
+    match decision {
+        Decision::Skip => "skip",
+        Decision::Allow => "allow",
+        Decision::Ask => "ask",
+        Decision::Block => "block",
+    }
+}
+
+/// Record the result of a gate.
+pub fn gate(gate: &'static str, result: &GateResult) {
+    record(|| TraceEvent::Gate {
+        gate,
+        decision: decision_label(result.decision),
+        reason: result.reason.clone(),
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_is_noop_when_inactive() {
+        assert!(!is_active());
+        record(|| panic!("closure must not run"));
+    }
+
+    #[test]
+    fn test_collect_captures_depth() {
+        let cmd = CommandInfo {
+            raw: "ls".to_string(),
+            program: "ls".to_string(),
+            ..Default::default()
+        };
+        let ((), trace) = collect(|| {
+            let _guard = enter_command(&cmd);
+            gate("basics", &GateResult::allow());
+        });
+        assert_eq!(trace.len(), 2);
+        assert_eq!(trace[0].depth, 0);
+        assert_eq!(trace[1].depth, 1);
+        assert!(!is_active());
+    }
+
+    #[test]
+    fn test_nested_collect_restores_outer() {
+        let ((), outer) = collect(|| {
+            toml_rule("git", "git", "allow", 0);
+            let ((), inner) = collect(|| toml_rule("git", "git", "ask", 1));
+            assert_eq!(inner.len(), 1);
+            toml_rule("git", "git", "block", 2);
+        });
+        assert_eq!(outer.len(), 2);
+    }
+}
@@ -2,6 +2,11 @@
 //!
 //! Tracks commands that return "ask" so PostToolUse can detect when they
 //! complete successfully and add them to the pending approval queue.
+//!
+//! An approved command can run for longer than the TTL, so expiry alone doesn't
+//! mean it was declined. An expired entry goes to the declined queue once its
+//! session has moved on (another Bash call asked or finished after the entry
+//! expired), or after [`MAX_TRACKING_SECS`] when the session has ended.
 
 use chrono::{DateTime, Duration, Utc};
 use fs2::FileExt;
@@ -11,12 +16,16 @@ use std::fs::{self, OpenOptions};
 use std::io::{Read, Seek, Write};
 use std::path::PathBuf;
 
+use crate::declined::{DeclineKind, DeclinedCommand, record_declined};
 use crate::models::Decision;
 
 /// Default TTL for tracked commands (15 minutes)
 /// Long enough to survive short breaks while still cleaning up stale entries.
 const DEFAULT_TTL_SECS: i64 = 900;
 
+/// Expired entries whose session never reported again are declined after this long (1 day)
+pub const MAX_TRACKING_SECS: i64 = 24 * 60 * 60;
+
 /// Information about a command part (for breakdown display)
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CommandPart {
@@ -48,6 +57,14 @@ impl CommandPart {
         self.expansion = Some(Box::new(expansion));
         self
     }
+
+    /// Program and args as one command line (for pattern matching)
+    pub fn command_line(&self) -> String {
+        std::iter::once(self.program.as_str())
+            .chain(self.args.iter().map(String::as_str))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
 }
 
 /// A tracked command awaiting PostToolUse confirmation
@@ -96,6 +113,9 @@ impl TrackedCommand {
 /// Tracking store backed by JSON file
 #[derive(Debug, Default, Serialize, Deserialize)]
 pub struct TrackingStore {
+    /// Last Bash hook activity per session
+    #[serde(default, rename = "_sessions")]
+    pub sessions: HashMap<String, DateTime<Utc>>,
     #[serde(flatten)]
     pub entries: HashMap<String, TrackedCommand>,
 }
@@ -141,12 +161,12 @@ impl TrackingStore {
             serde_json::from_str(&contents).unwrap_or_default()
         };
 
-        // Clean expired entries
-        store.clean_expired();
-
-        // Execute the modification function
+        // Run the modification first, so a PostToolUse finds its entry however old it is
         let result = f(&mut store);
 
+        // Entries left behind by a session that moved on never reached PostToolUse
+        let declined = store.take_declined(Utc::now());
+
         // Write back - truncate and seek to start
         file.set_len(0)?;
         file.seek(std::io::SeekFrom::Start(0))?;
@@ -158,6 +178,14 @@ impl TrackingStore {
         #[allow(clippy::incompatible_msrv)] // fs2 crate method, not std
         file.unlock()?;
 
+        let declined = declined
+            .into_iter()
+            .map(|tracked| DeclinedCommand::from_tracked(tracked, DeclineKind::Declined))
+            .collect();
+        if let Err(e) = record_declined(declined) {
+            eprintln!("Warning: Failed to save declined commands: {e}");
+        }
+
         Ok(result)
     }
 
@@ -183,9 +211,35 @@ impl TrackingStore {
         self.entries.contains_key(tool_use_id)
     }
 
-    /// Clean expired entries
-    pub fn clean_expired(&mut self) {
-        self.entries.retain(|_, v| !v.is_expired());
+    /// Note Bash hook activity in a session
+    pub fn touch(&mut self, session_id: &str) {
+        if !session_id.is_empty() {
+            self.sessions.insert(session_id.to_string(), Utc::now());
+        }
+    }
+
+    /// Remove and return entries that were declined: expired, and either their
+    /// session has had activity since, or they're older than [`MAX_TRACKING_SECS`].
+    pub fn take_declined(&mut self, now: DateTime<Utc>) -> Vec<TrackedCommand> {
+        let cutoff = now - Duration::seconds(MAX_TRACKING_SECS);
+        let declined_ids: Vec<String> = self
+            .entries
+            .iter()
+            .filter(|(_, v)| {
+                v.expires < now
+                    && (v.timestamp < cutoff
+                        || self
+                            .sessions
+                            .get(&v.session_id)
+                            .is_some_and(|last| *last > v.expires))
+            })
+            .map(|(k, _)| k.clone())
+            .collect();
+        self.sessions.retain(|_, last| *last > cutoff);
+        declined_ids
+            .iter()
+            .filter_map(|id| self.entries.remove(id))
+            .collect()
     }
 }
 
@@ -210,6 +264,7 @@ pub fn track_ask_command(
 
     let tool_use_id = tool_use_id.to_string();
     if let Err(e) = TrackingStore::with_exclusive_lock(|store| {
+        store.touch(session_id);
         store.track(&tool_use_id, tracked);
     }) {
         eprintln!("Warning: Failed to save tracking file: {e}");
@@ -229,10 +284,15 @@ pub fn peek_tracked_command(tool_use_id: &str) -> Option<TrackedCommand> {
     }
 }
 
-/// Get and remove a tracked command (called from PostToolUse after successful append)
-pub fn take_tracked_command(tool_use_id: &str) -> Option<TrackedCommand> {
+/// Get and remove a tracked command (called from every Bash PostToolUse).
+///
+/// Also notes the session's activity, which lets its expired entries be declined.
+pub fn take_tracked_command(tool_use_id: &str, session_id: &str) -> Option<TrackedCommand> {
     let tool_use_id = tool_use_id.to_string();
-    match TrackingStore::with_exclusive_lock(|store| store.take(&tool_use_id)) {
+    match TrackingStore::with_exclusive_lock(|store| {
+        store.touch(session_id);
+        store.take(&tool_use_id)
+    }) {
         Ok(result) => result,
         Err(e) => {
             eprintln!("Warning: Failed to access tracking file: {e}");
@@ -294,6 +354,69 @@ mod tests {
         });
     }
 
+    fn expired(command: &str, session_id: &str, age_secs: i64) -> TrackedCommand {
+        let mut tracked = TrackedCommand::new(
+            command.to_string(),
+            vec![],
+            vec![],
+            "/tmp".to_string(),
+            "/tmp".to_string(),
+            session_id.to_string(),
+        );
+        tracked.timestamp = Utc::now() - Duration::seconds(age_secs);
+        tracked.expires = tracked.timestamp + Duration::seconds(DEFAULT_TTL_SECS);
+        tracked
+    }
+
+    #[test]
+    fn test_declined_once_session_moves_on() {
+        let mut store = TrackingStore::default();
+        store.track("toolu_fresh", expired("npm install", "session1", 0));
+        store.track("toolu_stale", expired("git push --force", "session1", 1000));
+
+        // Expired, but the session hasn't done anything since: may still be running
+        assert!(store.take_declined(Utc::now()).is_empty());
+        assert!(store.contains("toolu_stale"));
+
+        store.touch("session1");
+        let declined = store.take_declined(Utc::now());
+        assert_eq!(declined.len(), 1);
+        assert_eq!(declined[0].command, "git push --force");
+        assert!(store.contains("toolu_fresh"));
+        assert!(!store.contains("toolu_stale"));
+    }
+
+    #[test]
+    fn test_long_running_command_not_declined() {
+        let mut store = TrackingStore::default();
+        store.track("toolu_build", expired("make release", "session1", 3600));
+        // Activity in another session says nothing about this one
+        store.touch("session2");
+        assert!(store.take_declined(Utc::now()).is_empty());
+        assert!(store.take("toolu_build").is_some());
+    }
+
+    #[test]
+    fn test_declined_after_max_age() {
+        let mut store = TrackingStore::default();
+        store.track(
+            "toolu_old",
+            expired("npm install", "ended", MAX_TRACKING_SECS + 1),
+        );
+        assert_eq!(store.take_declined(Utc::now()).len(), 1);
+    }
+
+    #[test]
+    fn test_store_roundtrip_keeps_sessions() {
+        let mut store = TrackingStore::default();
+        store.touch("session1");
+        store.track("toolu_1", expired("npm install", "session1", 0));
+        let json = serde_json::to_string(&store).unwrap();
+        let store: TrackingStore = serde_json::from_str(&json).unwrap();
+        assert!(store.sessions.contains_key("session1"));
+        assert_eq!(store.entries.len(), 1);
+    }
+
     #[test]
     fn test_command_part_creation() {
         let part = CommandPart::new(
@@ -1,11 +1,14 @@
 //! Application state and event handling for the review TUI.
 
+use crate::blast_radius::{BlastRadius, PreviewMatch};
+use crate::declined::{DeclineKind, read_declined, remove_declined_many};
 use crate::models::CommandInfo;
-use crate::patterns::suggest_patterns;
+use crate::patterns::{MergedSuggestion, merge_suggestions, suggest_patterns};
 use crate::pending::{
     PendingApproval, ProjectInfo, category_weight, derive_projects, read_pending,
     remove_pending_many,
 };
+use crate::session_approvals::{self, SessionApproval};
 use crate::settings_writer::{RuleType, Scope, add_rule, add_rule_to_project};
 use crate::tracking::CommandPart;
 use crossterm::{
@@ -23,6 +26,49 @@ use std::panic::AssertUnwindSafe;
 
 use super::ui;
 
+/// Which queue the TUI is reviewing
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum View {
+    /// Commands that were approved at the prompt and ran
+    Pending,
+    /// Commands that were asked about and never ran
+    Declined,
+}
+
+impl View {
+    fn load(self) -> Vec<PendingApproval> {
+        match self {
+            Self::Pending => read_pending(None),
+            Self::Declined => read_declined(None)
+                .into_iter()
+                .filter(|d| d.kind == DeclineKind::Declined)
+                .map(|d| d.entry)
+                .collect(),
+        }
+    }
+
+    fn remove(self, ids: &[String]) -> io::Result<usize> {
+        match self {
+            Self::Pending => remove_pending_many(ids),
+            Self::Declined => remove_declined_many(ids),
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Pending => "pending",
+            Self::Declined => "declined",
+        }
+    }
+
+    fn other(self) -> Self {
+        match self {
+            Self::Pending => Self::Declined,
+            Self::Declined => Self::Pending,
+        }
+    }
+}
+
 /// Which panel has keyboard focus
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Panel {
@@ -36,9 +82,19 @@ pub enum Panel {
 pub enum DetailRow {
     Segments,
     Pattern,
+    /// Pattern covering every visible entry of the same shape (only shown when there is one)
+    Merged,
     Scope,
 }
 
+/// Where approvals from the detail panel are written
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ApprovalScope {
+    Settings(Scope),
+    /// Temporary allow for the command's session (`session-approvals.json`)
+    Session,
+}
+
 /// Status message type
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum MessageKind {
@@ -58,6 +114,7 @@ pub struct LayoutAreas {
 /// Application state
 pub struct App {
     // Data
+    pub view: View,
     pub entries: Vec<PendingApproval>,
     pub projects: Vec<ProjectInfo>,
 
@@ -68,14 +125,18 @@ pub struct App {
     // Command list
     /// Indices into entries for the currently visible (filtered) commands
     pub visible: Vec<usize>,
+    /// Merged pattern suggestions for the visible entries
+    pub merged: Vec<MergedSuggestion>,
     pub command_cursor: usize,
     pub multi_selected: HashSet<usize>,
 
     // Detail panel
     pub selected_segment: usize,
     pub selected_pattern: usize,
-    pub scope: Scope,
+    pub scope: ApprovalScope,
     pub detail_row: DetailRow,
+    /// Blast radius of the focused pattern, recomputed when the pattern changes
+    pub preview: Option<BlastRadius>,
 
     // Scroll state
     pub sidebar_state: ListState,
@@ -89,8 +150,8 @@ pub struct App {
 }
 
 impl App {
-    pub fn new(show_all: bool) -> Self {
-        let entries = read_pending(None);
+    pub fn new(show_all: bool, view: View) -> Self {
+        let entries = view.load();
         let projects = derive_projects(&entries);
 
         // Auto-detect current project
@@ -102,24 +163,27 @@ impl App {
 
         // Default scope: project if viewing a specific project, user if "All"
         let scope = if project_cursor < projects.len() {
-            Scope::Project
+            ApprovalScope::Settings(Scope::Project)
         } else {
-            Scope::User
+            ApprovalScope::Settings(Scope::User)
         };
 
         let sidebar_state = ListState::default();
 
         let mut app = Self {
+            view,
             entries,
             projects,
             project_cursor,
             visible: Vec::new(),
+            merged: Vec::new(),
             command_cursor: 0,
             multi_selected: HashSet::new(),
             selected_segment: 0,
             selected_pattern: 0,
             scope,
             detail_row: DetailRow::Pattern,
+            preview: None,
             sidebar_state,
             command_state: ListState::default(),
             panel: Panel::CommandList,
@@ -161,6 +225,9 @@ impl App {
             wa.cmp(&wb).then(eb.count.cmp(&ea.count))
         });
 
+        let visible_entries: Vec<PendingApproval> =
+            indices.iter().map(|&i| self.entries[i].clone()).collect();
+        self.merged = merge_suggestions(&visible_entries);
         self.visible = indices;
         self.command_cursor = 0;
         self.command_state.select(if self.visible.is_empty() {
@@ -188,6 +255,49 @@ impl App {
             .and_then(|&idx| self.entries.get(idx))
     }
 
+    /// Merged suggestion covering the current entry, if any
+    pub fn current_merged(&self) -> Option<&MergedSuggestion> {
+        let entry = self.current_entry()?;
+        self.merged.iter().find(|m| m.entry_ids.contains(&entry.id))
+    }
+
+    /// The pattern Enter would save: the merged suggestion when focused, else the selected pattern
+    pub fn focused_pattern(&self) -> Option<String> {
+        if self.detail_row == DetailRow::Merged {
+            if let Some(merged) = self.current_merged() {
+                return Some(merged.pattern.clone());
+            }
+        }
+        self.current_patterns().get(self.selected_pattern).cloned()
+    }
+
+    /// Recompute the blast-radius preview if the focused pattern changed
+    pub fn update_preview(&mut self) {
+        let pattern = self.focused_pattern();
+        if self.preview.as_ref().map(|p| &p.pattern) == pattern.as_ref() {
+            return;
+        }
+        self.preview = pattern.map(|p| BlastRadius::load(&p));
+    }
+
+    /// Preview matches other than the current command itself
+    pub fn preview_matches(&self) -> Vec<&PreviewMatch> {
+        let (Some(preview), Some(entry)) = (&self.preview, self.current_entry()) else {
+            return Vec::new();
+        };
+        preview
+            .matches
+            .iter()
+            .filter(|m| {
+                m.command != entry.command
+                    && !entry
+                        .breakdown
+                        .iter()
+                        .any(|p| p.command_line() == m.command)
+            })
+            .collect()
+    }
+
     pub fn is_all_view(&self) -> bool {
         self.project_cursor >= self.projects.len()
     }
@@ -252,7 +362,20 @@ impl App {
 
     /// Get the target file path for display
     pub fn target_path(&self) -> String {
-        match self.scope {
+        let scope = match self.scope {
+            ApprovalScope::Settings(scope) => scope,
+            ApprovalScope::Session => {
+                let path = crate::session_approvals::approvals_path();
+                return match dirs::home_dir() {
+                    Some(home) => {
+                        path.to_string_lossy()
+                            .replacen(&home.to_string_lossy().to_string(), "~", 1)
+                    }
+                    None => path.to_string_lossy().to_string(),
+                };
+            }
+        };
+        match scope {
             Scope::User => {
                 let path = Scope::User.path();
                 if let Some(home) = dirs::home_dir() {
@@ -264,7 +387,7 @@ impl App {
             }
             Scope::Project | Scope::Local => {
                 if let Some(entry) = self.current_entry() {
-                    let filename = if self.scope == Scope::Project {
+                    let filename = if scope == Scope::Project {
                         "settings.json"
                     } else {
                         "settings.local.json"
@@ -350,9 +473,11 @@ impl App {
             }
             Panel::Detail => {
                 let has_segments = self.current_entry().is_some_and(|e| e.breakdown.len() > 1);
+                let has_merged = self.current_merged().is_some();
                 self.detail_row = match self.detail_row {
                     DetailRow::Segments => DetailRow::Pattern,
-                    DetailRow::Pattern => DetailRow::Scope,
+                    DetailRow::Pattern if has_merged => DetailRow::Merged,
+                    DetailRow::Pattern | DetailRow::Merged => DetailRow::Scope,
                     DetailRow::Scope => {
                         if has_segments {
                             DetailRow::Segments
@@ -390,6 +515,7 @@ impl App {
             }
             Panel::Detail => {
                 let has_segments = self.current_entry().is_some_and(|e| e.breakdown.len() > 1);
+                let has_merged = self.current_merged().is_some();
                 self.detail_row = match self.detail_row {
                     DetailRow::Segments => DetailRow::Scope,
                     DetailRow::Pattern => {
@@ -399,6 +525,8 @@ impl App {
                             DetailRow::Scope
                         }
                     }
+                    DetailRow::Merged => DetailRow::Pattern,
+                    DetailRow::Scope if has_merged => DetailRow::Merged,
                     DetailRow::Scope => DetailRow::Pattern,
                 };
             }
@@ -431,11 +559,15 @@ impl App {
                     };
                 }
             }
+            DetailRow::Merged => {}
             DetailRow::Scope => {
                 self.scope = match self.scope {
-                    Scope::User => Scope::Local,
-                    Scope::Project => Scope::User,
-                    Scope::Local => Scope::Project,
+                    ApprovalScope::Settings(Scope::User) => ApprovalScope::Session,
+                    ApprovalScope::Settings(Scope::Project) => ApprovalScope::Settings(Scope::User),
+                    ApprovalScope::Settings(Scope::Local) => {
+                        ApprovalScope::Settings(Scope::Project)
+                    }
+                    ApprovalScope::Session => ApprovalScope::Settings(Scope::Local),
                 };
             }
         }
@@ -459,11 +591,15 @@ impl App {
                     self.selected_pattern = (self.selected_pattern + 1) % count;
                 }
             }
+            DetailRow::Merged => {}
             DetailRow::Scope => {
                 self.scope = match self.scope {
-                    Scope::User => Scope::Project,
-                    Scope::Project => Scope::Local,
-                    Scope::Local => Scope::User,
+                    ApprovalScope::Settings(Scope::User) => ApprovalScope::Settings(Scope::Project),
+                    ApprovalScope::Settings(Scope::Project) => {
+                        ApprovalScope::Settings(Scope::Local)
+                    }
+                    ApprovalScope::Settings(Scope::Local) => ApprovalScope::Session,
+                    ApprovalScope::Session => ApprovalScope::Settings(Scope::User),
                 };
             }
         }
@@ -483,9 +619,9 @@ impl App {
     fn on_project_changed(&mut self) {
         // Update default scope to match view
         self.scope = if self.is_all_view() {
-            Scope::User
+            ApprovalScope::Settings(Scope::User)
         } else {
-            Scope::Project
+            ApprovalScope::Settings(Scope::Project)
         };
         self.update_visible();
     }
@@ -493,7 +629,9 @@ impl App {
     // === Actions ===
 
     pub fn approve(&mut self) {
-        if self.multi_selected.is_empty() {
+        if self.panel == Panel::Detail && self.detail_row == DetailRow::Merged {
+            self.approve_merged();
+        } else if self.multi_selected.is_empty() {
             self.approve_single(RuleType::Allow);
         } else {
             self.approve_multi();
@@ -529,7 +667,7 @@ impl App {
         }
 
         let count = ids.len();
-        if let Err(e) = remove_pending_many(&ids) {
+        if let Err(e) = self.view.remove(&ids) {
             self.message = Some((
                 format!("Failed to remove from queue: {e}"),
                 MessageKind::Error,
@@ -562,7 +700,7 @@ impl App {
             return;
         }
 
-        if let Err(e) = remove_pending_many(std::slice::from_ref(&entry.id)) {
+        if let Err(e) = self.view.remove(std::slice::from_ref(&entry.id)) {
             self.message = Some((
                 format!("Failed to remove from queue: {e}"),
                 MessageKind::Error,
@@ -613,7 +751,7 @@ impl App {
         }
 
         if !approved_ids.is_empty() {
-            if let Err(e) = remove_pending_many(&approved_ids) {
+            if let Err(e) = self.view.remove(&approved_ids) {
                 self.message = Some((
                     format!("Failed to remove from queue: {e}"),
                     MessageKind::Error,
@@ -647,24 +785,96 @@ impl App {
         self.refresh();
     }
 
+    /// Approve the current entry's merged suggestion for every entry it covers
+    fn approve_merged(&mut self) {
+        let Some(merged) = self.current_merged().cloned() else {
+            return;
+        };
+        let entries: Vec<PendingApproval> = self
+            .entries
+            .iter()
+            .filter(|e| merged.entry_ids.contains(&e.id))
+            .cloned()
+            .collect();
+
+        let mut approved_ids = Vec::new();
+        let mut errors = Vec::new();
+        for entry in &entries {
+            match self.write_rule(&merged.pattern, entry, RuleType::Allow) {
+                Ok(()) => approved_ids.push(entry.id.clone()),
+                Err(msg) => errors.push(msg),
+            }
+        }
+
+        if let Err(e) = self.view.remove(&approved_ids) {
+            self.message = Some((
+                format!("Failed to remove from queue: {e}"),
+                MessageKind::Error,
+            ));
+            self.refresh();
+            return;
+        }
+
+        self.message = Some(match errors.first() {
+            None => (
+                format!(
+                    "Approved {} for {} command(s) -> {}",
+                    merged.pattern,
+                    approved_ids.len(),
+                    scope_label(self.scope)
+                ),
+                MessageKind::Success,
+            ),
+            Some(first) => (
+                format!(
+                    "Approved {} but {} failed: {}",
+                    approved_ids.len(),
+                    errors.len(),
+                    first
+                ),
+                MessageKind::Error,
+            ),
+        });
+        self.refresh();
+    }
+
     fn write_rule(
         &self,
         pattern: &str,
         entry: &PendingApproval,
         rule_type: RuleType,
     ) -> Result<(), String> {
+        let cwd = if entry.cwd.is_empty() {
+            &entry.project_id
+        } else {
+            &entry.cwd
+        };
         match self.scope {
-            Scope::User => {
+            ApprovalScope::Settings(Scope::User) => {
                 add_rule(Scope::User, pattern, rule_type).map_err(|e| format!("User: {e}"))
             }
-            scope @ (Scope::Project | Scope::Local) => {
-                let cwd = if entry.cwd.is_empty() {
-                    &entry.project_id
-                } else {
-                    &entry.cwd
-                };
-                add_rule_to_project(scope, cwd, pattern, rule_type)
-                    .map_err(|e| format!("{}: {e}", cwd))
+            ApprovalScope::Settings(scope) => add_rule_to_project(scope, cwd, pattern, rule_type)
+                .map_err(|e| format!("{}: {e}", cwd)),
+            ApprovalScope::Session => {
+                if rule_type != RuleType::Allow {
+                    return Err(
+                        "Session scope can only approve; pick a settings scope to deny".into(),
+                    );
+                }
+                if entry.session_id.is_empty() {
+                    return Err(format!(
+                        "{}: no session recorded for this command",
+                        entry.command
+                    ));
+                }
+                session_approvals::add(SessionApproval {
+                    pattern: pattern.to_string(),
+                    project: cwd.clone(),
+                    session_id: Some(entry.session_id.clone()),
+                    expires_at: None,
+                    created_at: chrono::Utc::now(),
+                })
+                .map_err(|e| format!("Session: {e}"))
             }
         }
     }
@@ -676,7 +886,8 @@ impl App {
             None
         };
 
-        self.entries = read_pending(None);
+        self.entries = self.view.load();
+        self.preview = None;
         self.projects = derive_projects(&self.entries);
 
         // Try to keep the same project selected
@@ -691,6 +902,28 @@ impl App {
         self.update_visible();
     }
 
+    /// Switch between the pending and declined queues.
+    /// Returns false (and stays put) when the other queue is empty.
+    pub fn toggle_view(&mut self) -> bool {
+        let other = self.view.other();
+        if other.load().is_empty() {
+            self.message = Some((format!("No {} commands", other.label()), MessageKind::Info));
+            return false;
+        }
+        self.view = other;
+        self.multi_selected.clear();
+        self.command_cursor = 0;
+        self.detail_row = DetailRow::Pattern;
+        self.refresh();
+        if self.view == View::Declined {
+            self.message = Some((
+                "Commands asked about but never run. D adds a deny rule, d dismisses".to_string(),
+                MessageKind::Info,
+            ));
+        }
+        true
+    }
+
     /// Sync sidebar ListState, accounting for the separator row
     fn sync_sidebar_state(&mut self) {
         let list_index = if self.project_cursor >= self.projects.len() && !self.projects.is_empty()
@@ -755,15 +988,18 @@ fn segment_patterns(part: &CommandPart) -> Vec<String> {
         },
         program: part.program.clone(),
         args: part.args.clone(),
+        redirects: Vec::new(),
+        flow: Default::default(),
     };
     suggest_patterns(&cmd)
 }
 
-fn scope_label(scope: Scope) -> &'static str {
+fn scope_label(scope: ApprovalScope) -> &'static str {
     match scope {
-        Scope::User => "global",
-        Scope::Project => "project",
-        Scope::Local => "local",
+        ApprovalScope::Settings(Scope::User) => "global",
+        ApprovalScope::Settings(Scope::Project) => "project",
+        ApprovalScope::Settings(Scope::Local) => "local",
+        ApprovalScope::Session => "this session",
     }
 }
 
@@ -812,11 +1048,19 @@ pub fn extract_operators(raw: &str) -> Vec<&str> {
 // === Entry point ===
 
 /// Run the review TUI
-pub fn run_review(show_all: bool) -> io::Result<()> {
-    let mut app = App::new(show_all);
+pub fn run_review(show_all: bool, declined: bool) -> io::Result<()> {
+    let view = if declined {
+        View::Declined
+    } else {
+        View::Pending
+    };
+    let mut app = App::new(show_all, view);
 
     if app.entries.is_empty() {
-        eprintln!("No pending approvals to review.");
+        match view {
+            View::Pending => eprintln!("No pending approvals to review."),
+            View::Declined => eprintln!("No declined commands to review."),
+        }
         return Ok(());
     }
 
@@ -867,7 +1111,7 @@ where
     loop {
         terminal.draw(|f| ui::draw(f, app))?;
 
-        if app.visible.is_empty() && app.entries.is_empty() {
+        if app.visible.is_empty() && app.entries.is_empty() && !app.toggle_view() {
             app.should_quit = true;
         }
 
@@ -902,6 +1146,10 @@ where
                         KeyCode::Left | KeyCode::Char('h') => app.nav_left(),
                         KeyCode::Right | KeyCode::Char('l') => app.nav_right(),
 
+                        KeyCode::Char('v') => {
+                            app.toggle_view();
+                        }
+
                         // Multi-select
                         KeyCode::Char(' ') if app.panel == Panel::CommandList => {
                             app.toggle_select();
@@ -927,10 +1175,8 @@ where
                         _ => {}
                     }
                 }
-                Event::Mouse(mouse) => {
-                    if mouse.kind == MouseEventKind::Down(MouseButton::Left) {
-                        app.handle_mouse_click(mouse.column, mouse.row);
-                    }
+                Event::Mouse(mouse) if mouse.kind == MouseEventKind::Down(MouseButton::Left) => {
+                    app.handle_mouse_click(mouse.column, mouse.row);
                 }
                 _ => {}
             }
@@ -1,8 +1,12 @@
 //! UI rendering for the review TUI.
 
 use super::app::{
-    App, DetailRow, LayoutAreas, MessageKind, Panel, describe_pattern, extract_operators,
+    App, ApprovalScope, DetailRow, LayoutAreas, MessageKind, Panel, View, describe_pattern,
+    extract_operators,
 };
+use crate::blast_radius::PreviewMatch;
+use crate::models::Decision;
+use crate::patterns::MergedSuggestion;
 use crate::pending::display_project_path;
 use crate::settings_writer::Scope;
 use ratatui::{
@@ -39,6 +43,8 @@ pub fn draw(f: &mut Frame, app: &mut App) {
         detail: chunks[2],
     };
 
+    app.update_preview();
+
     draw_header(f, app, chunks[0]);
     draw_sidebar(f, app, main_chunks[0]);
     draw_command_list(f, app, main_chunks[1]);
@@ -62,7 +68,8 @@ fn draw_header(f: &mut Frame, app: &App, area: Rect) {
     };
 
     let text = format!(
-        " tool-gates review --{visible} commands ({project_name}) -- {total} total across {projects} project(s)"
+        " tool-gates review --{visible} {} commands ({project_name}) -- {total} total across {projects} project(s)",
+        app.view.label()
     );
 
     let header = Paragraph::new(Line::from(Span::styled(
@@ -327,6 +334,13 @@ fn draw_detail(f: &mut Frame, app: &App, area: Rect) {
         constraints.push(Constraint::Length(1)); // Reason
     }
     constraints.push(Constraint::Length(1)); // Pattern
+    if app.current_merged().is_some() {
+        constraints.push(Constraint::Length(1)); // Merged suggestion
+    }
+    let preview = app.preview_matches();
+    if !preview.is_empty() {
+        constraints.push(Constraint::Length(1)); // Blast radius
+    }
     constraints.push(Constraint::Length(2)); // Scope + target
     if has_tip {
         constraints.push(Constraint::Length(1)); // Tip
@@ -361,6 +375,18 @@ fn draw_detail(f: &mut Frame, app: &App, area: Rect) {
     draw_pattern_row(f, app, rows[row_idx]);
     row_idx += 1;
 
+    // Merged suggestion
+    if let Some(merged) = app.current_merged() {
+        draw_merged_row(f, app, merged, rows[row_idx]);
+        row_idx += 1;
+    }
+
+    // Blast radius
+    if !preview.is_empty() {
+        draw_preview_row(f, &preview, rows[row_idx]);
+        row_idx += 1;
+    }
+
     // Scope + target
     draw_scope_row(f, app, rows[row_idx]);
     row_idx += 1;
@@ -377,6 +403,10 @@ fn draw_detail(f: &mut Frame, app: &App, area: Rect) {
 
     // Actions
     draw_actions(f, app, rows[row_idx]);
+    row_idx += 1;
+
+    // Remaining space lists what else the pattern allows
+    draw_preview_list(f, &preview, rows[row_idx]);
 }
 
 fn draw_segments(f: &mut Frame, app: &App, entry: &crate::pending::PendingApproval, area: Rect) {
@@ -479,13 +509,112 @@ fn draw_pattern_row(f: &mut Frame, app: &App, area: Rect) {
     f.render_widget(Paragraph::new(line), area);
 }
 
+fn draw_merged_row(f: &mut Frame, app: &App, merged: &MergedSuggestion, area: Rect) {
+    let focused = app.panel == Panel::Detail && app.detail_row == DetailRow::Merged;
+
+    let label_style = if focused {
+        Style::default()
+            .fg(Color::Cyan)
+            .add_modifier(Modifier::BOLD)
+    } else {
+        Style::default().fg(Color::DarkGray)
+    };
+    let value_style = if focused {
+        Style::default()
+            .fg(Color::White)
+            .add_modifier(Modifier::BOLD)
+    } else {
+        Style::default()
+    };
+
+    let mut spans = vec![
+        Span::styled(" Merged:  ", label_style),
+        Span::styled(merged.pattern.clone(), value_style),
+        Span::styled(
+            format!("  (covers {} pending)", merged.entry_ids.len()),
+            Style::default().fg(Color::DarkGray),
+        ),
+    ];
+    if let Some(sample) = merged.also_allows.first() {
+        spans.push(Span::styled(
+            format!("  also allows: {sample}"),
+            Style::default().fg(Color::Yellow),
+        ));
+    }
+    f.render_widget(Paragraph::new(Line::from(spans)), area);
+}
+
+fn draw_preview_row(f: &mut Frame, preview: &[&PreviewMatch], area: Rect) {
+    let warnings = preview.iter().filter(|m| m.warning).count();
+    let mut spans = vec![
+        Span::styled(" Allows:  ", Style::default().fg(Color::DarkGray)),
+        Span::styled(
+            format!("{} other command(s)", preview.len()),
+            Style::default().fg(Color::White),
+        ),
+    ];
+    if let Some(first) = preview.iter().find(|m| m.warning) {
+        spans.push(Span::styled(
+            format!("  ! {warnings} high-risk: {}", first.command),
+            Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+        ));
+    }
+    f.render_widget(Paragraph::new(Line::from(spans)), area);
+}
+
+fn draw_preview_list(f: &mut Frame, preview: &[&PreviewMatch], area: Rect) {
+    let height = area.height as usize;
+    if height == 0 || preview.is_empty() {
+        return;
+    }
+
+    // High-risk first, and leave a line for the overflow count
+    let mut sorted: Vec<&PreviewMatch> = preview.to_vec();
+    sorted.sort_by_key(|m| !m.warning);
+    let shown = if sorted.len() > height {
+        height - 1
+    } else {
+        sorted.len()
+    };
+
+    let mut lines: Vec<Line> = sorted[..shown]
+        .iter()
+        .map(|m| {
+            let (marker, color) = match (m.decision, m.warning) {
+                (Decision::Block, _) => ("x", Color::Red),
+                (_, true) => ("!", Color::Red),
+                _ => (" ", Color::Gray),
+            };
+            Line::from(vec![
+                Span::styled(format!("   {marker} "), Style::default().fg(color)),
+                Span::styled(m.command.clone(), Style::default().fg(color)),
+                Span::styled(
+                    format!("  ({})", m.source.as_str()),
+                    Style::default().fg(Color::DarkGray),
+                ),
+            ])
+        })
+        .collect();
+    if shown < sorted.len() {
+        lines.push(Line::from(Span::styled(
+            format!(
+                "     +{} more (tool-gates approve '<pattern>' --preview)",
+                sorted.len() - shown
+            ),
+            Style::default().fg(Color::DarkGray),
+        )));
+    }
+    f.render_widget(Paragraph::new(lines), area);
+}
+
 fn draw_scope_row(f: &mut Frame, app: &App, area: Rect) {
     let focused = app.panel == Panel::Detail && app.detail_row == DetailRow::Scope;
 
     let scope_text = match app.scope {
-        Scope::User => "Global (all projects)",
-        Scope::Project => "This project (shared)",
-        Scope::Local => "This project (local only)",
+        ApprovalScope::Settings(Scope::User) => "Global (all projects)",
+        ApprovalScope::Settings(Scope::Project) => "This project (shared)",
+        ApprovalScope::Settings(Scope::Local) => "This project (local only)",
+        ApprovalScope::Session => "This session (temporary)",
     };
 
     let label_style = if focused {
@@ -523,7 +652,11 @@ fn draw_actions(f: &mut Frame, app: &App, area: Rect) {
     let focused = app.panel == Panel::Detail;
     let has_multi = !app.multi_selected.is_empty();
 
-    let approve_text = if has_multi {
+    let merged_focused = focused && app.detail_row == DetailRow::Merged;
+    let approve_text = if merged_focused {
+        let count = app.current_merged().map_or(0, |m| m.entry_ids.len());
+        format!(" Enter: Approve {count} ")
+    } else if has_multi {
         format!(" Enter: Approve {} ", app.multi_selected.len())
     } else {
         " Enter: Approve ".to_string()
@@ -606,13 +739,21 @@ fn draw_footer(f: &mut Frame, app: &App, area: Rect) {
     spans.push(Span::styled("Enter", Style::default().fg(Color::Green)));
     spans.push(Span::raw(" approve  "));
     spans.push(Span::styled("d", Style::default().fg(Color::Yellow)));
-    spans.push(Span::raw(" skip  "));
+    spans.push(Span::raw(match app.view {
+        View::Pending => " skip  ",
+        View::Declined => " dismiss  ",
+    }));
 
     if !has_multi {
         spans.push(Span::styled("D", Style::default().fg(Color::Red)));
         spans.push(Span::raw(" deny  "));
     }
 
+    spans.push(Span::styled("v", Style::default().fg(Color::Yellow)));
+    spans.push(Span::raw(match app.view {
+        View::Pending => " declined  ",
+        View::Declined => " pending  ",
+    }));
     spans.push(Span::styled("q", Style::default().fg(Color::Yellow)));
     spans.push(Span::raw(" quit"));
 
@@ -0,0 +1,1178 @@
+//! Runtime-loaded rule files.
+//!
+//! Built-in gates are compiled from `rules/*.toml` by `build.rs`. Rule files
+//! with the same schema are also read at runtime from:
+//!
+//! - `~/.config/tool-gates/rules/*.toml` (user)
+//! - `.tool-gates/rules/*.toml`, found walking up from cwd (project)
+//!
+//! They are evaluated by [`ProgramRules::check`], an interpreter that mirrors the
+//! generated `check_*_declarative` functions, after every built-in gate has run:
+//!
+//! ```toml
+//! [[programs]]
+//! name = "deployctl"
+//! unknown_action = "ask"
+//!
+//! [[programs.allow]]
+//! subcommand = "status"
+//!
+//! [[programs.block]]
+//! subcommand = "prod destroy"
+//! reason = "Destroys production"
+//! ```
+//!
+//! A program that also has built-in rules is extended by default: when none of
+//! the file's rules match, the built-in result stands. With `mode = "override"`
+//! the file's `unknown_action` applies instead. Either way, a built-in block is
+//! never loosened.
+//!
+//! Runtime files support `[meta]`, `[[programs]]` and `safe_commands`.
+//! `custom_handlers`, `conditional_allow` and `wrappers` need Rust code or
+//! codegen and are rejected.
+//!
+//! Project rule files are trust-gated like `.tool-gates.toml`: until
+//! `tool-gates trust` records their hash, only their ask and block results apply.
+
+use crate::config;
+use crate::models::{CommandInfo, Decision, GateResult};
+use crate::repo_policy::{Trust, TrustStore};
+use crate::trace::{self, TraceEvent};
+use serde::Deserialize;
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Project rule directory, relative to the directory it's found in.
+pub const PROJECT_RULES_DIR: &str = ".tool-gates/rules";
+
+thread_local! {
+    static ACTIVE: RefCell<Option<Arc<RuleSet>>> = const { RefCell::new(None) };
+}
+
+// === Schema (mirrors build.rs) ===
+
+/// A runtime rule file.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RuleFile {
+    #[serde(default)]
+    pub meta: RuleMeta,
+    #[serde(default)]
+    pub programs: Vec<ProgramRules>,
+    #[serde(default)]
+    pub safe_commands: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RuleMeta {
+    pub name: Option<String>,
+    pub description: Option<String>,
+    pub priority: Option<u32>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ProgramRules {
+    pub name: String,
+    #[serde(default)]
+    pub aliases: Vec<String>,
+    #[serde(default)]
+    pub allow: Vec<AllowRule>,
+    #[serde(default)]
+    pub ask: Vec<AskRule>,
+    #[serde(default)]
+    pub block: Vec<BlockRule>,
+    #[serde(default)]
+    pub allow_if_flags: Vec<FlagOverride>,
+    #[serde(default)]
+    pub api_rules: Option<ApiRules>,
+    #[serde(default)]
+    pub default_allow: bool,
+    #[serde(default)]
+    pub unknown_action: UnknownAction,
+    /// How this program combines with built-in rules for the same program
+    #[serde(default)]
+    pub mode: RuleMode,
+}
+
+#[derive(Debug, Default, Clone, Copy, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum UnknownAction {
+    #[default]
+    Ask,
+    Allow,
+    Skip,
+    Block,
+}
+
+/// How a runtime program combines with a built-in program of the same name
+#[derive(Debug, Default, Clone, Copy, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum RuleMode {
+    /// Unmatched invocations fall back to the built-in rules
+    #[default]
+    Extend,
+    /// Unmatched invocations use this program's `unknown_action`
+    Override,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AllowRule {
+    #[serde(default)]
+    pub subcommand: Option<String>,
+    #[serde(default)]
+    pub subcommands: Vec<String>,
+    #[serde(default)]
+    pub subcommand_prefix: Option<String>,
+    #[serde(default)]
+    pub action_prefix: Option<String>,
+    #[serde(default)]
+    pub unless_flags: Vec<String>,
+    #[serde(default)]
+    pub unless_args_contain: Vec<String>,
+    #[serde(default)]
+    pub if_flags_any: Vec<String>,
+    #[serde(default)]
+    pub reason: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AskRule {
+    #[serde(default)]
+    pub subcommand: Option<String>,
+    #[serde(default)]
+    pub subcommands: Vec<String>,
+    #[serde(default)]
+    pub subcommand_prefix: Option<String>,
+    #[serde(default)]
+    pub action_prefix: Option<String>,
+    pub reason: String,
+    #[serde(default)]
+    pub warn: bool,
+    #[serde(default)]
+    pub if_flags: Vec<String>,
+    #[serde(default)]
+    pub if_flags_any: Vec<String>,
+    #[serde(default)]
+    pub accept_edits_auto_allow: bool,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BlockRule {
+    #[serde(default)]
+    pub subcommand: Option<String>,
+    #[serde(default)]
+    pub subcommands: Vec<String>,
+    #[serde(default)]
+    pub subcommand_prefix: Option<String>,
+    pub reason: String,
+    #[serde(default)]
+    pub if_args_contain: Vec<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FlagOverride {
+    pub flags_any: Vec<String>,
+    #[serde(default)]
+    pub for_subcommands: Vec<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ApiRules {
+    pub trigger: String,
+    #[serde(default)]
+    pub method_flags: Vec<String>,
+    #[serde(default)]
+    pub safe_methods: Vec<String>,
+    #[serde(default)]
+    pub default_method: Option<String>,
+    #[serde(default)]
+    pub implicit_post_flags: Vec<String>,
+    #[serde(default)]
+    pub read_only_endpoints: Vec<String>,
+}
+
+fn subcommand_parts<'a>(subcommand: &'a Option<String>, subcommands: &'a [String]) -> Vec<&'a str> {
+    if let Some(s) = subcommand {
+        s.split_whitespace().collect()
+    } else {
+        subcommands.iter().map(String::as_str).collect()
+    }
+}
+
+impl AllowRule {
+    fn parts(&self) -> Vec<&str> {
+        subcommand_parts(&self.subcommand, &self.subcommands)
+    }
+
+    /// Matched by the `{NAME}_ALLOW` map lookup in generated code
+    fn is_simple(&self) -> bool {
+        self.subcommand_prefix.is_none()
+            && self.action_prefix.is_none()
+            && self.unless_flags.is_empty()
+            && self.unless_args_contain.is_empty()
+            && self.if_flags_any.is_empty()
+            && self.reason.is_none()
+    }
+
+    fn is_complex(&self) -> bool {
+        self.subcommand_prefix.is_some()
+            || self.action_prefix.is_some()
+            || !self.unless_flags.is_empty()
+            || !self.if_flags_any.is_empty()
+            || self.reason.is_some()
+    }
+}
+
+impl AskRule {
+    fn parts(&self) -> Vec<&str> {
+        subcommand_parts(&self.subcommand, &self.subcommands)
+    }
+
+    fn is_simple(&self) -> bool {
+        self.subcommand_prefix.is_none()
+            && self.action_prefix.is_none()
+            && self.if_flags.is_empty()
+            && self.if_flags_any.is_empty()
+    }
+
+    fn is_complex(&self) -> bool {
+        !self.if_flags_any.is_empty()
+            || self.subcommand_prefix.is_some()
+            || self.action_prefix.is_some()
+    }
+
+    fn is_bare(&self) -> bool {
+        self.parts().is_empty()
+            && self.subcommand_prefix.is_none()
+            && self.action_prefix.is_none()
+            && self.if_flags_any.is_empty()
+    }
+}
+
+impl BlockRule {
+    fn parts(&self) -> Vec<&str> {
+        subcommand_parts(&self.subcommand, &self.subcommands)
+    }
+
+    fn is_bare(&self) -> bool {
+        self.parts().is_empty()
+            && self.subcommand_prefix.is_none()
+            && self.if_args_contain.is_empty()
+    }
+}
+
+// === Interpreter ===
+
+/// Result of evaluating one program's rules
+#[derive(Debug, Clone)]
+pub struct RuleOutcome {
+    pub result: GateResult,
+    /// Rule section that decided (`allow`, `ask`, `block`, `allow_if_flags`,
+    /// `api_rules`, `unknown_action`)
+    pub section: &'static str,
+    pub index: usize,
+}
+
+impl RuleOutcome {
+    fn at(section: &'static str, index: usize, result: GateResult) -> Option<Self> {
+        Some(Self {
+            result,
+            section,
+            index,
+        })
+    }
+
+    /// False when the result came from `unknown_action`
+    pub fn matched(&self) -> bool {
+        self.section != "unknown_action"
+    }
+}
+
+/// Subcommand strings the generated code matches against
+struct Subcommand<'a> {
+    /// First two args joined ("pr list"), or the only arg
+    pair: String,
+    /// First arg
+    single: &'a str,
+}
+
+impl<'a> Subcommand<'a> {
+    fn of(cmd: &'a CommandInfo) -> Self {
+        let pair = match cmd.args.as_slice() {
+            [] => String::new(),
+            [only] => only.clone(),
+            [first, second, ..] => format!("{first} {second}"),
+        };
+        Self {
+            pair,
+            single: cmd.args.first().map_or("", String::as_str),
+        }
+    }
+
+    /// `subcommand_prefix` without subcommand parts: multi-word prefixes match the pair
+    fn starts_with(&self, prefix: &str) -> bool {
+        if prefix.contains(' ') {
+            self.pair.starts_with(prefix)
+        } else {
+            self.single.starts_with(prefix)
+        }
+    }
+}
+
+/// Whether the leading args equal the subcommand parts
+fn subcommand_matches(cmd: &CommandInfo, parts: &[&str]) -> bool {
+    cmd.args.len() >= parts.len() && parts.iter().zip(&cmd.args).all(|(p, a)| p == a)
+}
+
+/// `subcommand_prefix` check, with or without leading subcommand parts
+fn prefix_matches(cmd: &CommandInfo, sub: &Subcommand, parts: &[&str], prefix: &str) -> bool {
+    if parts.is_empty() {
+        sub.starts_with(prefix)
+    } else {
+        subcommand_matches(cmd, parts)
+            && cmd
+                .args
+                .get(parts.len())
+                .is_some_and(|a| a.starts_with(prefix))
+    }
+}
+
+fn has_any_flag(cmd: &CommandInfo, flags: &[String]) -> bool {
+    cmd.args.iter().any(|a| flags.contains(a))
+}
+
+fn action_matches(cmd: &CommandInfo, prefix: &str) -> bool {
+    cmd.args.get(1).is_some_and(|a| a.starts_with(prefix))
+}
+
+impl ProgramRules {
+    /// Whether this program handles the command (name or alias)
+    pub fn handles(&self, cmd: &CommandInfo) -> bool {
+        self.name == cmd.program || self.aliases.contains(&cmd.program)
+    }
+
+    /// Evaluate a command, in the same order as the generated `check_*_declarative`.
+    pub fn check(&self, cmd: &CommandInfo) -> Option<RuleOutcome> {
+        if !self.handles(cmd) {
+            return None;
+        }
+        let name = &self.name;
+
+        for (index, flag_override) in self.allow_if_flags.iter().enumerate() {
+            if has_any_flag(cmd, &flag_override.flags_any) {
+                return RuleOutcome::at("allow_if_flags", index, GateResult::allow());
+            }
+        }
+
+        if let Some((index, block)) = self.block.iter().enumerate().find(|(_, r)| r.is_bare()) {
+            return RuleOutcome::at(
+                "block",
+                index,
+                GateResult::block(format!("{name}: {}", block.reason)),
+            );
+        }
+
+        let sub = Subcommand::of(cmd);
+
+        // Simple blocks: exact lookup on the first two args
+        for (index, block) in self.block.iter().enumerate() {
+            if block.if_args_contain.is_empty()
+                && block.parts().join(" ") == sub.pair
+                && !sub.pair.is_empty()
+            {
+                return RuleOutcome::at(
+                    "block",
+                    index,
+                    GateResult::block(format!("{name}: {}", block.reason)),
+                );
+            }
+        }
+
+        // Blocks with a prefix or argument condition
+        for (index, block) in self.block.iter().enumerate() {
+            let parts = block.parts();
+            let hit = if let Some(prefix) = &block.subcommand_prefix {
+                prefix_matches(cmd, &sub, &parts, prefix)
+            } else if !block.if_args_contain.is_empty() && !parts.is_empty() {
+                subcommand_matches(cmd, &parts) && has_any_flag(cmd, &block.if_args_contain)
+            } else {
+                false
+            };
+            if hit {
+                return RuleOutcome::at(
+                    "block",
+                    index,
+                    GateResult::block(format!("{name}: {}", block.reason)),
+                );
+            }
+        }
+
+        // Asks with a flag or prefix condition
+        for (index, ask) in self.ask.iter().enumerate().filter(|(_, r)| r.is_complex()) {
+            let parts = ask.parts();
+            if !ask.if_flags_any.is_empty()
+                && subcommand_matches(cmd, &parts)
+                && has_any_flag(cmd, &ask.if_flags_any)
+            {
+                return RuleOutcome::at("ask", index, GateResult::ask(ask.reason.clone()));
+            }
+            let prefix_hit = ask
+                .subcommand_prefix
+                .as_deref()
+                .is_some_and(|prefix| prefix_matches(cmd, &sub, &parts, prefix));
+            let action_hit = ask
+                .action_prefix
+                .as_deref()
+                .is_some_and(|prefix| action_matches(cmd, prefix));
+            if prefix_hit || action_hit {
+                return RuleOutcome::at(
+                    "ask",
+                    index,
+                    GateResult::ask(format!("{name}: {}", ask.reason)),
+                );
+            }
+        }
+
+        // Simple allows: exact lookup on the first two args, then the first arg
+        for key in [sub.pair.as_str(), sub.single] {
+            if let Some((index, _)) =
+                self.allow.iter().enumerate().find(|(_, r)| {
+                    r.is_simple() && !r.parts().is_empty() && r.parts().join(" ") == key
+                })
+            {
+                return RuleOutcome::at("allow", index, GateResult::allow());
+            }
+        }
+
+        // Allows with conditions or a reason
+        for (index, allow) in self
+            .allow
+            .iter()
+            .enumerate()
+            .filter(|(_, r)| r.is_complex())
+        {
+            let parts = allow.parts();
+            let hit = allow
+                .subcommand_prefix
+                .as_deref()
+                .is_some_and(|prefix| prefix_matches(cmd, &sub, &parts, prefix))
+                || allow
+                    .action_prefix
+                    .as_deref()
+                    .is_some_and(|prefix| action_matches(cmd, prefix))
+                || (!allow.unless_flags.is_empty()
+                    && !parts.is_empty()
+                    && subcommand_matches(cmd, &parts)
+                    && !has_any_flag(cmd, &allow.unless_flags))
+                || (!allow.if_flags_any.is_empty()
+                    && subcommand_matches(cmd, &parts)
+                    && has_any_flag(cmd, &allow.if_flags_any))
+                || (allow.reason.is_some()
+                    && allow.subcommand_prefix.is_none()
+                    && allow.action_prefix.is_none()
+                    && allow.unless_flags.is_empty()
+                    && allow.if_flags_any.is_empty()
+                    && !parts.is_empty()
+                    && subcommand_matches(cmd, &parts));
+            if hit {
+                return RuleOutcome::at(
+                    "allow",
+                    index,
+                    match &allow.reason {
+                        Some(reason) => GateResult::allow_with_reason(reason.clone()),
+                        None => GateResult::allow(),
+                    },
+                );
+            }
+        }
+
+        // Simple asks: exact lookup on the first two args, then the first arg
+        for key in [sub.pair.as_str(), sub.single] {
+            if let Some((index, ask)) =
+                self.ask.iter().enumerate().find(|(_, r)| {
+                    r.is_simple() && !r.parts().is_empty() && r.parts().join(" ") == key
+                })
+            {
+                return RuleOutcome::at(
+                    "ask",
+                    index,
+                    GateResult::ask(format!("{name}: {}", ask.reason)),
+                );
+            }
+        }
+
+        if let Some(api) = &self.api_rules {
+            if sub.single == api.trigger {
+                let method = api.method(cmd);
+                let safe = api.safe_methods.iter().any(|m| m.to_uppercase() == method);
+                return RuleOutcome::at(
+                    "api_rules",
+                    0,
+                    if safe {
+                        GateResult::allow()
+                    } else {
+                        GateResult::ask(format!("{name} {}: {method} request", api.trigger))
+                    },
+                );
+            }
+        }
+
+        if let Some((index, ask)) = self.ask.iter().enumerate().find(|(_, r)| r.is_bare()) {
+            return RuleOutcome::at(
+                "ask",
+                index,
+                GateResult::ask(format!("{name}: {}", ask.reason)),
+            );
+        }
+
+        let result = match self.unknown_action {
+            UnknownAction::Skip => return None,
+            UnknownAction::Ask => GateResult::ask(format!("{name}: {}", sub.single)),
+            UnknownAction::Allow => GateResult::allow(),
+            UnknownAction::Block => {
+                GateResult::block(format!("{name}: unknown subcommand {}", sub.single))
+            }
+        };
+        RuleOutcome::at("unknown_action", 0, result)
+    }
+
+    /// Schema checks `build.rs` applies to built-in files, as errors instead of panics.
+    fn validate(&self) -> Result<(), String> {
+        let name = &self.name;
+        if name.trim().is_empty() {
+            return Err("program has empty name".to_string());
+        }
+
+        let mut allow_keys: HashSet<String> = HashSet::new();
+        for rule in &self.allow {
+            let parts = rule.parts();
+            let key = if !parts.is_empty() {
+                parts.join(" ")
+            } else if rule.subcommand_prefix.is_none()
+                && rule.action_prefix.is_none()
+                && rule.if_flags_any.is_empty()
+            {
+                String::new()
+            } else {
+                continue;
+            };
+            if !allow_keys.insert(key.clone()) {
+                return Err(format!("{name}: duplicate allow rule for '{key}'"));
+            }
+        }
+
+        let mut ask_keys: HashSet<String> = HashSet::new();
+        let mut has_bare_ask = false;
+        for (i, rule) in self.ask.iter().enumerate() {
+            if rule.reason.trim().is_empty() {
+                return Err(format!("{name}: ask[{i}] has empty reason"));
+            }
+            if rule.is_bare() {
+                if has_bare_ask {
+                    return Err(format!("{name}: duplicate bare ask rule"));
+                }
+                has_bare_ask = true;
+            } else if rule.is_simple() && !rule.parts().is_empty() {
+                let key = rule.parts().join(" ");
+                if !ask_keys.insert(key.clone()) {
+                    return Err(format!("{name}: duplicate ask rule for '{key}'"));
+                }
+            }
+        }
+
+        let mut block_keys: HashSet<String> = HashSet::new();
+        let mut has_bare_block = false;
+        for (i, rule) in self.block.iter().enumerate() {
+            if rule.reason.trim().is_empty() {
+                return Err(format!("{name}: block[{i}] has empty reason"));
+            }
+            let parts = rule.parts();
+            if parts.is_empty() && rule.subcommand_prefix.is_none() {
+                if has_bare_block {
+                    return Err(format!("{name}: duplicate bare block rule"));
+                }
+                has_bare_block = true;
+            }
+            if !parts.is_empty() {
+                let key = parts.join(" ");
+                if !block_keys.insert(key.clone()) {
+                    return Err(format!("{name}: duplicate block rule for '{key}'"));
+                }
+                if allow_keys.contains(&key) {
+                    return Err(format!(
+                        "{name}: conflicting rules - '{key}' is in both allow and block"
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl ApiRules {
+    /// HTTP method of an API call, uppercased (empty when unknown)
+    fn method(&self, cmd: &CommandInfo) -> String {
+        let explicit = cmd
+            .args
+            .iter()
+            .position(|a| self.method_flags.contains(a))
+            .and_then(|i| cmd.args.get(i + 1))
+            .map(|s| s.to_uppercase());
+        if let Some(method) = explicit {
+            return method;
+        }
+        let read_only = !self.read_only_endpoints.is_empty()
+            && cmd
+                .args
+                .iter()
+                .skip(1)
+                .find(|a| !a.starts_with('-'))
+                .is_some_and(|e| {
+                    self.read_only_endpoints
+                        .iter()
+                        .any(|p| e.starts_with(p.as_str()))
+                });
+        let implicit_post = cmd.args.iter().any(|a| {
+            self.implicit_post_flags
+                .iter()
+                .any(|f| a == f || a.starts_with(&format!("{f}=")))
+        });
+        if !self.implicit_post_flags.is_empty() && !read_only && implicit_post {
+            return "POST".to_string();
+        }
+        if read_only && !self.implicit_post_flags.is_empty() {
+            return "GET".to_string();
+        }
+        self.default_method
+            .as_deref()
+            .map(str::to_uppercase)
+            .unwrap_or_default()
+    }
+}
+
+impl RuleFile {
+    /// Parse and validate a rule file.
+    pub fn parse(content: &str) -> Result<Self, String> {
+        let file: RuleFile = toml::from_str(content).map_err(|e| {
+            let line = e
+                .span()
+                .map(|span| content[..span.start].lines().count().max(1));
+            match line {
+                Some(line) => format!("parse error at line {line}: {}", e.message()),
+                None => format!("parse error: {}", e.message()),
+            }
+        })?;
+        for (i, cmd) in file.safe_commands.iter().enumerate() {
+            if cmd.trim().is_empty() {
+                return Err(format!("safe_commands[{i}] is empty"));
+            }
+        }
+        for program in &file.programs {
+            program.validate()?;
+        }
+        Ok(file)
+    }
+}
+
+// === Loading ===
+
+/// Where a rule file was loaded from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RuleScope {
+    User,
+    Project(Trust),
+}
+
+/// A loaded rule file
+#[derive(Debug, Clone)]
+pub struct RuleSource {
+    pub path: PathBuf,
+    pub scope: RuleScope,
+    pub rules: RuleFile,
+}
+
+impl RuleSource {
+    /// Whether allow results from this file take effect
+    pub fn can_loosen(&self) -> bool {
+        matches!(
+            self.scope,
+            RuleScope::User | RuleScope::Project(Trust::Trusted)
+        )
+    }
+}
+
+/// Runtime rule files for one working directory, in evaluation order.
+#[derive(Debug, Clone, Default)]
+pub struct RuleSet {
+    pub sources: Vec<RuleSource>,
+    /// Files that failed to load, with the reason
+    pub errors: Vec<(PathBuf, String)>,
+}
+
+/// User rule directory (`~/.config/tool-gates/rules`).
+pub fn user_rules_dir() -> PathBuf {
+    config::config_dir().join("rules")
+}
+
+/// Nearest `.tool-gates/rules` directory at or above `cwd`.
+pub fn project_rules_dir(cwd: &str) -> Option<PathBuf> {
+    if cwd.is_empty() {
+        return None;
+    }
+    Path::new(cwd)
+        .ancestors()
+        .map(|dir| dir.join(PROJECT_RULES_DIR))
+        .find(|dir| dir.is_dir())
+}
+
+/// `*.toml` files in a directory, sorted by name.
+pub fn rule_files_in(dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut files: Vec<PathBuf> = entries
+        .flatten()
+        .map(|e| e.path())
+        .filter(|p| p.extension().is_some_and(|e| e == "toml"))
+        .collect();
+    files.sort();
+    files
+}
+
+impl RuleSet {
+    /// Load user rule files and the project rule files for `cwd`.
+    ///
+    /// Invalid files are skipped with a warning; `tool-gates doctor` lists them.
+    pub fn load(cwd: &str) -> Self {
+        let set = Self::load_from(
+            &user_rules_dir(),
+            project_rules_dir(cwd).as_deref(),
+            &TrustStore::load(),
+        );
+        for (path, error) in &set.errors {
+            eprintln!("tool-gates: warning: {} {error}", path.display());
+        }
+        set
+    }
+
+    /// Load from explicit directories.
+    pub fn load_from(user_dir: &Path, project_dir: Option<&Path>, trust: &TrustStore) -> Self {
+        let mut set = Self::default();
+        let project_files = project_dir.map(rule_files_in).unwrap_or_default();
+        let files = rule_files_in(user_dir)
+            .into_iter()
+            .map(|path| (path, None))
+            .chain(project_files.into_iter().map(|path| (path, Some(()))));
+
+        for (path, project) in files {
+            let content = match fs::read_to_string(&path) {
+                Ok(content) => content,
+                Err(e) => {
+                    set.errors.push((path, format!("read error: {e}")));
+                    continue;
+                }
+            };
+            match RuleFile::parse(&content) {
+                Ok(rules) => {
+                    let scope = match project {
+                        Some(()) => RuleScope::Project(trust.trust_of(
+                            &path,
+                            &crate::repo_policy::hash_contents(content.as_bytes()),
+                        )),
+                        None => RuleScope::User,
+                    };
+                    set.sources.push(RuleSource { path, scope, rules });
+                }
+                Err(e) => set.errors.push((path, e)),
+            }
+        }
+
+        // Lower priority first, like built-in files (stable, so user before project)
+        set.sources
+            .sort_by_key(|s| s.rules.meta.priority.unwrap_or(100));
+        set
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sources.is_empty()
+    }
+
+    /// Combine runtime rules with the built-in gate result for a command.
+    ///
+    /// Returns None when no runtime rule applies. The strictest applicable
+    /// result across files wins.
+    pub fn check(&self, cmd: &CommandInfo, builtin: &GateResult) -> Option<GateResult> {
+        let mut strictest: Option<GateResult> = None;
+
+        for source in &self.sources {
+            let safe = source
+                .rules
+                .safe_commands
+                .iter()
+                .position(|c| *c == cmd.program)
+                .and_then(|index| RuleOutcome::at("safe_commands", index, GateResult::allow()))
+                .map(|outcome| (cmd.program.as_str(), outcome));
+            let programs = source.rules.programs.iter().filter_map(|program| {
+                let outcome = program.check(cmd)?;
+                // Extending a built-in program: unmatched falls back to it
+                let fallback = !outcome.matched()
+                    && program.mode == RuleMode::Extend
+                    && builtin.decision != Decision::Skip;
+                (!fallback).then_some((program.name.as_str(), outcome))
+            });
+
+            for (program, outcome) in safe.into_iter().chain(programs) {
+                let decision = outcome.result.decision;
+                // Untrusted project files can only make the decision stricter
+                if !source.can_loosen()
+                    && (decision < Decision::Ask || decision <= builtin.decision)
+                {
+                    continue;
+                }
+                trace::record(|| TraceEvent::UserRule {
+                    file: source.path.to_string_lossy().to_string(),
+                    program: program.to_string(),
+                    section: outcome.section,
+                    index: outcome.index,
+                });
+                if strictest.as_ref().is_none_or(|s| decision > s.decision) {
+                    strictest = Some(outcome.result);
+                }
+            }
+        }
+
+        strictest
+    }
+}
+
+/// Run `f` with `rules` as the active runtime rules on this thread.
+///
+/// The router consults the active set from every gate check, including
+/// commands re-checked inside wrappers and `bash -c`.
+pub fn with_rules<T>(rules: Arc<RuleSet>, f: impl FnOnce() -> T) -> T {
+    let previous = ACTIVE.with(|active| active.borrow_mut().replace(rules));
+    let result = f();
+    ACTIVE.with(|active| *active.borrow_mut() = previous);
+    result
+}
+
+/// Check a command against the active runtime rules (see [`RuleSet::check`]).
+pub fn check(cmd: &CommandInfo, builtin: &GateResult) -> Option<GateResult> {
+    ACTIVE.with(|active| {
+        let active = active.borrow();
+        let rules = active.as_ref().filter(|rules| !rules.is_empty())?;
+        rules.check(cmd, builtin)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::extract_commands;
+    use tempfile::TempDir;
+
+    fn cmd(command: &str) -> CommandInfo {
+        extract_commands(command).remove(0)
+    }
+
+    fn rules(content: &str) -> RuleSet {
+        RuleSet {
+            sources: vec![RuleSource {
+                path: PathBuf::from("test.toml"),
+                scope: RuleScope::User,
+                rules: RuleFile::parse(content).unwrap(),
+            }],
+            errors: Vec::new(),
+        }
+    }
+
+    const DEPLOYCTL: &str = r#"
+[[programs]]
+name = "deployctl"
+
+[[programs.allow]]
+subcommand = "status"
+
+[[programs.allow]]
+subcommand_prefix = "list-"
+
+[[programs.ask]]
+subcommand = "rollout"
+reason = "Rolls out a release"
+
+[[programs.block]]
+subcommand = "prod destroy"
+reason = "Destroys production"
+"#;
+
+    #[test]
+    fn test_new_program() {
+        let rules = rules(DEPLOYCTL);
+        let skip = GateResult::skip();
+        let decide = |c: &str| rules.check(&cmd(c), &skip).map(|r| r.decision);
+        assert_eq!(decide("deployctl status"), Some(Decision::Allow));
+        assert_eq!(decide("deployctl list-envs"), Some(Decision::Allow));
+        assert_eq!(decide("deployctl rollout v2"), Some(Decision::Ask));
+        assert_eq!(decide("deployctl prod destroy"), Some(Decision::Block));
+        // unknown_action defaults to ask
+        assert_eq!(decide("deployctl frobnicate"), Some(Decision::Ask));
+        assert_eq!(decide("otherctl status"), None);
+    }
+
+    #[test]
+    fn test_extend_and_override_builtin() {
+        let extend = rules(
+            r#"
+[[programs]]
+name = "git"
+
+[[programs.allow]]
+subcommand = "town sync"
+"#,
+        );
+        let builtin_ask = GateResult::ask("git: Pushing");
+        let result = extend.check(&cmd("git town sync"), &builtin_ask).unwrap();
+        assert_eq!(result.decision, Decision::Allow);
+        // Unmatched subcommands keep the built-in result
+        assert!(extend.check(&cmd("git push"), &builtin_ask).is_none());
+
+        let over = rules(
+            r#"
+[[programs]]
+name = "git"
+mode = "override"
+unknown_action = "block"
+"#,
+        );
+        let result = over.check(&cmd("git push"), &builtin_ask).unwrap();
+        assert_eq!(result.decision, Decision::Block);
+    }
+
+    #[test]
+    fn test_untrusted_project_only_tightens() {
+        let mut set = rules(DEPLOYCTL);
+        set.sources[0].scope = RuleScope::Project(Trust::Untrusted);
+        let skip = GateResult::skip();
+        assert!(set.check(&cmd("deployctl status"), &skip).is_none());
+        assert_eq!(
+            set.check(&cmd("deployctl prod destroy"), &skip)
+                .unwrap()
+                .decision,
+            Decision::Block
+        );
+
+        set.sources[0].scope = RuleScope::Project(Trust::Trusted);
+        assert_eq!(
+            set.check(&cmd("deployctl status"), &skip).unwrap().decision,
+            Decision::Allow
+        );
+    }
+
+    #[test]
+    fn test_safe_commands() {
+        let rules = rules("safe_commands = [\"dbtool\"]\n");
+        let result = rules
+            .check(&cmd("dbtool dump"), &GateResult::skip())
+            .unwrap();
+        assert_eq!(result.decision, Decision::Allow);
+    }
+
+    #[test]
+    fn test_invalid_files_reported() {
+        let dir = TempDir::new().unwrap();
+        fs::write(dir.path().join("good.toml"), DEPLOYCTL).unwrap();
+        fs::write(dir.path().join("typo.toml"), "[[program]]\nname = \"x\"\n").unwrap();
+        fs::write(
+            dir.path().join("handler.toml"),
+            "[[custom_handlers]]\nprogram = \"x\"\nhandler = \"check_x\"\n",
+        )
+        .unwrap();
+        fs::write(
+            dir.path().join("dup.toml"),
+            "[[programs]]\nname = \"x\"\n[[programs.allow]]\nsubcommand = \"a\"\n[[programs.block]]\nsubcommand = \"a\"\nreason = \"r\"\n",
+        )
+        .unwrap();
+        fs::write(dir.path().join("notes.txt"), "ignored").unwrap();
+
+        let set = RuleSet::load_from(dir.path(), None, &TrustStore::default());
+        assert_eq!(set.sources.len(), 1);
+        let mut failed: Vec<String> = set
+            .errors
+            .iter()
+            .map(|(p, _)| p.file_name().unwrap().to_string_lossy().to_string())
+            .collect();
+        failed.sort();
+        assert_eq!(failed, ["dup.toml", "handler.toml", "typo.toml"]);
+    }
+
+    #[test]
+    fn test_project_trust() {
+        let dir = TempDir::new().unwrap();
+        let project = dir.path().join("repo");
+        let rules_dir = project.join(PROJECT_RULES_DIR);
+        fs::create_dir_all(&rules_dir).unwrap();
+        let file = rules_dir.join("deploy.toml");
+        fs::write(&file, DEPLOYCTL).unwrap();
+
+        let nested = project.join("src");
+        fs::create_dir_all(&nested).unwrap();
+        assert_eq!(
+            project_rules_dir(&nested.to_string_lossy()),
+            Some(rules_dir.clone())
+        );
+
+        let mut store = TrustStore::default();
+        let user_dir = dir.path().join("user");
+        let set = RuleSet::load_from(&user_dir, Some(&rules_dir), &store);
+        assert_eq!(set.sources[0].scope, RuleScope::Project(Trust::Untrusted));
+
+        store.trust(
+            &file,
+            &crate::repo_policy::hash_contents(DEPLOYCTL.as_bytes()),
+        );
+        let set = RuleSet::load_from(&user_dir, Some(&rules_dir), &store);
+        assert_eq!(set.sources[0].scope, RuleScope::Project(Trust::Trusted));
+    }
+
+    /// Built-in programs from rules/*.toml, parsed with the runtime schema
+    fn builtin_programs() -> Vec<ProgramRules> {
+        let mut files: Vec<(u32, Vec<ProgramRules>)> = rule_files_in(Path::new("rules"))
+            .iter()
+            .map(|path| {
+                let value: toml::Value =
+                    toml::from_str(&fs::read_to_string(path).unwrap()).unwrap();
+                let priority = value
+                    .get("meta")
+                    .and_then(|m| m.get("priority"))
+                    .and_then(|p| p.as_integer())
+                    .unwrap_or(100) as u32;
+                let programs = value
+                    .get("programs")
+                    .cloned()
+                    .map(|p| p.try_into().unwrap())
+                    .unwrap_or_default();
+                (priority, programs)
+            })
+            .collect();
+        files.sort_by_key(|(priority, _)| *priority);
+        files
+            .into_iter()
+            .flat_map(|(_, programs)| programs)
+            .collect()
+    }
+
+    /// Commands exercising each rule of a program
+    fn probes(program: &ProgramRules) -> Vec<String> {
+        let name = &program.name;
+        let mut probes = vec![
+            name.clone(),
+            format!("{name} --help"),
+            format!("{name} zzz"),
+        ];
+        let with = |parts: &[&str], extra: &str| {
+            let mut s = name.clone();
+            for p in parts {
+                s.push(' ');
+                s.push_str(p);
+            }
+            if !extra.is_empty() {
+                s.push(' ');
+                s.push_str(extra);
+            }
+            s
+        };
+        for rule in &program.allow {
+            let parts = rule.parts();
+            probes.push(with(&parts, ""));
+            probes.push(with(&parts, "target"));
+            for flag in rule.unless_flags.iter().chain(&rule.if_flags_any) {
+                probes.push(with(&parts, flag));
+            }
+            if let Some(prefix) = &rule.subcommand_prefix {
+                probes.push(with(&parts, &format!("{prefix}x")));
+            }
+            if let Some(prefix) = &rule.action_prefix {
+                probes.push(with(&["svc"], &format!("{prefix}-x")));
+            }
+        }
+        for rule in &program.ask {
+            let parts = rule.parts();
+            probes.push(with(&parts, ""));
+            for flag in rule.if_flags_any.iter().chain(&rule.if_flags) {
+                probes.push(with(&parts, flag));
+            }
+            if let Some(prefix) = &rule.subcommand_prefix {
+                probes.push(with(&parts, &format!("{prefix}x")));
+            }
+            if let Some(prefix) = &rule.action_prefix {
+                probes.push(with(&["svc"], &format!("{prefix}-x")));
+            }
+        }
+        for rule in &program.block {
+            let parts = rule.parts();
+            probes.push(with(&parts, ""));
+            for arg in &rule.if_args_contain {
+                probes.push(with(&parts, arg));
+            }
+            if let Some(prefix) = &rule.subcommand_prefix {
+                probes.push(with(&parts, &format!("{prefix}x")));
+            }
+        }
+        for flag_override in &program.allow_if_flags {
+            for flag in &flag_override.flags_any {
+                probes.push(with(&["zzz"], flag));
+            }
+        }
+        if let Some(api) = &program.api_rules {
+            probes.push(with(&[&api.trigger], "repos/o/r"));
+            probes.push(with(&[&api.trigger], "search/issues -f q=x"));
+            for flag in &api.method_flags {
+                probes.push(with(&[&api.trigger, flag], "DELETE repos/o/r"));
+                probes.push(with(&[&api.trigger, flag], "get repos/o/r"));
+            }
+            for flag in &api.implicit_post_flags {
+                probes.push(with(&[&api.trigger], &format!("repos/o/r {flag} a=b")));
+            }
+        }
+        probes
+    }
+
+    #[test]
+    fn test_interpreter_matches_generated_code() {
+        use crate::generated::rules::{
+            check_conditional_allow, check_declarative, check_safe_command,
+        };
+
+        let programs = builtin_programs();
+        assert!(programs.len() > 50, "expected built-in programs");
+        let mut checked = 0;
+        for program in &programs {
+            for probe in probes(program) {
+                let cmd = cmd(&probe);
+                if check_safe_command(&cmd).is_some() || check_conditional_allow(&cmd).is_some() {
+                    continue;
+                }
+                // First program handling the command decides, as in check_declarative
+                let interpreted = programs
+                    .iter()
+                    .filter(|p| p.handles(&cmd))
+                    .find_map(|p| p.check(&cmd))
+                    .map(|o| (o.result.decision, o.result.reason));
+                let generated = check_declarative(&cmd).map(|r| (r.decision, r.reason));
+                assert_eq!(interpreted, generated, "mismatch for `{probe}`");
+                checked += 1;
+            }
+        }
+        assert!(checked > 500, "only {checked} probes checked");
+    }
+}
@@ -0,0 +1,453 @@
+//! Working-directory tracking across the segments of a command.
+//!
+//! `cd /etc && sed -i ... passwd` edits `/etc/passwd`, not a file in the hook's
+//! cwd. The router walks the parsed segments with [`Workdir`] so path checks,
+//! package.json / mise lookup and settings loading see the directory each
+//! segment actually runs in.
+//!
+//! Tracking is sequential over the flattened segment list, using each
+//! segment's [`Flow`] to decide how far a `cd` reaches. A top-level `cd`
+//! persists. One in an `&&` chain holds for the rest of the chain, but the
+//! chain may have stopped before it, so afterwards the directory is unknown.
+//! A `cd` behind `||`, in a subshell, pipeline, branch, loop or background
+//! job may or may not have run, so it makes the directory unknown.
+
+use crate::generated::rules::wrapper_spec;
+use crate::models::{CommandInfo, Flow};
+use std::ops::Range;
+use std::path::{Component, Path, PathBuf};
+
+/// Per-command flags that run the command in another directory.
+///
+/// (program, flags, flag value is a file inside the directory)
+const DIRECTORY_FLAGS: &[(&str, &[&str], bool)] = &[
+    ("git", &["-C"], false),
+    ("make", &["-C", "--directory"], false),
+    ("gmake", &["-C", "--directory"], false),
+    ("npm", &["--prefix"], false),
+    ("pnpm", &["-C", "--dir"], false),
+    ("yarn", &["--cwd"], false),
+    ("bun", &["--cwd"], false),
+    ("cargo", &["--manifest-path"], true),
+    ("mise", &["-C", "--cd"], false),
+    ("env", &["-C", "--chdir"], false),
+];
+
+/// Shell working directory while walking a command's segments.
+///
+/// `None` means the directory can't be known statically (`cd $DIR`, `pushd +1`).
+#[derive(Debug, Clone)]
+pub struct Workdir {
+    cwd: Option<PathBuf>,
+    previous: Option<PathBuf>,
+    stack: Vec<Option<PathBuf>>,
+    /// The stack may hold entries below `stack` that weren't tracked
+    stack_unknown: bool,
+    /// `&&` chain whose directory change is in effect
+    chain: Option<usize>,
+}
+
+impl Workdir {
+    pub fn new(cwd: &str) -> Self {
+        Self {
+            cwd: Some(PathBuf::from(cwd)),
+            previous: None,
+            stack: Vec::new(),
+            stack_unknown: false,
+            chain: None,
+        }
+    }
+
+    /// Note that `cmd` runs next. Leaving the `&&` chain a `cd` ran in makes
+    /// the directory unknown, since the chain may have stopped before the `cd`.
+    pub fn enter(&mut self, cmd: &CommandInfo) {
+        if self.chain.is_some() && self.chain != cmd.flow.chain() {
+            self.chain = None;
+            self.forget();
+        }
+    }
+
+    /// Current directory, if known.
+    pub fn current(&self) -> Option<&Path> {
+        self.cwd.as_deref()
+    }
+
+    /// Apply a `cd`, `pushd` or `popd`. Returns false for any other command.
+    pub fn apply(&mut self, cmd: &CommandInfo) -> bool {
+        if !is_navigation(cmd) {
+            return false;
+        }
+        match cmd.flow {
+            Flow::Sequential => {}
+            Flow::Chain(id) => self.chain = Some(id),
+            // May not run, or runs in a subshell
+            Flow::Piped(_) | Flow::Nested => {
+                self.forget();
+                return true;
+            }
+        }
+
+        let operands: Vec<&str> = cmd
+            .args
+            .iter()
+            .map(String::as_str)
+            .filter(|a| !matches!(*a, "-L" | "-P" | "-e" | "-@" | "--" | "-n"))
+            .collect();
+
+        match cmd.program.as_str() {
+            "cd" => {
+                let target = match operands.first() {
+                    Some(&"-") => self.previous.clone(),
+                    Some(target) => self.resolve(target),
+                    None => home(),
+                };
+                self.change_to(target);
+            }
+            "pushd" => match operands.first() {
+                // Rotating the stack by index isn't tracked
+                Some(arg) if is_stack_index(arg) => {
+                    self.stack.push(self.cwd.take());
+                }
+                Some(target) => {
+                    let target = self.resolve(target);
+                    self.stack.push(self.cwd.clone());
+                    self.change_to(target);
+                }
+                // Bare pushd swaps the top two entries
+                None => {
+                    if let Some(top) = self.pop_stack() {
+                        self.stack.push(self.cwd.clone());
+                        self.change_to(top);
+                    }
+                }
+            },
+            "popd" => match operands.first() {
+                Some(arg) if is_stack_index(arg) => {
+                    // Removes an entry without changing directory (except +0)
+                    if self.pop_stack().is_none() || *arg == "+0" {
+                        self.change_to(None);
+                    }
+                }
+                _ => {
+                    if let Some(top) = self.pop_stack() {
+                        self.change_to(top);
+                    }
+                }
+            },
+            _ => return false,
+        }
+        true
+    }
+
+    /// Directory a command runs in, honoring flags like `git -C` and `npm --prefix`.
+    pub fn dir_for(&self, cmd: &CommandInfo) -> Option<PathBuf> {
+        match directory_flag(cmd) {
+            Some(dir) => self.resolve(&dir),
+            None => self.cwd.clone(),
+        }
+    }
+
+    /// The directory (and the `cd -` / stack history) can't be known anymore
+    fn forget(&mut self) {
+        self.cwd = None;
+        self.previous = None;
+        self.stack.iter_mut().for_each(|entry| *entry = None);
+        self.stack_unknown = true;
+    }
+
+    /// Pop the directory stack. `Some(None)` is an entry that isn't known.
+    fn pop_stack(&mut self) -> Option<Option<PathBuf>> {
+        match self.stack.pop() {
+            None if self.stack_unknown => Some(None),
+            top => top,
+        }
+    }
+
+    fn change_to(&mut self, target: Option<PathBuf>) {
+        self.previous = std::mem::replace(&mut self.cwd, target);
+    }
+
+    /// Resolve a `cd` target against the current directory.
+    fn resolve(&self, target: &str) -> Option<PathBuf> {
+        if target.contains('$') || target.contains('`') {
+            return None;
+        }
+        if target == "~" {
+            return home();
+        }
+        if let Some(rest) = target.strip_prefix("~/") {
+            return home().map(|h| normalize(&h.join(rest)));
+        }
+        if target.starts_with('~') {
+            // ~user - another user's home
+            return None;
+        }
+        if target.starts_with('/') {
+            return Some(normalize(Path::new(target)));
+        }
+        self.cwd.as_ref().map(|cwd| normalize(&cwd.join(target)))
+    }
+}
+
+/// Directory each segment runs in, in segment order.
+///
+/// `cd`/`pushd`/`popd` segments get the directory they were run from.
+pub fn segment_dirs(commands: &[CommandInfo], cwd: &str) -> Vec<Option<String>> {
+    let mut workdir = Workdir::new(cwd);
+    commands
+        .iter()
+        .map(|cmd| {
+            workdir.enter(cmd);
+            let dir = workdir.dir_for(cmd);
+            workdir.apply(cmd);
+            dir.map(|d| d.to_string_lossy().to_string())
+        })
+        .collect()
+}
+
+/// Whether a command only changes directory
+pub fn is_navigation(cmd: &CommandInfo) -> bool {
+    matches!(cmd.program.as_str(), "cd" | "pushd" | "popd")
+}
+
+/// Directory given by a per-command flag (`git -C dir`, `cargo --manifest-path dir/Cargo.toml`).
+pub fn directory_flag(cmd: &CommandInfo) -> Option<String> {
+    let flag = find_directory_flag(cmd)?;
+    if !flag.is_file {
+        return Some(flag.value);
+    }
+    match Path::new(&flag.value).parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => {
+            Some(parent.to_string_lossy().to_string())
+        }
+        _ => Some(".".to_string()),
+    }
+}
+
+/// The command with its directory flag removed (`npm --prefix web run build` -> `npm run build`).
+pub fn without_directory_flag(cmd: &CommandInfo) -> CommandInfo {
+    let Some(flag) = find_directory_flag(cmd) else {
+        return cmd.clone();
+    };
+    let mut args = cmd.args.clone();
+    args.drain(flag.args);
+    let raw = std::iter::once(cmd.program.as_str())
+        .chain(args.iter().map(String::as_str))
+        .collect::<Vec<_>>()
+        .join(" ");
+    CommandInfo {
+        raw,
+        program: cmd.program.clone(),
+        args,
+        redirects: cmd.redirects.clone(),
+        flow: cmd.flow,
+    }
+}
+
+/// A directory flag found in a command's args
+struct DirectoryFlag {
+    /// Args occupied by the flag(s) and value(s)
+    args: Range<usize>,
+    value: String,
+    /// Value names a file inside the directory (`--manifest-path`)
+    is_file: bool,
+}
+
+/// Locate a directory flag.
+///
+/// Only global options before the subcommand count. Repeated `git -C` flags
+/// combine, each relative to the previous one.
+fn find_directory_flag(cmd: &CommandInfo) -> Option<DirectoryFlag> {
+    let program = cmd.program.rsplit('/').next().unwrap_or(&cmd.program);
+    let (_, flags, is_file) = DIRECTORY_FLAGS.iter().find(|(p, _, _)| *p == program)?;
+    // A wrapper's other flags can take a value (`env -u NAME -C dir`)
+    let value_flags = wrapper_spec(cmd).map_or(&[][..], |spec| spec.flags_with_value);
+
+    let mut found: Option<(Range<usize>, PathBuf)> = None;
+    let mut i = 0;
+    while i < cmd.args.len() {
+        let arg = &cmd.args[i];
+        let value = flags.iter().find_map(|flag| {
+            if arg == flag {
+                cmd.args.get(i + 1).map(|v| (v.clone(), 2))
+            } else if let Some(v) = arg.strip_prefix(&format!("{flag}=")) {
+                Some((v.to_string(), 1))
+            } else if flag.len() == 2 && arg.starts_with(flag) && arg.len() > 2 {
+                // Attached short form: -Cdir
+                Some((arg[2..].to_string(), 1))
+            } else {
+                None
+            }
+        });
+
+        match value {
+            Some((value, width)) => {
+                let dir = match &found {
+                    Some((_, prev)) if !value.starts_with('/') => prev.join(&value),
+                    _ => PathBuf::from(&value),
+                };
+                let start = found.as_ref().map_or(i, |(range, _)| range.start);
+                found = Some((start..i + width, dir));
+                i += width;
+            }
+            // cargo's --manifest-path can come after the subcommand
+            None if !arg.starts_with('-') && !is_file => break,
+            None if value_flags.contains(&arg.as_str()) => i += 2,
+            None => i += 1,
+        }
+    }
+
+    let (args, dir) = found?;
+    Some(DirectoryFlag {
+        args,
+        value: dir.to_string_lossy().to_string(),
+        is_file: *is_file,
+    })
+}
+
+/// `+N` / `-N` directory stack index
+fn is_stack_index(arg: &str) -> bool {
+    arg.len() > 1
+        && (arg.starts_with('+') || arg.starts_with('-'))
+        && arg[1..].chars().all(|c| c.is_ascii_digit())
+}
+
+fn home() -> Option<PathBuf> {
+    dirs::home_dir()
+}
+
+/// Lexically resolve `.` and `..` (no symlink resolution).
+fn normalize(path: &Path) -> PathBuf {
+    let mut out = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::ParentDir => {
+                out.pop();
+            }
+            Component::CurDir => {}
+            other => out.push(other),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::extract_commands;
+
+    fn dirs(command: &str) -> Vec<Option<String>> {
+        segment_dirs(&extract_commands(command), "/project")
+    }
+
+    fn dir(d: &str) -> Option<String> {
+        Some(d.to_string())
+    }
+
+    #[test]
+    fn test_cd_changes_following_segments() {
+        assert_eq!(
+            dirs("cd /etc && sed -i s/a/b/ passwd"),
+            vec![dir("/project"), dir("/etc")]
+        );
+        assert_eq!(
+            dirs("cd src && cd ../docs && ls"),
+            vec![dir("/project"), dir("/project/src"), dir("/project/docs")]
+        );
+    }
+
+    #[test]
+    fn test_cd_dash_and_home() {
+        let home = dirs::home_dir().unwrap().to_string_lossy().to_string();
+        assert_eq!(
+            dirs("cd /tmp && cd - && ls"),
+            vec![dir("/project"), dir("/tmp"), dir("/project")]
+        );
+        assert_eq!(dirs("cd && ls")[1], Some(home.clone()));
+        assert_eq!(dirs("cd ~ && ls")[1], Some(home.clone()));
+        assert_eq!(dirs("cd ~/code && ls")[1], Some(format!("{home}/code")));
+    }
+
+    #[test]
+    fn test_pushd_popd() {
+        assert_eq!(
+            dirs("pushd /tmp && ls && popd && ls"),
+            vec![dir("/project"), dir("/tmp"), dir("/tmp"), dir("/project")]
+        );
+        assert_eq!(dirs("pushd /a && pushd /b && pushd && ls")[3], dir("/a"));
+    }
+
+    #[test]
+    fn test_unknown_directory() {
+        assert_eq!(dirs("cd $DIR && ls")[1], None);
+        assert_eq!(dirs("cd $DIR && cd /tmp && ls")[2], dir("/tmp"));
+        assert_eq!(dirs("pushd +1 && ls")[1], None);
+    }
+
+    #[test]
+    fn test_conditional_cd_makes_directory_unknown() {
+        // The subshell's cd doesn't persist, and the one after `||` may not run
+        assert_eq!(
+            dirs("cd /etc && (cd /project/sub) && sed -i s/a/b/ hosts")[2],
+            None
+        );
+        assert_eq!(dirs("cd /etc || cd /project; sed -i s/a/b/ hosts")[2], None);
+        assert_eq!(dirs("cd /tmp | cat; ls")[2], None);
+        assert_eq!(dirs("if true; then cd /tmp; fi; ls")[2], None);
+    }
+
+    #[test]
+    fn test_chain_cd_unknown_after_chain() {
+        // `false && cd /tmp` stops before the cd, so ls runs in /project
+        assert_eq!(
+            dirs("false && cd /tmp && ls; ls"),
+            vec![dir("/project"), dir("/project"), dir("/tmp"), None]
+        );
+        assert_eq!(dirs("cd /tmp; ls")[1], dir("/tmp"));
+        assert_eq!(dirs("cd /tmp && ls | head")[2], dir("/tmp"));
+    }
+
+    #[test]
+    fn test_directory_flags() {
+        assert_eq!(dirs("git -C /repo status"), vec![dir("/repo")]);
+        assert_eq!(dirs("git -C a -C b status"), vec![dir("/project/a/b")]);
+        assert_eq!(dirs("make -C build all"), vec![dir("/project/build")]);
+        assert_eq!(dirs("make --directory=build"), vec![dir("/project/build")]);
+        assert_eq!(
+            dirs("npm --prefix web run build"),
+            vec![dir("/project/web")]
+        );
+        assert_eq!(
+            dirs("cargo build --manifest-path crates/core/Cargo.toml"),
+            vec![dir("/project/crates/core")]
+        );
+        assert_eq!(
+            dirs("cd /srv && git -C app pull"),
+            vec![dir("/project"), dir("/srv/app")]
+        );
+        assert_eq!(dirs("env -C /etc sed -i s/a/b/ hosts"), vec![dir("/etc")]);
+        assert_eq!(
+            dirs("env -u HOME --chdir=sub make"),
+            vec![dir("/project/sub")]
+        );
+        // Options end at the first assignment or command
+        assert_eq!(dirs("env FOO=1 -C /etc"), vec![dir("/project")]);
+    }
+
+    #[test]
+    fn test_flag_after_subcommand_ignored() {
+        // `git log -C` is copy detection, not a directory
+        assert_eq!(dirs("git log -C"), vec![dir("/project")]);
+    }
+
+    #[test]
+    fn test_without_directory_flag() {
+        let cmd = &extract_commands("npm --prefix web run build")[0];
+        let stripped = without_directory_flag(cmd);
+        assert_eq!(stripped.raw, "npm run build");
+        assert_eq!(stripped.args, vec!["run", "build"]);
+
+        let cmd = &extract_commands("pnpm -C web lint")[0];
+        assert_eq!(without_directory_flag(cmd).raw, "pnpm lint");
+    }
+}